@@ -3,10 +3,34 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::Row;
+use sqlx::{PgPool, Row};
 
 use crate::{auth::create_jwt, state::AppState};
 
+/// Lifetime of a freshly-minted refresh token before it must be renewed
+/// through `/auth/refresh` again.
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Persists a freshly-generated refresh token (hashed) for `user_id` and
+/// returns the raw token to hand back to the client.
+async fn issue_refresh_token(pool: &PgPool, user_id: &str) -> Result<String, sqlx::Error> {
+    let (token, token_hash) = crate::auth::generate_refresh_token();
+    let id = uuid::Uuid::new_v4().to_string();
+    let expires_at = (chrono::Utc::now() + chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS)).naive_utc();
+
+    sqlx::query(
+        "INSERT INTO refresh_tokens (id, user_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(&id)
+    .bind(user_id)
+    .bind(&token_hash)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(token)
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub username: String,
@@ -16,6 +40,7 @@ pub struct LoginRequest {
 #[derive(Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user_id: String,
     pub username: String,
 }
@@ -63,16 +88,27 @@ pub async fn login(
                     .into_response();
             }
 
-            // Génération du JWT
+            // Génération du JWT et d'un refresh token persisté
             match create_jwt(user_id.clone(), username.clone()) {
-                Ok(token) => {
-                    let response = LoginResponse {
-                        token,
-                        user_id,
-                        username,
-                    };
-                    (StatusCode::OK, Json(response)).into_response()
-                }
+                Ok(token) => match issue_refresh_token(pool, &user_id).await {
+                    Ok(refresh_token) => {
+                        let response = LoginResponse {
+                            token,
+                            refresh_token,
+                            user_id,
+                            username,
+                        };
+                        (StatusCode::OK, Json(response)).into_response()
+                    }
+                    Err(e) => {
+                        tracing::error!("Erreur persistance refresh token: {:?}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(json!({"error": "Token generation failed"})),
+                        )
+                            .into_response()
+                    }
+                },
                 Err(e) => {
                     tracing::error!("Erreur JWT: {}", e);
                     (
@@ -197,3 +233,115 @@ pub async fn register(
         }
     }
 }
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// POST /auth/refresh
+///
+/// Redeems a persisted, single-use refresh token for a new access JWT.
+/// The presented token is deleted before a replacement is issued (rotation),
+/// so a stolen-and-replayed refresh token is rejected on its second use.
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> impl IntoResponse {
+    let pool = state.db.as_ref().expect("DB non initialisée");
+
+    let token_hash = crate::auth::hash_refresh_token(&payload.refresh_token);
+
+    let row = sqlx::query(
+        "SELECT id, user_id FROM refresh_tokens WHERE token_hash = $1 AND expires_at > NOW()",
+    )
+    .bind(&token_hash)
+    .fetch_optional(pool)
+    .await;
+
+    let (token_id, user_id): (String, String) = match row {
+        Ok(Some(row)) => (row.get("id"), row.get("user_id")),
+        Ok(None) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({"error": "Invalid or expired refresh token"})),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Erreur DB: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            )
+                .into_response();
+        }
+    };
+
+    // Rotation: burn the redeemed token before minting its replacement.
+    if let Err(e) = sqlx::query("DELETE FROM refresh_tokens WHERE id = $1")
+        .bind(&token_id)
+        .execute(pool)
+        .await
+    {
+        tracing::error!("Erreur DB delete: {:?}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Database error"})),
+        )
+            .into_response();
+    }
+
+    let username: String = match sqlx::query("SELECT username FROM users WHERE id = $1")
+        .bind(&user_id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(row)) => row.get("username"),
+        Ok(None) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({"error": "User no longer exists"})),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Erreur DB: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database error"})),
+            )
+                .into_response();
+        }
+    };
+
+    match create_jwt(user_id.clone(), username.clone()) {
+        Ok(token) => match issue_refresh_token(pool, &user_id).await {
+            Ok(refresh_token) => {
+                let response = LoginResponse {
+                    token,
+                    refresh_token,
+                    user_id,
+                    username,
+                };
+                (StatusCode::OK, Json(response)).into_response()
+            }
+            Err(e) => {
+                tracing::error!("Erreur persistance refresh token: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "Token generation failed"})),
+                )
+                    .into_response()
+            }
+        },
+        Err(e) => {
+            tracing::error!("Erreur JWT: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Token generation failed"})),
+            )
+                .into_response()
+        }
+    }
+}
@@ -1,13 +1,52 @@
 use axum::{extract::{State, Query}, http::StatusCode, response::IntoResponse, Json};
+use chrono::Datelike;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::Row;
+use vpn_core::credits::{draw_from_buckets, CreditBucket, ProtocolPricing, SpendPriority};
+use vpn_core::protocol::VpnProtocol;
 
-use crate::{auth::AuthUser, state::AppState};
+use crate::{auth::{AuthUser, ClientAddr, RateLimited}, state::AppState};
 
 #[derive(Serialize)]
 pub struct BalanceResponse {
     pub credits: i64,
+    pub earned_credits: i64,
+    pub premium_credits: i64,
+    pub total_requests: i64,
+    pub period_earned: i64,
+    pub period_spent: i64,
+    pub breakdown: UsageBreakdown,
+}
+
+/// Per-`transaction_type` totals, mirroring `vpn_core::credits::UsageBreakdown`
+/// so the in-memory and DB-backed paths agree on shape.
+#[derive(Serialize, Default)]
+pub struct UsageBreakdown {
+    pub earned: i64,
+    pub spent: i64,
+    pub bonus: i64,
+    pub penalty: i64,
+}
+
+/// Raw aggregation row from `credit_transactions`. `SUM(bigint)` comes back
+/// as `NUMERIC` in Postgres, so these land as `Decimal` and get converted
+/// down to `i64` explicitly rather than truncated by the driver.
+#[derive(sqlx::FromRow)]
+struct UsageAggregateRow {
+    total_requests: i64,
+    period_earned: Option<Decimal>,
+    period_spent: Option<Decimal>,
+    earned: Option<Decimal>,
+    spent: Option<Decimal>,
+    bonus: Option<Decimal>,
+    penalty: Option<Decimal>,
+}
+
+fn decimal_to_credits(value: Option<Decimal>) -> i64 {
+    value.and_then(|d| d.to_i64()).unwrap_or(0)
 }
 
 #[derive(Serialize, sqlx::FromRow)]
@@ -23,27 +62,89 @@ pub struct TransactionResponse {
 pub struct SyncTrafficRequest {
     pub shared_bytes: i64,
     pub consumed_bytes: i64,
+    pub protocol: VpnProtocol,
+    /// Source port the session was issued `token` for at `/vpn/connect`
+    /// time — paired with the request's real source IP (see `ClientAddr`)
+    /// to re-verify `token` against `vpn_core::abuse::AbuseDetector`.
+    pub src_port: u16,
+    /// Echoes the `abuse_token` returned by `/vpn/connect`'s
+    /// `ConnectResponse`, binding this report to that session's origin.
+    pub token: String,
 }
 
 /// GET /credits/balance
-/// Returns the current credit balance for the authenticated user
+/// Returns the current credit balance for the authenticated user, alongside
+/// a dashboard-ready usage snapshot (total requests served, credits earned
+/// vs. spent in the current billing period, and a breakdown by transaction
+/// type) aggregated in the same round-trip rather than requiring a second
+/// call to `/credits/history`.
 pub async fn get_balance(
     State(state): State<AppState>,
-    AuthUser(user): AuthUser,
+    RateLimited(AuthUser(user)): RateLimited,
 ) -> impl IntoResponse {
     let pool = state.db.as_ref().expect("DB not initialized");
 
-    let row = sqlx::query("SELECT credits FROM users WHERE id = $1")
+    let row = sqlx::query("SELECT earned_credits, premium_credits FROM users WHERE id = $1")
         .bind(&user.sub)
         .fetch_optional(pool)
         .await;
 
-    match row {
-        Ok(Some(r)) => {
-            let credits: i64 = r.try_get("credits").unwrap_or(0);
-            (StatusCode::OK, Json(BalanceResponse { credits })).into_response()
-        },
-        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": "User not found"}))).into_response(),
+    let (earned_credits, premium_credits): (i64, i64) = match row {
+        Ok(Some(r)) => (
+            r.try_get("earned_credits").unwrap_or(0),
+            r.try_get("premium_credits").unwrap_or(0),
+        ),
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "User not found"}))).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
+    };
+    let credits = earned_credits + premium_credits;
+
+    // Current billing period = calendar month to date (UTC).
+    let period_start = chrono::Utc::now()
+        .date_naive()
+        .with_day(1)
+        .expect("day 1 is always valid")
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always valid");
+
+    let usage = sqlx::query_as::<_, UsageAggregateRow>(
+        r#"
+        SELECT
+            COUNT(*) AS total_requests,
+            COALESCE(SUM(amount) FILTER (WHERE amount > 0 AND created_at >= $2), 0) AS period_earned,
+            COALESCE(SUM(-amount) FILTER (WHERE amount < 0 AND created_at >= $2), 0) AS period_spent,
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'EARNED'), 0) AS earned,
+            COALESCE(SUM(-amount) FILTER (WHERE transaction_type = 'SPENT'), 0) AS spent,
+            COALESCE(SUM(amount) FILTER (WHERE transaction_type = 'BONUS'), 0) AS bonus,
+            COALESCE(SUM(-amount) FILTER (WHERE transaction_type = 'PENALTY'), 0) AS penalty
+        FROM credit_transactions
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(&user.sub)
+    .bind(period_start)
+    .fetch_one(pool)
+    .await;
+
+    match usage {
+        Ok(row) => (
+            StatusCode::OK,
+            Json(BalanceResponse {
+                credits,
+                earned_credits,
+                premium_credits,
+                total_requests: row.total_requests,
+                period_earned: decimal_to_credits(row.period_earned),
+                period_spent: decimal_to_credits(row.period_spent),
+                breakdown: UsageBreakdown {
+                    earned: decimal_to_credits(row.earned),
+                    spent: decimal_to_credits(row.spent),
+                    bonus: decimal_to_credits(row.bonus),
+                    penalty: decimal_to_credits(row.penalty),
+                },
+            }),
+        )
+            .into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
     }
 }
@@ -69,61 +170,132 @@ pub async fn get_history(
     }
 }
 
+fn bucket_db_str(bucket: CreditBucket) -> &'static str {
+    match bucket {
+        CreditBucket::Earned => "EARNED",
+        CreditBucket::Premium => "PREMIUM",
+    }
+}
+
 /// POST /credits/sync
-/// Synchronizes local traffic consumption/sharing with the central server
+/// Synchronizes local traffic consumption/sharing with the central server.
+/// Shared traffic always earns into the `earned` bucket; consumed traffic
+/// is drawn down earned-first, falling back to premium credits once earned
+/// runs out (mirrors `vpn_core::credits::CreditConfig::spend_priority`'s
+/// default).
 pub async fn sync_traffic(
     State(state): State<AppState>,
-    AuthUser(user): AuthUser,
+    RateLimited(AuthUser(user)): RateLimited,
+    ClientAddr(client_addr): ClientAddr,
     Json(payload): Json<SyncTrafficRequest>,
 ) -> impl IntoResponse {
     let pool = state.db.as_ref().expect("DB not initialized");
-    
-    // Standard conversion factor: 1 MB = 1 Credit
-    const BYTES_PER_CREDIT: i64 = 1_048_576;
-    let earned = (payload.shared_bytes / BYTES_PER_CREDIT) as i64;
-    let spent = (payload.consumed_bytes / BYTES_PER_CREDIT) as i64;
-    
-    let net_change = earned - spent;
 
-    if net_change == 0 {
-         return (StatusCode::OK, Json(json!({"message": "No change", "credits_change": 0}))).into_response();
+    // Feed the self-reported byte counts into the abuse detector before
+    // trusting them for billing, and reject the sync outright (no credit
+    // mutation) if either check comes back negative. `record_traffic`
+    // re-derives `token` from the request's real source address and
+    // returns `false` (as `SpoofedIdentity`) for a report whose token
+    // doesn't match — i.e. one replayed from a different origin than
+    // `/vpn/connect` issued it to. `check_share_ratio` returns `false` when
+    // the claimed shared/consumed split is itself abusive (far more
+    // consumed than shared), which would otherwise let a user who never
+    // actually shares anything keep drawing down credits indefinitely.
+    let total_bytes = (payload.shared_bytes.max(0) as u64).saturating_add(payload.consumed_bytes.max(0) as u64);
+    let traffic_ok = state.abuse_detector
+        .record_traffic(&user.sub, total_bytes, client_addr.ip(), payload.src_port, &payload.token)
+        .await;
+    let ratio_ok = state.abuse_detector
+        .check_share_ratio(&user.sub, payload.shared_bytes.max(0) as u64, payload.consumed_bytes.max(0) as u64)
+        .await;
+
+    if !traffic_ok || !ratio_ok {
+        return (StatusCode::FORBIDDEN, Json(json!({
+            "error": "Traffic report rejected by abuse detection",
+            "hint": "See /transparency/abuse for details"
+        }))).into_response();
     }
 
-    let transaction_type = if net_change >= 0 { "EARNED" } else { "SPENT" };
-    let description = format!("Sync: Shared {} MB, Consumed {} MB", 
-        payload.shared_bytes / BYTES_PER_CREDIT, 
-        payload.consumed_bytes / BYTES_PER_CREDIT
-    );
+    // Standard conversion factor: 1 MB = 1 Credit, scaled by how scarce the
+    // protocol that carried the traffic is (stealth protocols cost more).
+    const BYTES_PER_CREDIT: i64 = 1_048_576;
+    let rate = ProtocolPricing::default().multiplier(payload.protocol);
+    let earned = ((payload.shared_bytes / BYTES_PER_CREDIT) as f64 * rate) as i64;
+    let spent = ((payload.consumed_bytes / BYTES_PER_CREDIT) as f64 * rate) as i64;
+
+    if earned == 0 && spent == 0 {
+        return (StatusCode::OK, Json(json!({"message": "No change", "credits_change": 0, "premium_credits_used": 0}))).into_response();
+    }
 
-    // Atomically update balance and record transaction history
     let mut tx = match pool.begin().await {
         Ok(tx) => tx,
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
     };
 
-    let log_id = uuid::Uuid::new_v4().to_string();
-    let q1 = sqlx::query(
-        "INSERT INTO credit_transactions (id, user_id, amount, transaction_type, description) VALUES ($1, $2, $3, $4, $5)"
-    )
-    .bind(&log_id)
-    .bind(&user.sub)
-    .bind(net_change)
-    .bind(transaction_type)
-    .bind(&description)
-    .execute(&mut *tx)
-    .await;
+    let row = sqlx::query("SELECT earned_credits, premium_credits FROM users WHERE id = $1 FOR UPDATE")
+        .bind(&user.sub)
+        .fetch_optional(&mut *tx)
+        .await;
 
-    if let Err(e) = q1 {
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("Log failed: {}", e)}))).into_response();
+    let (mut earned_balance, mut premium_balance): (i64, i64) = match row {
+        Ok(Some(r)) => (r.get("earned_credits"), r.get("premium_credits")),
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "User not found"}))).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
+    };
+
+    if spent > earned_balance + premium_balance + earned {
+        return (StatusCode::PAYMENT_REQUIRED, Json(json!({"error": "Insufficient credits"}))).into_response();
     }
 
-    let q2 = sqlx::query(
-        "UPDATE users SET credits = credits + $1 WHERE id = $2"
-    )
-    .bind(net_change)
-    .bind(&user.sub)
-    .execute(&mut *tx)
-    .await;
+    let description = format!(
+        "Sync: Shared {} MB, Consumed {} MB via {} (rate {:.2}x)",
+        payload.shared_bytes / BYTES_PER_CREDIT,
+        payload.consumed_bytes / BYTES_PER_CREDIT,
+        payload.protocol,
+        rate,
+    );
+
+    let mut rows_to_insert: Vec<(i64, CreditBucket, &'static str)> = Vec::new();
+    if earned > 0 {
+        earned_balance += earned;
+        rows_to_insert.push((earned, CreditBucket::Earned, "EARNED"));
+    }
+
+    let mut premium_used = 0i64;
+    if spent > 0 {
+        for (bucket, amount) in draw_from_buckets(&mut earned_balance, &mut premium_balance, SpendPriority::EarnedFirst, spent) {
+            if bucket == CreditBucket::Premium {
+                premium_used += amount;
+            }
+            rows_to_insert.push((amount, bucket, "SPENT"));
+        }
+    }
+
+    for (amount, bucket, transaction_type) in &rows_to_insert {
+        let signed_amount = if *transaction_type == "SPENT" { -amount } else { *amount };
+        let q = sqlx::query(
+            "INSERT INTO credit_transactions (id, user_id, amount, transaction_type, bucket, description) VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&user.sub)
+        .bind(signed_amount)
+        .bind(*transaction_type)
+        .bind(bucket_db_str(*bucket))
+        .bind(&description)
+        .execute(&mut *tx)
+        .await;
+
+        if let Err(e) = q {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("Log failed: {}", e)}))).into_response();
+        }
+    }
+
+    let q2 = sqlx::query("UPDATE users SET earned_credits = $1, premium_credits = $2 WHERE id = $3")
+        .bind(earned_balance)
+        .bind(premium_balance)
+        .bind(&user.sub)
+        .execute(&mut *tx)
+        .await;
 
     if let Err(e) = q2 {
         return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("Update failed: {}", e)}))).into_response();
@@ -135,6 +307,7 @@ pub async fn sync_traffic(
 
     (StatusCode::OK, Json(json!({
         "message": "Sync successful",
-        "credits_change": net_change
+        "credits_change": earned - spent,
+        "premium_credits_used": premium_used
     }))).into_response()
 }
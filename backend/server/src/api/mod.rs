@@ -16,10 +16,12 @@ pub fn router(state: AppState) -> Router {
         // Authentication
         .route("/auth/login", post(auth::login))
         .route("/auth/register", post(auth::register))
+        .route("/auth/refresh", post(auth::refresh))
         
         // VPN connection
         .route("/vpn/connect", post(vpn::connect))
         .route("/vpn/disconnect", post(vpn::disconnect))
+        .route("/vpn/ws", get(vpn::ws_relay))
         
         // Credits system
         .route("/credits/balance", get(credits::get_balance))
@@ -32,11 +34,13 @@ pub fn router(state: AppState) -> Router {
         .route("/nodes/heartbeat", post(nodes::heartbeat))
         .route("/nodes/offline", post(nodes::go_offline))
         .route("/nodes/my", get(nodes::my_node))
+        .route("/nodes/ws", get(nodes::node_ws))
         
         // Transparency dashboard (real-time monitoring)
         .route("/transparency/sessions", get(transparency::get_active_sessions))
         .route("/transparency/history", get(transparency::get_session_history))
         .route("/transparency/stats", get(transparency::get_stats))
-        
+        .route("/transparency/abuse", get(transparency::get_abuse_history))
+
         .with_state(state)
 }
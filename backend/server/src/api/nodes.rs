@@ -1,5 +1,8 @@
 use axum::{
-    extract::{State, Query, Path},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
     http::StatusCode,
     response::IntoResponse,
     Json,
@@ -7,8 +10,13 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::Row;
+use tokio::sync::mpsc;
 
-use crate::{auth::AuthUser, state::AppState};
+use crate::{
+    auth::{AuthUser, ClientAddr},
+    state::AppState,
+    ws_hub::NodeEvent,
+};
 
 /// Node registration request from desktop/mobile client
 #[derive(Deserialize)]
@@ -20,6 +28,11 @@ pub struct RegisterNodeRequest {
     pub allow_streaming: Option<bool>,
     pub allow_torrents: Option<bool>,
     pub max_daily_gb: Option<i32>,
+    /// Directly dialable `ip:port` the client obtained from its own
+    /// `vpn_core::upnp::PortMapper` (UPnP/IGD), if mapping succeeded. `None`
+    /// means the node couldn't forward a port and `/vpn/connect` should
+    /// route clients to the central relay instead of a dead address.
+    pub external_endpoint: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -35,23 +48,27 @@ pub struct NodeInfo {
 pub async fn register_node(
     State(state): State<AppState>,
     AuthUser(user): AuthUser,
+    ClientAddr(client_addr): ClientAddr,
     Json(payload): Json<RegisterNodeRequest>,
 ) -> impl IntoResponse {
     let pool = state.db.as_ref().expect("DB not initialized");
-    
+
     let node_id = uuid::Uuid::new_v4().to_string();
     let protocols_json = serde_json::to_string(&payload.protocols).unwrap_or("[]".to_string());
-    
-    // Hash the user's IP for privacy (in production, get real IP from request)
-    let ip_hash = format!("hash_{}", uuid::Uuid::new_v4());
+
+    // Salted SHA-256 of the real client IP, so nodes can be rate-limited/banned
+    // by network without storing the raw address.
+    let ip_salt = std::env::var("IP_HASH_SALT").unwrap_or_else(|_| "default_salt".to_string());
+    let ip_hash = crate::proxy_protocol::hash_client_ip(client_addr.ip(), &ip_salt);
     
     let result = sqlx::query(
-        r#"INSERT INTO nodes 
-           (id, user_id, public_ip_hash, country_code, city, available_bandwidth_mbps, 
-            protocols, allow_streaming, allow_torrents, max_daily_gb, is_online)
-           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, TRUE)
+        r#"INSERT INTO nodes
+           (id, user_id, public_ip_hash, country_code, city, available_bandwidth_mbps,
+            protocols, allow_streaming, allow_torrents, max_daily_gb, external_endpoint, is_online)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, TRUE)
            ON CONFLICT (id) DO UPDATE SET
                is_online = TRUE,
+               external_endpoint = EXCLUDED.external_endpoint,
                last_heartbeat = CURRENT_TIMESTAMP,
                updated_at = CURRENT_TIMESTAMP
            RETURNING id"#
@@ -66,6 +83,7 @@ pub async fn register_node(
     .bind(payload.allow_streaming.unwrap_or(true))
     .bind(payload.allow_torrents.unwrap_or(false))
     .bind(payload.max_daily_gb.unwrap_or(50))
+    .bind(&payload.external_endpoint)
     .fetch_one(pool)
     .await;
 
@@ -101,23 +119,30 @@ pub struct DiscoverQuery {
 pub async fn discover_nodes(
     State(state): State<AppState>,
     AuthUser(user): AuthUser,
+    ClientAddr(client_addr): ClientAddr,
     Query(params): Query<DiscoverQuery>,
 ) -> impl IntoResponse {
     let pool = state.db.as_ref().expect("DB not initialized");
-    
+
     let limit = params.limit.unwrap_or(10).min(50);
-    
+
+    // Never hand a client a node running on its own IP (self-relay is useless
+    // and a common way to farm reputation/credits).
+    let ip_salt = std::env::var("IP_HASH_SALT").unwrap_or_else(|_| "default_salt".to_string());
+    let requester_ip_hash = crate::proxy_protocol::hash_client_ip(client_addr.ip(), &ip_salt);
+
     // Build dynamic query based on filters
     let mut query = String::from(
         r#"SELECT id, country_code, reputation_score, current_connections,
                   available_bandwidth_mbps, avg_latency_ms, protocols, node_group
-           FROM nodes 
-           WHERE is_online = TRUE 
+           FROM nodes
+           WHERE is_online = TRUE
              AND current_connections < max_connections
-             AND (user_id IS NULL OR user_id != $1)"#
+             AND (user_id IS NULL OR user_id != $1)
+             AND public_ip_hash != $2"#
     );
-    
-    let mut bind_count = 1;
+
+    let mut bind_count = 2;
     
     if params.country.is_some() {
         bind_count += 1;
@@ -138,8 +163,8 @@ pub async fn discover_nodes(
     query.push_str(&format!(" LIMIT {}", limit));
 
     // Execute with dynamic bindings
-    let mut q = sqlx::query(&query).bind(&user.sub);
-    
+    let mut q = sqlx::query(&query).bind(&user.sub).bind(&requester_ip_hash);
+
     if let Some(ref country) = params.country {
         q = q.bind(country);
     }
@@ -280,3 +305,103 @@ pub async fn my_node(
         }
     }
 }
+
+/// Query string carrying the JWT for the WebSocket upgrade, since browsers
+/// and embedded clients can't set an `Authorization` header on a `ws://` handshake.
+#[derive(Deserialize)]
+pub struct WsAuthQuery {
+    pub token: String,
+}
+
+/// GET /nodes/ws - Upgrade to a persistent socket that replaces REST heartbeat
+/// polling with periodic liveness pings, and receives server-pushed events
+/// (`connection_request`, `go_offline`, `reputation_changed`).
+pub async fn node_ws(
+    State(state): State<AppState>,
+    Query(params): Query<WsAuthQuery>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let claims = match crate::auth::verify_jwt(&params.token) {
+        Ok(claims) => claims,
+        Err(_) => {
+            return (StatusCode::UNAUTHORIZED, Json(json!({
+                "error": "Invalid or expired token"
+            }))).into_response();
+        }
+    };
+
+    ws.on_upgrade(move |socket| handle_node_socket(socket, state, claims.sub))
+        .into_response()
+}
+
+/// Drives one node's socket: registers it in the hub, relays pushed events
+/// out, and treats any inbound frame as the liveness ping that used to be a
+/// `POST /nodes/heartbeat`.
+async fn handle_node_socket(mut socket: WebSocket, state: AppState, user_id: String) {
+    let Some(pool) = state.db.as_ref() else {
+        let _ = socket.send(Message::Close(None)).await;
+        return;
+    };
+
+    let node_id: Option<String> = sqlx::query("SELECT id FROM nodes WHERE user_id = $1")
+        .bind(&user_id)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .map(|row| row.get("id"));
+
+    let Some(node_id) = node_id else {
+        let _ = socket
+            .send(Message::Text(json!({"error": "No node registered"}).to_string()))
+            .await;
+        return;
+    };
+
+    let (tx, mut rx) = mpsc::channel::<NodeEvent>(32);
+    state.ws_hub.register(node_id.clone(), tx).await;
+    let _ = sqlx::query("UPDATE nodes SET is_online = TRUE, last_heartbeat = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(&node_id)
+        .execute(pool)
+        .await;
+    tracing::info!("Node {} connected to notification hub", node_id);
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                match event {
+                    Some(event) => {
+                        let payload = serde_json::to_string(&event).unwrap_or_default();
+                        if socket.send(Message::Text(payload)).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Err(_)) => break,
+                    Some(Ok(_)) => {
+                        // Any client frame (ping, pong, or text) is a liveness
+                        // signal, standing in for the old REST heartbeat.
+                        let _ = sqlx::query(
+                            "UPDATE nodes SET last_heartbeat = CURRENT_TIMESTAMP, is_online = TRUE WHERE id = $1"
+                        )
+                        .bind(&node_id)
+                        .execute(pool)
+                        .await;
+                    }
+                }
+            }
+        }
+    }
+
+    state.ws_hub.unregister(&node_id).await;
+    let _ = sqlx::query("UPDATE nodes SET is_online = FALSE WHERE id = $1")
+        .bind(&node_id)
+        .execute(pool)
+        .await;
+    tracing::info!("Node {} disconnected from notification hub", node_id);
+}
@@ -134,6 +134,39 @@ pub struct HistoryQuery {
     pub days: Option<i32>,
 }
 
+/// GET /transparency/abuse - Why was I flagged/banned? Self-audit over the
+/// persisted ban/incident/risk-score history `AbuseDetector` keeps for this
+/// account (see `vpn_core::abuse::AbuseDetector::load_from`).
+pub async fn get_abuse_history(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+) -> impl IntoResponse {
+    let detector = &state.abuse_detector;
+
+    let currently_banned = detector.is_banned(&user.sub).await;
+    let risk_score = detector.get_risk_score(&user.sub).await;
+    let incidents: Vec<serde_json::Value> = detector
+        .get_abuse_history(Some(&user.sub), 50)
+        .await
+        .into_iter()
+        .map(|e| {
+            json!({
+                "abuse_type": format!("{:?}", e.abuse_type),
+                "severity": e.severity,
+                "timestamp": e.timestamp,
+                "details": e.details,
+            })
+        })
+        .collect();
+
+    (StatusCode::OK, Json(json!({
+        "currently_banned": currently_banned,
+        "risk_score": risk_score,
+        "incident_count": incidents.len(),
+        "incidents": incidents
+    }))).into_response()
+}
+
 /// GET /transparency/stats - Aggregated statistics for dashboard
 pub async fn get_stats(
     State(state): State<AppState>,
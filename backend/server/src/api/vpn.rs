@@ -1,14 +1,19 @@
 use axum::{
-    extract::State,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
-use crate::state::AppState;
+use crate::{auth::ClientAddr, state::AppState};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use vpn_core::protocol::VpnProtocol;
 use sqlx::Row;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
 
 #[derive(Deserialize)]
 pub struct ConnectRequest {
@@ -25,26 +30,52 @@ pub struct ConnectResponse {
     pub assigned_ip: String,
     pub server_public_key: Option<String>,
     pub node_country: Option<String>,
+    /// Binds this session to the client's source IP+port (see
+    /// `vpn_core::abuse::AbuseDetector::issue_session_token`). The client
+    /// must echo its own `src_port` plus this token back on `/credits/sync`
+    /// so traffic reports can be verified as actually coming from this
+    /// session's origin rather than replayed from elsewhere.
+    pub abuse_token: String,
 }
 
 /// POST /vpn/connect - Connect to VPN via P2P node or fallback server
 pub async fn connect(
     State(state): State<AppState>,
     user: crate::auth::AuthUser,
+    ClientAddr(client_addr): ClientAddr,
     Json(payload): Json<ConnectRequest>,
 ) -> impl IntoResponse {
     tracing::info!("Connection request from user: {} (JWT: {})", payload.username, user.0.sub);
 
+    // 0. Refuse outright if the abuse detector has already banned this
+    // account or is currently punishing the network origin it's connecting
+    // from (see `vpn_core::abuse::AbuseDetector`) — otherwise a banned user
+    // or a flooding source just gets handed a fresh session every time.
+    if state.abuse_detector.is_banned(&user.0.sub).await
+        || state.abuse_detector.is_punished(client_addr.ip()).await
+    {
+        return (StatusCode::FORBIDDEN, Json(json!({
+            "error": "Connection blocked due to abuse detection",
+            "hint": "See /transparency/abuse for details"
+        }))).into_response();
+    }
+
     let pool = state.db.as_ref().expect("DB not initialized");
 
-    // 1. Check user balance (must have credits to connect)
-    let balance_check = sqlx::query("SELECT credits FROM users WHERE id = $1")
+    // 1. Check user balance (must have credits to connect). Earned and
+    // premium credits are tracked separately (see `/credits/sync`), but the
+    // minimum-to-connect check is enforced against their combined total.
+    let balance_check = sqlx::query("SELECT earned_credits, premium_credits FROM users WHERE id = $1")
         .bind(&user.0.sub)
         .fetch_optional(pool)
         .await;
 
     let credits: i64 = match balance_check {
-        Ok(Some(row)) => row.get("credits"),
+        Ok(Some(row)) => {
+            let earned: i64 = row.get("earned_credits");
+            let premium: i64 = row.get("premium_credits");
+            earned + premium
+        }
         Ok(None) => 0,
         Err(_) => 0,
     };
@@ -61,12 +92,18 @@ pub async fn connect(
     // 2. Find best available P2P node
     let preferred = payload.preferred_country.as_deref().unwrap_or("*");
     
+    // Only consider nodes that successfully mapped a port via UPnP/IGD
+    // (`vpn_core::upnp::PortMapper`, recorded at `/nodes/register` time) —
+    // a node without a reachable `external_endpoint` can't accept an
+    // inbound tunnel, so it's skipped in favor of the central relay instead
+    // of handing out a dead address.
     let node_query = if preferred == "*" {
         sqlx::query(
-            r#"SELECT id, country_code, public_ip_hash 
-               FROM nodes 
-               WHERE is_online = TRUE 
+            r#"SELECT id, country_code, external_endpoint
+               FROM nodes
+               WHERE is_online = TRUE
                  AND current_connections < max_connections
+                 AND external_endpoint IS NOT NULL
                  AND user_id != $1
                ORDER BY reputation_score DESC, avg_latency_ms ASC
                LIMIT 1"#
@@ -74,10 +111,11 @@ pub async fn connect(
         .bind(&user.0.sub)
     } else {
         sqlx::query(
-            r#"SELECT id, country_code, public_ip_hash 
-               FROM nodes 
-               WHERE is_online = TRUE 
+            r#"SELECT id, country_code, external_endpoint
+               FROM nodes
+               WHERE is_online = TRUE
                  AND current_connections < max_connections
+                 AND external_endpoint IS NOT NULL
                  AND user_id != $1
                  AND country_code = $2
                ORDER BY reputation_score DESC, avg_latency_ms ASC
@@ -93,17 +131,35 @@ pub async fn connect(
         Ok(Some(row)) => {
             let nid: String = row.get("id");
             let country: String = row.get("country_code");
-            // In production: decrypt/resolve the actual IP
-            let ep = format!("peer-{}.worldvpn.net:51820", &nid[..8]);
+            let ep: String = row.get("external_endpoint");
             (Some(nid), Some(country), ep)
         }
         _ => {
-            // Fallback to central server if no P2P node available
+            // Fallback to central server if no P2P node available (or none
+            // with a working port mapping)
             tracing::warn!("No P2P nodes available, using fallback server");
             (None, None, "fallback.worldvpn.net:51820".to_string())
         }
     };
 
+    // 2b. Feed the real connection into the abuse detector (connection-flood
+    // and origin-IP punishment tracking, plus port-scan/fan-out tracking
+    // when there's a concrete destination) — bound to the client's actual
+    // source via a token only valid for this `(ip, port)` pair, so a
+    // replayed report from elsewhere can't ride along as this user. Runs
+    // for fallback-routed connections too (`endpoint` is a hostname, not an
+    // `ip:port`, so there's no destination to feed the port-scan/fan-out
+    // checks) — otherwise every fallback connection would skip abuse
+    // tracking entirely.
+    let abuse_token = state
+        .abuse_detector
+        .issue_session_token(client_addr.ip(), client_addr.port());
+    let dest = endpoint.parse::<SocketAddr>().ok().map(|a| (a.ip(), a.port()));
+    state
+        .abuse_detector
+        .record_connection(&user.0.sub, client_addr.ip(), client_addr.port(), dest, &abuse_token)
+        .await;
+
     // 3. Create session
     let session_id = uuid::Uuid::new_v4().to_string();
     let virtual_ip = format!("10.0.0.{}", rand::random::<u8>());
@@ -155,6 +211,13 @@ pub async fn connect(
         .bind(&client_hash)
         .execute(pool)
         .await;
+
+        // Wake the matched node immediately instead of making it wait for its
+        // next heartbeat poll to discover it has a client.
+        state.ws_hub.notify(nid, crate::ws_hub::NodeEvent::ConnectionRequest {
+            session_id: session_id.clone(),
+            client_country: node_country.clone(),
+        }).await;
     }
 
     tracing::info!("Session created: {} -> {} via {:?}", session_id, endpoint, payload.protocol);
@@ -165,6 +228,7 @@ pub async fn connect(
         assigned_ip: virtual_ip,
         server_public_key: credentials,
         node_country,
+        abuse_token,
     };
 
     (StatusCode::OK, Json(response)).into_response()
@@ -205,3 +269,111 @@ pub async fn disconnect(
 pub struct DisconnectRequest {
     pub session_id: String,
 }
+
+/// Query string for the `/vpn/ws` upgrade: a browser/embedded client can't
+/// set an `Authorization` header on a `ws://` handshake, so the JWT and the
+/// session to relay travel as query params instead (mirrors `/nodes/ws`'s
+/// `WsAuthQuery`).
+#[derive(Deserialize)]
+pub struct WsRelayQuery {
+    pub token: String,
+    pub session_id: String,
+}
+
+/// GET /vpn/ws - De-frames a WireGuard-over-WSS client's WebSocket binary
+/// messages (see `vpn_core::tunnel::WsTlsTransport`) and relays each one,
+/// unmodified, as a UDP datagram to the session's assigned WireGuard peer —
+/// and relays the peer's reply datagrams back the same way. This lets a
+/// client stuck behind a firewall that blocks raw UDP reach the same peer a
+/// direct WireGuard client would, by tunneling through 443/TLS instead.
+pub async fn ws_relay(
+    State(state): State<AppState>,
+    Query(params): Query<WsRelayQuery>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let claims = match crate::auth::verify_jwt(&params.token) {
+        Ok(claims) => claims,
+        Err(_) => {
+            return (StatusCode::UNAUTHORIZED, Json(json!({
+                "error": "Invalid or expired token"
+            }))).into_response();
+        }
+    };
+
+    let Some(pool) = state.db.as_ref() else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+            "error": "Database unavailable"
+        }))).into_response();
+    };
+
+    let endpoint: Option<String> = sqlx::query(
+        "SELECT endpoint FROM sessions WHERE id = $1 AND user_id = $2"
+    )
+    .bind(&params.session_id)
+    .bind(&claims.sub)
+    .fetch_optional(pool)
+    .await
+    .ok()
+    .flatten()
+    .map(|row| row.get("endpoint"));
+
+    let Some(endpoint) = endpoint else {
+        return (StatusCode::NOT_FOUND, Json(json!({
+            "error": "Unknown or expired session"
+        }))).into_response();
+    };
+
+    ws.on_upgrade(move |socket| relay_socket(socket, endpoint))
+        .into_response()
+}
+
+/// Drives one relay: a UDP socket connected to the WireGuard peer's
+/// `endpoint` pumps datagrams in both directions against the client's WS
+/// frames until either side closes.
+async fn relay_socket(mut socket: WebSocket, endpoint: String) {
+    let peer_socket = match UdpSocket::bind("0.0.0.0:0").await {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!("vpn/ws relay: failed to bind UDP socket: {}", e);
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+
+    if let Err(e) = peer_socket.connect(&endpoint).await {
+        tracing::warn!("vpn/ws relay: failed to reach peer {}: {}", endpoint, e);
+        let _ = socket.send(Message::Close(None)).await;
+        return;
+    }
+
+    let mut buf = vec![0u8; 65535];
+    loop {
+        tokio::select! {
+            recv = peer_socket.recv(&mut buf) => {
+                match recv {
+                    Ok(n) => {
+                        if socket.send(Message::Binary(buf[..n].to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("vpn/ws relay: peer read error: {}", e);
+                        break;
+                    }
+                }
+            }
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Binary(frame))) => {
+                        if peer_socket.send(&frame).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Err(_)) => break,
+                    Some(Ok(_)) => {} // ignore ping/pong/text keepalives
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,18 @@
 use axum::{
-    extract::FromRequestParts,
+    extract::{ConnectInfo, FromRequestParts},
     http::{request::Parts, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use std::net::SocketAddr;
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use crate::rate_limit::RateLimitTier;
+use crate::state::AppState;
+
 /// Standard JWT payload
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -54,6 +58,26 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     Ok(token_data.claims)
 }
 
+/// Generates a new opaque refresh token and its hash. Only the hash is ever
+/// persisted (in the `refresh_tokens` table), so a database leak doesn't
+/// hand out tokens usable to mint fresh access JWTs.
+pub fn generate_refresh_token() -> (String, String) {
+    use rand::RngCore;
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let hash = hash_refresh_token(&token);
+    (token, hash)
+}
+
+/// Hashes a refresh token for storage/lookup comparison.
+pub fn hash_refresh_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
 /// Axum extractor that mandates a valid Bearer token for protected routes
 #[derive(Debug, Clone)]
 pub struct AuthUser(pub Claims);
@@ -101,3 +125,70 @@ where
         }
     }
 }
+
+/// Axum extractor for the real client source address.
+///
+/// In TLS mode the accept loop inserts this directly into the request
+/// extensions — either the PROXY-protocol-recovered address (when
+/// `TRUST_PROXY_PROTOCOL=true` and the peer is a trusted upstream) or the raw
+/// TCP peer otherwise. In plain HTTP mode it falls back to axum's built-in
+/// `ConnectInfo`.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub SocketAddr);
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ClientAddr
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(addr) = parts.extensions.get::<SocketAddr>() {
+            return Ok(ClientAddr(*addr));
+        }
+        if let Some(ConnectInfo(addr)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() {
+            return Ok(ClientAddr(*addr));
+        }
+        Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "Client address unavailable"})),
+        )
+            .into_response())
+    }
+}
+
+/// Wraps `AuthUser` with a deferred rate-limit check (see `rate_limit`
+/// module), so endpoints that are cheap to spam (balance lookups, traffic
+/// sync) reject a user once their per-window quota is exceeded instead of
+/// accepting unlimited requests per token.
+#[derive(Debug, Clone)]
+pub struct RateLimited(pub AuthUser);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for RateLimited {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let auth = AuthUser::from_request_parts(parts, state).await?;
+
+        let status = state.rate_limiter.check(&auth.0.sub, RateLimitTier::STANDARD).await;
+        if !status.allowed {
+            return Err(rate_limit_response(status));
+        }
+
+        Ok(RateLimited(auth))
+    }
+}
+
+fn rate_limit_response(status: crate::rate_limit::RateLimitStatus) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [
+            ("X-RateLimit-Limit", status.limit.to_string()),
+            ("X-RateLimit-Remaining", status.remaining.to_string()),
+        ],
+        Json(json!({"error": "Rate limit exceeded"})),
+    )
+        .into_response()
+}
@@ -0,0 +1,94 @@
+//! Optional HTTP/3 (QUIC) listener, run alongside the TCP/TLS listener.
+//!
+//! Enabled via `ENABLE_HTTP3=true`. Shares the same certificate/key and axum
+//! router as the H2/HTTP-1.1 listener, advertising `h3` over ALPN so mobile
+//! clients on lossy links get 0-RTT resumption and no head-of-line blocking.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::Router;
+use h3::server::RequestStream;
+use h3_quinn::quinn;
+use tracing::{error, info};
+
+/// Runs the QUIC accept loop until the process is terminated or a fatal bind error occurs.
+pub async fn serve(
+    addr: SocketAddr,
+    mut tls_config: rustls::ServerConfig,
+    app: Router,
+) -> anyhow::Result<()> {
+    // h3 negotiates over its own ALPN id, independent from the h2/http1.1 listener.
+    tls_config.alpn_protocols = vec![b"h3".to_vec()];
+    tls_config.max_early_data_size = u32::MAX; // allow 0-RTT resumption
+
+    let quinn_config = quinn::crypto::rustls::QuicServerConfig::try_from(tls_config)
+        .map_err(|e| anyhow::anyhow!("Invalid QUIC/TLS configuration: {}", e))?;
+    let server_config = quinn::ServerConfig::with_crypto(Arc::new(quinn_config));
+
+    let endpoint = quinn::Endpoint::server(server_config, addr)?;
+    info!("🎧 HTTP/3 (QUIC) API Server listening on h3://{}", addr);
+
+    while let Some(connecting) = endpoint.accept().await {
+        let app = app.clone();
+        tokio::spawn(async move {
+            let remote = connecting.remote_address();
+            match connecting.await {
+                Ok(connection) => {
+                    if let Err(e) = handle_connection(connection, app).await {
+                        error!("HTTP/3 connection error from {}: {}", remote, e);
+                    }
+                }
+                Err(e) => error!("HTTP/3 handshake error from {}: {}", remote, e),
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn handle_connection(connection: quinn::Connection, app: Router) -> anyhow::Result<()> {
+    let mut h3_conn =
+        h3::server::Connection::new(h3_quinn::Connection::new(connection)).await?;
+
+    loop {
+        match h3_conn.accept().await {
+            Ok(Some((req, stream))) => {
+                let app = app.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_request(req, stream, app).await {
+                        error!("HTTP/3 request error: {}", e);
+                    }
+                });
+            }
+            Ok(None) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Dispatches a single HTTP/3 request into the shared axum router via tower.
+async fn handle_request(
+    req: http::Request<()>,
+    mut stream: RequestStream<h3_quinn::BidiStream<bytes::Bytes>, bytes::Bytes>,
+    app: Router,
+) -> anyhow::Result<()> {
+    let (parts, _) = req.into_parts();
+    let request = axum::http::Request::from_parts(parts, axum::body::Body::empty());
+
+    let response = tower::ServiceExt::oneshot(app, request).await?;
+    let (parts, body) = response.into_parts();
+
+    let h3_response = http::Response::from_parts(parts, ());
+    stream.send_response(h3_response).await?;
+
+    let bytes = axum::body::to_bytes(body, usize::MAX).await?;
+    if !bytes.is_empty() {
+        stream.send_data(bytes).await?;
+    }
+    stream.finish().await?;
+
+    Ok(())
+}
@@ -1,6 +1,12 @@
 mod state;
 mod api;
 mod auth;
+mod http3;
+mod node_prober;
+mod proxy_protocol;
+mod rate_limit;
+mod services;
+mod ws_hub;
 
 use crate::state::AppState;
 use std::net::SocketAddr;
@@ -18,6 +24,14 @@ async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let use_tls = std::env::var("USE_TLS").unwrap_or_else(|_| "false".to_string()) == "true";
+    let enable_http3 = std::env::var("ENABLE_HTTP3").unwrap_or_else(|_| "false".to_string()) == "true";
+    let trust_proxy_protocol =
+        std::env::var("TRUST_PROXY_PROTOCOL").unwrap_or_else(|_| "false".to_string()) == "true";
+    let trusted_proxy_upstreams: Vec<std::net::IpAddr> = std::env::var("TRUSTED_PROXY_UPSTREAMS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse().ok())
+        .collect();
 
     // Establish persistent database connection pool
     info!("📦 Connecting to database: {}", db_url);
@@ -32,7 +46,29 @@ async fn main() -> anyhow::Result<()> {
     sqlx::query("SELECT 1").execute(&db_pool).await.expect("DB Health check failed");
 
     // Initialize global application state
-    let state = AppState::new(Some(db_pool));
+    let mut state = AppState::new(Some(db_pool));
+
+    // Periodically re-probe P2P nodes' reachability/RTT so stale or dead
+    // peers fall out of `/vpn/connect`'s selection on their own.
+    if let Some(ref pool) = state.db {
+        node_prober::spawn(pool.clone());
+        tokio::spawn(services::vpngate::start_vpngate_sync(pool.clone()));
+
+        // Rehydrate bans/incidents/risk scores recorded before a restart.
+        // `state.abuse_detector` is already live (and cheaply `Arc`-cloned
+        // into any handlers that ran before this completes), so the
+        // rehydrated detector replaces its contents in place rather than
+        // swapping the `Arc` itself.
+        match vpn_core::abuse::AbuseDetector::load_from(
+            pool.clone(),
+            vpn_core::abuse::AbuseThresholds::default(),
+        )
+        .await
+        {
+            Ok(detector) => state.abuse_detector = Arc::new(detector),
+            Err(e) => tracing::error!("Failed to load persisted abuse state: {}", e),
+        }
+    }
 
     // Register API routes
     let app = api::router(state);
@@ -74,47 +110,90 @@ async fn main() -> anyhow::Result<()> {
         // Negotiate ALPN for H2 and HTTP/1.1
         server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
-        let tls_acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+        let tls_acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config.clone()));
 
         info!("🎧 HTTPS API Server listening on https://{}", addr);
 
         let listener = tokio::net::TcpListener::bind(addr).await?;
-
-        // Primary connection accept loop
-        loop {
-            let (tcp_stream, remote_addr) = listener.accept().await?;
-            let tls_acceptor = tls_acceptor.clone();
-            let app = app.clone();
-
-            tokio::spawn(async move {
-                let tls_stream = match tls_acceptor.accept(tcp_stream).await {
-                    Ok(stream) => stream,
-                    Err(e) => {
-                        tracing::error!("TLS handshake error from {}: {}", remote_addr, e);
-                        return;
+        let app_for_h3 = app.clone();
+
+        // Primary TCP/TLS connection accept loop (H2/HTTP-1.1)
+        let tcp_loop = async move {
+            loop {
+                let (tcp_stream, remote_addr) = listener.accept().await?;
+                let tls_acceptor = tls_acceptor.clone();
+                let app = app.clone();
+                let trusted_upstreams = trusted_proxy_upstreams.clone();
+
+                tokio::spawn(async move {
+                    // Only peel a PROXY protocol header off peers we've explicitly
+                    // configured as trusted load balancers — otherwise any client
+                    // could spoof its own source address.
+                    let should_trust_proxy =
+                        trust_proxy_protocol && trusted_upstreams.contains(&remote_addr.ip());
+
+                    let (client_addr, tcp_stream) = if should_trust_proxy {
+                        match proxy_protocol::peel_header(tcp_stream).await {
+                            Ok((Some(addr), stream)) => (addr, stream),
+                            Ok((None, stream)) => (remote_addr, stream),
+                            Err(e) => {
+                                tracing::error!(
+                                    "PROXY protocol parse error from {}: {}",
+                                    remote_addr,
+                                    e
+                                );
+                                return;
+                            }
+                        }
+                    } else {
+                        (remote_addr, proxy_protocol::PrefixedStream::passthrough(tcp_stream))
+                    };
+
+                    let tls_stream = match tls_acceptor.accept(tcp_stream).await {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            tracing::error!("TLS handshake error from {}: {}", remote_addr, e);
+                            return;
+                        }
+                    };
+
+                    // Serve connection via hyper (low-level handling for custom TLS)
+                    if let Err(e) = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(
+                            hyper_util::rt::TokioIo::new(tls_stream),
+                            hyper::service::service_fn(move |mut req| {
+                                req.extensions_mut().insert(client_addr);
+                                tower::ServiceExt::oneshot(app.clone(), req)
+                            })
+                        )
+                        .await
+                    {
+                        tracing::error!("HTTPS connection error: {}", e);
                     }
-                };
-
-                // Serve connection via hyper (low-level handling for custom TLS)
-                if let Err(e) = hyper::server::conn::http1::Builder::new()
-                    .serve_connection(
-                        hyper_util::rt::TokioIo::new(tls_stream),
-                        hyper::service::service_fn(move |req| {
-                            tower::ServiceExt::oneshot(app.clone(), req)
-                        })
-                    )
-                    .await
-                {
-                    tracing::error!("HTTPS connection error: {}", e);
-                }
-            });
+                });
+            }
+            #[allow(unreachable_code)]
+            Ok::<(), anyhow::Error>(())
+        };
+
+        if enable_http3 {
+            info!("🚀 HTTP/3 (QUIC) listener enabled (ENABLE_HTTP3=true)");
+            // Same bind port: QUIC rides UDP, the TCP listener above keeps the port on TCP.
+            let h3_loop = http3::serve(addr, server_config, app_for_h3);
+            tokio::try_join!(tcp_loop, h3_loop)?;
+        } else {
+            tcp_loop.await?;
         }
     } else {
         info!("⚠️  HTTP mode (unsecured) - Use USE_TLS=true for HTTPS");
         info!("🎧 HTTP API Server listening on http://{}", addr);
         
         let listener = tokio::net::TcpListener::bind(addr).await?;
-        axum::serve(listener, app).await?;
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
     }
 
     Ok(())
@@ -0,0 +1,151 @@
+//! Background reachability/latency prober for P2P nodes.
+//!
+//! `reputation_score`/`avg_latency_ms` drive node selection in
+//! `/vpn/connect`'s `ORDER BY reputation_score DESC, avg_latency_ms ASC`,
+//! but until now nothing ever updated them after registration — a node
+//! could go dark behind its UPnP mapping (see `vpn_core::upnp::PortMapper`)
+//! and keep getting handed out. This sweeps every node with a live
+//! `external_endpoint` on an interval, pings it, folds the RTT into an
+//! EWMA, and nudges `reputation_score` up or down so `connect` naturally
+//! stops routing clients to peers that no longer answer.
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// How often the prober sweeps all online nodes.
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `ping` deadline per node, in whole seconds (passed straight to `-W`).
+const PROBE_TIMEOUT_SECS: u64 = 2;
+
+/// Smoothing factor for the RTT EWMA: `ewma = alpha*sample + (1-alpha)*ewma`.
+const EWMA_ALPHA: f64 = 0.3;
+
+/// Consecutive failed probes before a node is demoted to offline.
+const MAX_CONSECUTIVE_FAILURES: i32 = 3;
+
+#[derive(sqlx::FromRow)]
+struct ProbeTarget {
+    id: String,
+    external_endpoint: String,
+    avg_latency_ms: i32,
+    reputation_score: i32,
+    consecutive_probe_failures: i32,
+}
+
+/// Spawns the prober loop against `pool`. Runs for the lifetime of the
+/// server process.
+pub fn spawn(pool: PgPool) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(PROBE_INTERVAL).await;
+            if let Err(e) = sweep(&pool).await {
+                warn!("Node probe sweep failed: {}", e);
+            }
+        }
+    })
+}
+
+async fn sweep(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let targets = sqlx::query_as::<_, ProbeTarget>(
+        r#"SELECT id, external_endpoint, avg_latency_ms, reputation_score, consecutive_probe_failures
+           FROM nodes
+           WHERE is_online = TRUE AND external_endpoint IS NOT NULL"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let probes = targets.into_iter().map(|target| async move {
+        let rtt_ms = probe_node(&target.external_endpoint).await;
+        (target, rtt_ms)
+    });
+
+    for (target, rtt_ms) in futures::future::join_all(probes).await {
+        if let Err(e) = apply_probe_result(pool, target, rtt_ms).await {
+            warn!("Failed to persist probe result: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn apply_probe_result(
+    pool: &PgPool,
+    target: ProbeTarget,
+    rtt_ms: Option<f64>,
+) -> Result<(), sqlx::Error> {
+    let (avg_latency_ms, reputation_score, consecutive_failures, is_online) = match rtt_ms {
+        Some(sample) => {
+            let smoothed = if target.avg_latency_ms <= 0 {
+                sample
+            } else {
+                EWMA_ALPHA * sample + (1.0 - EWMA_ALPHA) * target.avg_latency_ms as f64
+            };
+            let reputation = (target.reputation_score + 2).min(100);
+            (smoothed.round() as i32, reputation, 0, true)
+        }
+        None => {
+            let failures = target.consecutive_probe_failures + 1;
+            let reputation = (target.reputation_score - 10).max(0);
+            let online = failures < MAX_CONSECUTIVE_FAILURES;
+            if !online {
+                info!(
+                    "Node {} failed {} consecutive probes, marking offline",
+                    target.id, failures
+                );
+            }
+            (target.avg_latency_ms, reputation, failures, online)
+        }
+    };
+
+    sqlx::query(
+        r#"UPDATE nodes
+           SET avg_latency_ms = $1, reputation_score = $2, consecutive_probe_failures = $3, is_online = $4
+           WHERE id = $5"#,
+    )
+    .bind(avg_latency_ms)
+    .bind(reputation_score)
+    .bind(consecutive_failures)
+    .bind(is_online)
+    .bind(&target.id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Pings the IP half of `endpoint` (`ip:port`) via the system `ping`
+/// binary and parses the round-trip time out of its output. ICMP rather
+/// than a protocol-level handshake keeps this honest about reachability
+/// without needing real WireGuard/OpenVPN key material just to measure
+/// RTT.
+async fn probe_node(endpoint: &str) -> Option<f64> {
+    let ip: IpAddr = endpoint.rsplit_once(':')?.0.parse().ok()?;
+
+    let output = tokio::process::Command::new("ping")
+        .arg("-c")
+        .arg("1")
+        .arg("-W")
+        .arg(PROBE_TIMEOUT_SECS.to_string())
+        .arg(ip.to_string())
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .split("time=")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse::<f64>()
+        .ok()
+}
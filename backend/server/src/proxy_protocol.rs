@@ -0,0 +1,199 @@
+//! PROXY protocol v1/v2 support for recovering the real client IP when the
+//! server sits behind a TCP load balancer.
+//!
+//! Only trusted when `TRUST_PROXY_PROTOCOL=true` is set — never parse this
+//! header from an untrusted peer, since it lets a client claim an arbitrary
+//! source address.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+
+const V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// Wraps a TCP stream, replaying any bytes consumed while probing for a
+/// PROXY protocol header so the rest of the connection (e.g. the TLS
+/// handshake) sees an unmodified byte stream.
+pub struct PrefixedStream<S> {
+    prefix: Vec<u8>,
+    prefix_pos: usize,
+    inner: S,
+}
+
+impl<S> PrefixedStream<S> {
+    fn new(inner: S, leftover: Vec<u8>) -> Self {
+        Self {
+            prefix: leftover,
+            prefix_pos: 0,
+            inner,
+        }
+    }
+
+    /// Wraps a stream with no replayed prefix, for callers that skip PROXY
+    /// protocol parsing entirely but still need a `PrefixedStream` to match
+    /// the accept loop's stream type.
+    pub fn passthrough(inner: S) -> Self {
+        Self::new(inner, Vec::new())
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PrefixedStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.prefix_pos < self.prefix.len() {
+            let remaining = &self.prefix[self.prefix_pos..];
+            let n = remaining.len().min(buf.remaining());
+            buf.put_slice(&remaining[..n]);
+            self.prefix_pos += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PrefixedStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Attempts to read a PROXY protocol v1 or v2 header from the front of `stream`.
+///
+/// Returns the recovered source address (if any) and a stream that replays
+/// whatever bytes were consumed while probing, so the caller can keep reading
+/// the TLS handshake that follows unmodified.
+pub async fn peel_header<S: AsyncRead + Unpin>(
+    mut stream: S,
+) -> io::Result<(Option<SocketAddr>, PrefixedStream<S>)> {
+    let mut probe = [0u8; 16];
+    let n = peek_fill(&mut stream, &mut probe).await?;
+
+    if n >= 12 && probe[..12] == V2_SIGNATURE {
+        return parse_v2(stream, &probe[..n]).await;
+    }
+
+    if n >= 5 && &probe[..5] == b"PROXY" {
+        return parse_v1(stream, &probe[..n]).await;
+    }
+
+    Ok((None, PrefixedStream::new(stream, probe[..n].to_vec())))
+}
+
+/// Reads up to `buf.len()` bytes, stopping early on EOF (best-effort fill).
+async fn peek_fill<S: AsyncRead + Unpin>(stream: &mut S, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match stream.read(&mut buf[total..]).await {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+async fn parse_v1<S: AsyncRead + Unpin>(
+    mut stream: S,
+    prefix: &[u8],
+) -> io::Result<(Option<SocketAddr>, PrefixedStream<S>)> {
+    let mut line = prefix.to_vec();
+    while !line.ends_with(b"\r\n") && line.len() < 107 {
+        let mut byte = [0u8; 1];
+        if stream.read_exact(&mut byte).await.is_err() {
+            break;
+        }
+        line.push(byte[0]);
+    }
+
+    let text = String::from_utf8_lossy(&line);
+    let trimmed = text.trim_end_matches("\r\n");
+    let parts: Vec<&str> = trimmed.split_whitespace().collect();
+
+    // "PROXY TCP4 <src> <dst> <sport> <dport>"
+    let addr = if parts.len() >= 6 && (parts[1] == "TCP4" || parts[1] == "TCP6") {
+        match (parts[2].parse::<IpAddr>(), parts[4].parse::<u16>()) {
+            (Ok(ip), Ok(port)) => Some(SocketAddr::new(ip, port)),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    Ok((addr, PrefixedStream::new(stream, Vec::new())))
+}
+
+async fn parse_v2<S: AsyncRead + Unpin>(
+    mut stream: S,
+    prefix: &[u8],
+) -> io::Result<(Option<SocketAddr>, PrefixedStream<S>)> {
+    // Bytes 12..16: version/command (1), address family/protocol (1), length (2, BE)
+    let mut header = prefix.to_vec();
+    while header.len() < 16 {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await?;
+        header.push(byte[0]);
+    }
+
+    let ver_cmd = header[12];
+    let version = ver_cmd >> 4;
+    if version != 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported PROXY protocol version"));
+    }
+    let command = ver_cmd & 0x0F;
+
+    let fam_proto = header[13];
+    let address_family = fam_proto >> 4;
+
+    let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+    let mut addr_block = vec![0u8; len];
+    stream.read_exact(&mut addr_block).await?;
+
+    // command 0x0 = LOCAL (health check from the LB itself): no real client address.
+    let addr = if command == 0x1 {
+        match address_family {
+            0x1 if addr_block.len() >= 12 => {
+                let src = Ipv4Addr::new(addr_block[0], addr_block[1], addr_block[2], addr_block[3]);
+                let sport = u16::from_be_bytes([addr_block[8], addr_block[9]]);
+                Some(SocketAddr::new(IpAddr::V4(src), sport))
+            }
+            0x2 if addr_block.len() >= 36 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&addr_block[0..16]);
+                let src = Ipv6Addr::from(octets);
+                let sport = u16::from_be_bytes([addr_block[32], addr_block[33]]);
+                Some(SocketAddr::new(IpAddr::V6(src), sport))
+            }
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    Ok((addr, PrefixedStream::new(stream, Vec::new())))
+}
+
+/// Derives a stable, non-reversible identifier for a client IP for reputation/abuse tracking.
+pub fn hash_client_ip(ip: IpAddr, salt: &str) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(ip.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
@@ -0,0 +1,136 @@
+//! Deferred, Redis-backed rate limiting for auth and credit endpoints.
+//!
+//! Mirrors the "deferred" approach large reverse proxies use to keep a
+//! shared backing store off the hot path: each request increments a local
+//! in-memory counter keyed by `(user_id, window)` where
+//! `window = unix_secs / period`, and only reconciles with Redis — an
+//! atomic `INCR` + `EXPIRE` — once the local count crosses a configurable
+//! fraction of the tier's limit. This keeps Redis load roughly constant
+//! regardless of request volume, and falls back to pure local limiting
+//! (still correct for a single server instance) if Redis is unreachable.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use redis::AsyncCommands;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Fraction of a tier's limit the local counter can absorb before an
+/// authoritative Redis reconciliation is triggered.
+const SYNC_FRACTION: f64 = 0.5;
+
+/// Per-tier request budget over a fixed window.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitTier {
+    pub limit: u64,
+    pub period_secs: u64,
+}
+
+impl RateLimitTier {
+    pub const STANDARD: RateLimitTier = RateLimitTier { limit: 60, period_secs: 60 };
+    pub const PREMIUM: RateLimitTier = RateLimitTier { limit: 600, period_secs: 60 };
+}
+
+/// Outcome of a rate limit check, carrying the values callers surface as
+/// `X-RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+}
+
+#[derive(Default)]
+struct LocalCounter {
+    count: u64,
+}
+
+/// Deferred rate limiter keyed by `(user_id, window)`, backed by Redis for
+/// the authoritative count across server instances.
+pub struct DeferredRateLimiter {
+    redis: Option<redis::Client>,
+    local: Arc<Mutex<HashMap<(String, u64), LocalCounter>>>,
+}
+
+impl DeferredRateLimiter {
+    /// Connects to `REDIS_URL` if set; otherwise every check stays purely local.
+    pub fn from_env() -> Self {
+        let redis = std::env::var("REDIS_URL").ok().and_then(|url| redis::Client::open(url).ok());
+        Self {
+            redis,
+            local: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn current_window(period_secs: u64) -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        now / period_secs.max(1)
+    }
+
+    /// Increments the local counter for `user_id` and, once it crosses
+    /// `SYNC_FRACTION` of `tier.limit`, reconciles with the authoritative
+    /// Redis count. Below that threshold the local counter is trusted and
+    /// Redis is never touched for this request.
+    pub async fn check(&self, user_id: &str, tier: RateLimitTier) -> RateLimitStatus {
+        let window = Self::current_window(tier.period_secs);
+
+        let local_count = {
+            let mut local = self.local.lock().await;
+            local.retain(|(_, w), _| *w == window);
+            let counter = local.entry((user_id.to_string(), window)).or_default();
+            counter.count += 1;
+            counter.count
+        };
+
+        let sync_threshold = ((tier.limit as f64) * SYNC_FRACTION) as u64;
+        if local_count < sync_threshold {
+            return RateLimitStatus {
+                allowed: true,
+                limit: tier.limit,
+                remaining: tier.limit.saturating_sub(local_count),
+            };
+        }
+
+        match self.sync_with_redis(user_id, window, tier).await {
+            Some(authoritative_count) => RateLimitStatus {
+                allowed: authoritative_count <= tier.limit,
+                limit: tier.limit,
+                remaining: tier.limit.saturating_sub(authoritative_count),
+            },
+            None => RateLimitStatus {
+                allowed: local_count <= tier.limit,
+                limit: tier.limit,
+                remaining: tier.limit.saturating_sub(local_count),
+            },
+        }
+    }
+
+    async fn sync_with_redis(&self, user_id: &str, window: u64, tier: RateLimitTier) -> Option<u64> {
+        let client = self.redis.as_ref()?;
+        let mut conn = match client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Rate limiter: Redis unreachable, falling back to local counting: {}", e);
+                return None;
+            }
+        };
+
+        let redis_key = format!("ratelimit:{}:{}", user_id, window);
+
+        let count: u64 = match conn.incr(&redis_key, 1u64).await {
+            Ok(count) => count,
+            Err(e) => {
+                warn!("Rate limiter: Redis INCR failed, falling back to local counting: {}", e);
+                return None;
+            }
+        };
+
+        if count == 1 {
+            let _: Result<(), _> = conn.expire(&redis_key, tier.period_secs as i64).await;
+        }
+
+        Some(count)
+    }
+}
@@ -0,0 +1,160 @@
+pub mod vpngate;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, IpAddr as TlsIpAddr, ServerName};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio_rustls::TlsConnector;
+
+const PROBE_CONCURRENCY: usize = 20;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Result of actively probing a candidate node's advertised endpoint
+/// before trusting it enough to mark `is_online`, instead of blindly
+/// believing a provider's self-reported speed/uptime figures.
+pub struct ProbeOutcome {
+    pub is_online: bool,
+    pub avg_latency_ms: i32,
+}
+
+/// Accepts whatever certificate the endpoint presents. These are
+/// arbitrary third-party VPN hosts, not a PKI relationship we have any
+/// basis to verify — this probe only times how long TCP connect + a TLS
+/// handshake take, the way reqwest's connector measures connect time, not
+/// whether the certificate should be trusted for anything.
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<rustls::crypto::CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn probe_client_config() -> Arc<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = Arc::new(AcceptAnyCert(provider.clone()));
+
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("rustls default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+
+    Arc::new(config)
+}
+
+/// Measures TCP connect + TLS handshake time to each candidate's
+/// advertised endpoint, bounded by a semaphore so a batch with many dead
+/// nodes can't stall the rest of the sync. An endpoint that's missing or
+/// unparsable has nothing to dial, so it's treated as unreachable.
+pub async fn probe_all(endpoints: &[Option<String>]) -> Vec<ProbeOutcome> {
+    let semaphore = Arc::new(Semaphore::new(PROBE_CONCURRENCY));
+    let client_config = probe_client_config();
+
+    let futures = endpoints.iter().map(|endpoint| {
+        let semaphore = semaphore.clone();
+        let client_config = client_config.clone();
+        let endpoint = endpoint.clone();
+        async move {
+            let _permit = semaphore.acquire().await.expect("probe semaphore closed");
+            probe_one(endpoint.as_deref(), &client_config).await
+        }
+    });
+
+    futures::future::join_all(futures).await
+}
+
+async fn probe_one(endpoint: Option<&str>, client_config: &Arc<ClientConfig>) -> ProbeOutcome {
+    let Some(addr) = endpoint.and_then(|e| e.parse::<SocketAddr>().ok()) else {
+        return ProbeOutcome { is_online: false, avg_latency_ms: 0 };
+    };
+
+    let started = Instant::now();
+
+    let stream = match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => stream,
+        _ => return ProbeOutcome { is_online: false, avg_latency_ms: 0 },
+    };
+
+    // Best-effort: most public OpenVPN endpoints don't speak TLS on their
+    // VPN port at all, so a failed/timed-out handshake still leaves the
+    // node counted reachable off the TCP connect alone — only the
+    // measured latency grows by whatever time the attempt took.
+    let connector = TlsConnector::from(client_config.clone());
+    let server_name = ServerName::IpAddress(TlsIpAddr::from(addr.ip()));
+    let _ = tokio::time::timeout(PROBE_TIMEOUT, connector.connect(server_name, stream)).await;
+
+    ProbeOutcome {
+        is_online: true,
+        avg_latency_ms: started.elapsed().as_millis() as i32,
+    }
+}
+
+/// A public node as discovered by a `PublicProvider`, normalized so
+/// `start_vpngate_sync`'s upsert loop doesn't need to know anything
+/// provider-specific.
+pub struct ProviderNode {
+    /// Stable ID derived from the provider + the node's real address, so
+    /// repeated syncs update the same row instead of duplicating it.
+    pub id: String,
+    pub country_code: String,
+    pub available_bandwidth_mbps: i32,
+    pub protocols: Vec<String>,
+    /// Directly dialable `ip:port`, when the parsed config exposes one.
+    pub external_endpoint: Option<String>,
+    /// Raw config blob as handed out by the provider (e.g. the embedded
+    /// OpenVPN profile), stored verbatim so a client can still fetch it.
+    pub public_config_data: String,
+    /// SHA-256 hash of the node's real IP, so it can be tracked/banned by
+    /// network without ever storing the address in the clear.
+    pub public_ip_hash: String,
+}
+
+/// A source of public (community-run) VPN nodes that can be synced into
+/// the `nodes` table alongside user-contributed ones. Implementing this
+/// is the only thing a new provider needs to do — `start_vpngate_sync`
+/// runs every registered provider the same way.
+#[axum::async_trait]
+pub trait PublicProvider: Send + Sync {
+    /// Short identifier stored in `public_provider_stats.provider_name`.
+    fn name(&self) -> &str;
+
+    /// Fetches and parses the provider's current node list.
+    async fn fetch(&self) -> anyhow::Result<Vec<ProviderNode>>;
+}
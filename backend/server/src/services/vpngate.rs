@@ -2,85 +2,216 @@ use sqlx::PgPool;
 use std::time::Duration;
 use base64::{Engine as _, engine::general_purpose};
 
+use super::{PublicProvider, ProviderNode};
+
 /// VPN Gate API URL (CSV Format)
 const VPNGATE_API_URL: &str = "http://www.vpngate.net/api/iphone/";
 
+/// Providers currently registered for `start_vpngate_sync` to pull from.
+/// Adding a new public source (e.g. a different CSV/JSON aggregator) is
+/// just implementing `PublicProvider` and listing it here — the sync loop
+/// and upsert logic below don't change.
+fn registered_providers() -> Vec<Box<dyn PublicProvider>> {
+    vec![Box::new(VpnGateProvider)]
+}
+
 pub async fn start_vpngate_sync(pool: PgPool) {
-    tracing::info!("Starting VPN Gate synchronization service...");
-    
+    tracing::info!("Starting public node provider synchronization service...");
+
     loop {
-        if let Err(e) = sync_nodes(&pool).await {
-            tracing::error!("VPN Gate sync failed: {}", e);
+        for provider in registered_providers() {
+            if let Err(e) = sync_provider(provider.as_ref(), &pool).await {
+                tracing::error!("{} sync failed: {}", provider.name(), e);
+            }
         }
-        
+
         // Wait for 1 hour before next sync
         tokio::time::sleep(Duration::from_secs(3600)).await;
     }
 }
 
-async fn sync_nodes(pool: &PgPool) -> anyhow::Result<()> {
-    tracing::info!("Fetching latest nodes from VPN Gate...");
-    
-    let response = reqwest::get(VPNGATE_API_URL).await?.text().await?;
-    
-    // Skip first two lines (header comment and column names)
-    let csv_content = response.lines()
-        .skip(1)
-        .collect::<Vec<_>>()
-        .join("\n");
-        
-    let mut rdr = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(csv_content.as_bytes());
+/// Starting reputation handed to a freshly-probed node that answered
+/// within the timeout. Deliberately neutral rather than the max score —
+/// `node_prober`'s regular EWMA sweep nudges it up/down from here once
+/// the node has a real track record, same as any other node.
+const INITIAL_REPUTATION: i32 = 70;
+
+async fn sync_provider(provider: &dyn PublicProvider, pool: &PgPool) -> anyhow::Result<()> {
+    tracing::info!("Fetching latest nodes from {}...", provider.name());
+
+    let nodes = provider.fetch().await?;
+
+    let endpoints: Vec<Option<String>> = nodes.iter().map(|n| n.external_endpoint.clone()).collect();
+    let outcomes = super::probe_all(&endpoints).await;
 
     let mut nodes_added = 0;
-    
-    for result in rdr.records() {
-        let record = result?;
-        
-        // CSV columns: #HostName,IP,Score,Ping,Speed,CountryLong,CountryShort,NumVpnSessions,Uptime,TotalUsers,TotalTraffic,LogType,Operator,Message,OpenVPN_ConfigData_Base64
-        if record.len() < 15 { continue; }
-        
-        let ip = &record[1];
-        let country_short = &record[6];
-        let speed = record[4].parse::<i32>().unwrap_or(0) / 1000000; // Convert to Mbps
-        let config_b64 = &record[14];
-        
-        // Generate a stable ID based on IP
-        let node_id = format!("vpngate_{}", ip.replace(".", "_"));
-        
-        // Insert or update node
+    let mut nodes_online = 0;
+
+    for (node, outcome) in nodes.iter().zip(outcomes.iter()) {
+        let protocols_json = serde_json::to_string(&node.protocols).unwrap_or_else(|_| "[]".to_string());
+        let reputation_score = if outcome.is_online { INITIAL_REPUTATION } else { 0 };
+
         sqlx::query(
-            r#"INSERT INTO nodes 
-               (id, node_group, is_public, country_code, available_bandwidth_mbps, 
-                protocols, public_config_data, is_online, public_ip_hash)
-               VALUES ($1, 'PUBLIC', TRUE, $2, $3, '["OpenVPN"]', $4, TRUE, $5)
+            r#"INSERT INTO nodes
+               (id, node_group, is_public, country_code, available_bandwidth_mbps,
+                protocols, public_config_data, external_endpoint, is_online,
+                public_ip_hash, avg_latency_ms, reputation_score)
+               VALUES ($1, 'PUBLIC', TRUE, $2, $3, $4, $5, $6, $7, $8, $9, $10)
                ON CONFLICT (id) DO UPDATE SET
-                   is_online = TRUE,
+                   is_online = $7,
                    available_bandwidth_mbps = $3,
-                   public_config_data = $4,
+                   protocols = $4,
+                   public_config_data = $5,
+                   external_endpoint = $6,
+                   avg_latency_ms = $9,
+                   reputation_score = $10,
                    last_heartbeat = CURRENT_TIMESTAMP,
-                   updated_at = CURRENT_TIMESTAMP"#
+                   updated_at = CURRENT_TIMESTAMP"#,
         )
-        .bind(&node_id)
-        .bind(country_short)
-        .bind(speed)
-        .bind(config_b64)
-        .bind(format!("hash_{}", node_id))
+        .bind(&node.id)
+        .bind(&node.country_code)
+        .bind(node.available_bandwidth_mbps)
+        .bind(&protocols_json)
+        .bind(&node.public_config_data)
+        .bind(&node.external_endpoint)
+        .bind(outcome.is_online)
+        .bind(&node.public_ip_hash)
+        .bind(outcome.avg_latency_ms)
+        .bind(reputation_score)
         .execute(pool)
         .await?;
-        
+
         nodes_added += 1;
-        if nodes_added >= 100 { break; } // Limit to 100 nodes for now
+        if outcome.is_online {
+            nodes_online += 1;
+        }
     }
 
-    tracing::info!("Successfully synced {} public nodes from VPN Gate", nodes_added);
-    
-    // Record stats
-    sqlx::query("INSERT INTO public_provider_stats (provider_name, total_nodes_found, status) VALUES ('VPN_GATE', $1, 'SUCCESS')")
+    tracing::info!(
+        "Successfully synced {} public nodes from {} ({} reachable)",
+        nodes_added,
+        provider.name(),
+        nodes_online
+    );
+
+    sqlx::query("INSERT INTO public_provider_stats (provider_name, total_nodes_found, status) VALUES ($1, $2, 'SUCCESS')")
+        .bind(provider.name())
         .bind(nodes_added as i32)
         .execute(pool)
         .await?;
 
     Ok(())
 }
+
+struct VpnGateProvider;
+
+#[axum::async_trait]
+impl PublicProvider for VpnGateProvider {
+    fn name(&self) -> &str {
+        "VPN_GATE"
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Vec<ProviderNode>> {
+        let response = reqwest::get(VPNGATE_API_URL).await?.text().await?;
+
+        // Skip first two lines (header comment and column names)
+        let csv_content = response.lines().skip(1).collect::<Vec<_>>().join("\n");
+
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(csv_content.as_bytes());
+
+        let mut nodes = Vec::new();
+
+        for result in rdr.records() {
+            let record = result?;
+
+            // CSV columns: #HostName,IP,Score,Ping,Speed,CountryLong,CountryShort,NumVpnSessions,Uptime,TotalUsers,TotalTraffic,LogType,Operator,Message,OpenVPN_ConfigData_Base64
+            if record.len() < 15 {
+                continue;
+            }
+
+            let ip = &record[1];
+            let country_short = &record[6];
+            let speed_mbps = record[4].parse::<i32>().unwrap_or(0) / 1_000_000;
+            let config_b64 = &record[14];
+
+            let Ok(config_bytes) = general_purpose::STANDARD.decode(config_b64.trim()) else {
+                tracing::warn!("VPN Gate: skipping {} (invalid base64 config)", ip);
+                continue;
+            };
+            let Ok(config_text) = String::from_utf8(config_bytes) else {
+                tracing::warn!("VPN Gate: skipping {} (non-UTF8 config)", ip);
+                continue;
+            };
+
+            let parsed = parse_openvpn_config(&config_text);
+
+            let node_id = format!("vpngate_{}", ip.replace('.', "_"));
+            let ip_salt = std::env::var("IP_HASH_SALT").unwrap_or_else(|_| "default_salt".to_string());
+            let public_ip_hash = crate::proxy_protocol::hash_client_ip(
+                ip.parse().unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED)),
+                &ip_salt,
+            );
+
+            nodes.push(ProviderNode {
+                id: node_id,
+                country_code: country_short.to_string(),
+                available_bandwidth_mbps: speed_mbps,
+                protocols: vec![parsed.protocol_label()],
+                external_endpoint: parsed.remote.map(|(host, port)| format!("{}:{}", host, port)),
+                public_config_data: config_b64.to_string(),
+                public_ip_hash,
+            });
+
+            if nodes.len() >= 100 {
+                break; // Limit to 100 nodes for now
+            }
+        }
+
+        Ok(nodes)
+    }
+}
+
+/// The handful of directives `.ovpn` profiles always carry that we care
+/// about: `proto udp/tcp`, `remote <host> <port>`, and the data cipher.
+struct ParsedOpenVpnConfig {
+    proto: Option<String>,
+    remote: Option<(String, u16)>,
+    #[allow(dead_code)]
+    cipher: Option<String>,
+}
+
+impl ParsedOpenVpnConfig {
+    fn protocol_label(&self) -> String {
+        match self.proto.as_deref() {
+            Some("udp") => "OpenVpnUdp".to_string(),
+            Some("tcp") | Some("tcp-client") => "OpenVpnTcp".to_string(),
+            _ => "OpenVPN".to_string(),
+        }
+    }
+}
+
+fn parse_openvpn_config(config: &str) -> ParsedOpenVpnConfig {
+    let mut proto = None;
+    let mut remote = None;
+    let mut cipher = None;
+
+    for line in config.lines() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("proto") => proto = parts.next().map(str::to_lowercase),
+            Some("remote") => {
+                if let (Some(host), Some(port)) = (parts.next(), parts.next()) {
+                    if let Ok(port) = port.parse() {
+                        remote = Some((host.to_string(), port));
+                    }
+                }
+            }
+            Some("cipher") => cipher = parts.next().map(str::to_string),
+            _ => {}
+        }
+    }
+
+    ParsedOpenVpnConfig { proto, remote, cipher }
+}
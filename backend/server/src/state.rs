@@ -1,14 +1,37 @@
+use std::sync::Arc;
+
 use sqlx::PgPool;
+use vpn_core::abuse::{AbuseDetector, AbuseThresholds};
+
+use crate::rate_limit::DeferredRateLimiter;
+use crate::ws_hub::NotificationHub;
 
 /// Shared application state accessible across all API handlers
 #[derive(Clone)]
 pub struct AppState {
     /// PostgreSQL connection pool (optional for testing/mocking)
     pub db: Option<PgPool>,
+    /// Registry of connected node notification sockets
+    pub ws_hub: NotificationHub,
+    /// Deferred per-user rate limiter for auth/credit endpoints
+    pub rate_limiter: Arc<DeferredRateLimiter>,
+    /// Shared abuse-detection state (bans, incidents, risk scores).
+    /// `AbuseDetector` shards its own internal locking (see its doc
+    /// comment), so handlers share one `Arc` directly instead of wrapping
+    /// it in a coarse mutex. Constructed empty here — `main` rehydrates it
+    /// from Postgres via `AbuseDetector::load_from` once the pool is
+    /// available, since that requires an `.await` this synchronous
+    /// constructor can't do.
+    pub abuse_detector: Arc<AbuseDetector>,
 }
 
 impl AppState {
     pub fn new(db: Option<PgPool>) -> Self {
-        Self { db }
+        Self {
+            db,
+            ws_hub: NotificationHub::new(),
+            rate_limiter: Arc::new(DeferredRateLimiter::from_env()),
+            abuse_detector: Arc::new(AbuseDetector::new(AbuseThresholds::default())),
+        }
     }
 }
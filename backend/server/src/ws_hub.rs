@@ -0,0 +1,59 @@
+//! In-memory WebSocket notification hub, following the same `id -> channel`
+//! registry pattern Vaultwarden uses for its notification hub.
+//!
+//! Registered nodes hold a persistent `/nodes/ws` socket instead of polling
+//! `/nodes/heartbeat`, and the server pushes events to them (e.g. when a
+//! client picks that node during discovery/connect) instead of the node
+//! having to poll for work.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::{mpsc, RwLock};
+
+/// Server -> node push events delivered over the notification socket.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeEvent {
+    ConnectionRequest {
+        session_id: String,
+        client_country: Option<String>,
+    },
+    GoOffline,
+    ReputationChanged {
+        new_score: i32,
+    },
+}
+
+/// Registry of `node_id -> sender` for currently-connected node sockets.
+#[derive(Clone, Default)]
+pub struct NotificationHub {
+    channels: Arc<RwLock<HashMap<String, mpsc::Sender<NodeEvent>>>>,
+}
+
+impl NotificationHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers (or replaces) the channel for a connected node.
+    pub async fn register(&self, node_id: String, tx: mpsc::Sender<NodeEvent>) {
+        self.channels.write().await.insert(node_id, tx);
+    }
+
+    /// Removes a node's channel, e.g. once its socket closes.
+    pub async fn unregister(&self, node_id: &str) {
+        self.channels.write().await.remove(node_id);
+    }
+
+    /// Pushes `event` to `node_id` if it currently holds an open socket.
+    /// Returns whether the node was reachable.
+    pub async fn notify(&self, node_id: &str, event: NodeEvent) -> bool {
+        let tx = { self.channels.read().await.get(node_id).cloned() };
+        match tx {
+            Some(tx) => tx.send(event).await.is_ok(),
+            None => false,
+        }
+    }
+}
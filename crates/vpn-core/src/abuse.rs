@@ -1,7 +1,24 @@
-use std::collections::HashMap;
-use std::net::IpAddr;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use ring::hmac;
 use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tokio::sync::RwLock;
+
+/// Hard cap on each source-address punishment map, so an attacker can't
+/// exhaust memory by rotating through addresses that each earn a
+/// punishment entry. When full, the entry with the earliest expiry is
+/// evicted to make room — it was about to age out soonest anyway.
+const MAX_PUNISHMENT_ENTRIES: usize = 65536;
+
+/// Number of independent shards `user_metrics`/`banned_users` are split
+/// into (see `AbuseDetector::shard_for`). Users hashing into different
+/// shards never contend for the same lock, so the hot per-packet-batch
+/// paths (`record_traffic`/`record_connection`) scale with concurrent
+/// *distinct* users rather than serializing behind one global lock.
+const NUM_SHARDS: usize = 16;
 
 /// Thresholds for detecting abusive behavior
 #[derive(Debug, Clone)]
@@ -11,6 +28,35 @@ pub struct AbuseThresholds {
     pub max_unique_ports_per_minute: u32,
     pub min_share_ratio: f64,
     pub ban_duration_secs: u64,
+    /// Connections from a single source IPv4 address allowed within a
+    /// rolling 60s window before it's punished. Independent of the
+    /// `user_id` tracking above, so an attacker can't evade it by simply
+    /// rotating accounts/tokens while reusing the same network origin.
+    pub max_connections_per_ip4: u32,
+    /// Same idea, but keyed on an IPv6 `/64` prefix (see
+    /// `ipv6_prefix_bits`) rather than a single address, since a /64 is
+    /// the smallest block an ISP typically hands a single customer.
+    pub max_connections_per_ip6_prefix: u32,
+    /// A second, shared rate check applied to both address families
+    /// alongside their own threshold above — whichever limit is hit
+    /// first triggers the punishment. Catches fast bursts even when a
+    /// family-specific allowance (e.g. a large CGNAT pool) is generous.
+    pub max_connection_frequency_per_min: u32,
+    /// Bits of the source IPv6 address kept when bucketing into
+    /// `conn_timestamps_by_ip6_prefix` / `punishments_by_ip6_prefix`.
+    pub ipv6_prefix_bits: u8,
+    /// How long a source IP stays in `punishments_by_ip4` /
+    /// `punishments_by_ip6_prefix` after tripping a threshold.
+    pub ip_punishment_duration_secs: u64,
+    /// Half-life of a single risk-score contribution: after this many
+    /// seconds its weight towards the decayed aggregate (see
+    /// `AbuseDetector::get_risk_score`) has halved. Lets a user who
+    /// tripped one moderate incident regain trust over time instead of
+    /// needing an explicit `reset_user_score` call.
+    pub half_life_secs: u64,
+    /// Decayed risk-score aggregate (0-100 scale, same as `get_risk_score`)
+    /// at or above which `report_abuse` automatically bans the user.
+    pub risk_ban_threshold: u8,
 }
 
 impl Default for AbuseThresholds {
@@ -21,6 +67,13 @@ impl Default for AbuseThresholds {
             max_unique_ports_per_minute: 100,
             min_share_ratio: 0.1, // Minimum 10% upload/download ratio
             ban_duration_secs: 3600, // 1 hour ban by default
+            max_connections_per_ip4: 120,
+            max_connections_per_ip6_prefix: 360, // one /64 can hide many real clients
+            max_connection_frequency_per_min: 200,
+            ipv6_prefix_bits: 64,
+            ip_punishment_duration_secs: 900, // 15 minutes
+            half_life_secs: 3600, // risk decays by half every hour
+            risk_ban_threshold: 80,
         }
     }
 }
@@ -32,6 +85,34 @@ pub enum AbuseType {
     LowShareRatio,
     SuspiciousConnections,
     DdosPattern,
+    /// A traffic/connection report's presented source IP+port didn't match
+    /// the token `ConnectionValidator` bound the session to — the
+    /// `user_id` is being replayed against a different network origin.
+    SpoofedIdentity,
+}
+
+impl AbuseType {
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            AbuseType::TrafficFlooding => "TRAFFIC_FLOODING",
+            AbuseType::PortScanning => "PORT_SCANNING",
+            AbuseType::LowShareRatio => "LOW_SHARE_RATIO",
+            AbuseType::SuspiciousConnections => "SUSPICIOUS_CONNECTIONS",
+            AbuseType::DdosPattern => "DDOS_PATTERN",
+            AbuseType::SpoofedIdentity => "SPOOFED_IDENTITY",
+        }
+    }
+
+    fn from_db_str(s: &str) -> Self {
+        match s {
+            "TRAFFIC_FLOODING" => AbuseType::TrafficFlooding,
+            "PORT_SCANNING" => AbuseType::PortScanning,
+            "LOW_SHARE_RATIO" => AbuseType::LowShareRatio,
+            "SUSPICIOUS_CONNECTIONS" => AbuseType::SuspiciousConnections,
+            "SPOOFED_IDENTITY" => AbuseType::SpoofedIdentity,
+            _ => AbuseType::DdosPattern,
+        }
+    }
 }
 
 /// Represents a recorded abuse incident
@@ -44,133 +125,676 @@ pub struct AbuseEvent {
     pub details: String,
 }
 
+/// A single severity contribution towards a user's decayed risk score,
+/// timestamped so its weight can be recomputed on access rather than baked
+/// into a monotonic counter (see `AbuseDetector::decayed_score`).
+#[derive(Debug, Clone)]
+struct ScoreContribution {
+    timestamp: Instant,
+    severity_weight: f64,
+}
+
+/// Expected distinct peers a `DestinationBloom` is sized for.
+const BLOOM_WIDTH: usize = 4096;
+/// Number of slots each insert touches (the Kirsch-Mitzenmacher `h1 + i*h2`
+/// scheme derives all of them from just two hashes).
+const BLOOM_HASHES: usize = 3;
+
+/// Fixed-width counting Bloom filter estimating how many distinct
+/// destination IPs a user has contacted in the current window, used in
+/// place of a `HashMap<IpAddr, u32>` whose memory scales with actual
+/// fan-out — under a real DDoS fan-out attack that map itself becomes a
+/// memory-exhaustion vector, while this stays constant size regardless.
+#[derive(Debug, Clone)]
+struct DestinationBloom {
+    counters: [u8; BLOOM_WIDTH],
+    /// Saturating estimate of distinct destinations seen this window.
+    /// Incremented only when an insert finds at least one of its `k`
+    /// slots still at zero, so hash collisions only ever cause an
+    /// undercount (a false "already seen"), never an overcount.
+    unique_estimate: u32,
+}
+
+impl Default for DestinationBloom {
+    fn default() -> Self {
+        Self { counters: [0; BLOOM_WIDTH], unique_estimate: 0 }
+    }
+}
+
+impl DestinationBloom {
+    fn hash(ip: IpAddr, seed: u64) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        seed.hash(&mut hasher);
+        ip.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Derives the `k` = `BLOOM_HASHES` slot indices for `ip` from two
+    /// independent 64-bit hashes via `h1 + i*h2`, avoiding the cost of `k`
+    /// separate hash functions.
+    fn indices(ip: IpAddr) -> [usize; BLOOM_HASHES] {
+        let h1 = Self::hash(ip, 0x9E37_79B9_7F4A_7C15);
+        let h2 = Self::hash(ip, 0xC2B2_AE3D_27D4_EB4F);
+        let mut idx = [0usize; BLOOM_HASHES];
+        for (i, slot) in idx.iter_mut().enumerate() {
+            *slot = (h1.wrapping_add((i as u64).wrapping_mul(h2)) as usize) % BLOOM_WIDTH;
+        }
+        idx
+    }
+
+    /// Records a connection to `ip`, returning the updated unique-count
+    /// estimate.
+    fn record(&mut self, ip: IpAddr) -> u32 {
+        let indices = Self::indices(ip);
+        let already_present = indices.iter().all(|&i| self.counters[i] > 0);
+        for i in indices {
+            self.counters[i] = self.counters[i].saturating_add(1);
+        }
+        if !already_present {
+            self.unique_estimate = self.unique_estimate.saturating_add(1);
+        }
+        self.unique_estimate
+    }
+
+    fn reset(&mut self) {
+        self.counters = [0; BLOOM_WIDTH];
+        self.unique_estimate = 0;
+    }
+}
+
 #[derive(Debug, Clone)]
 struct UserMetrics {
     traffic_windows: Vec<(Instant, u64)>,
-    contacted_ips: HashMap<IpAddr, u32>,
+    destination_bloom: DestinationBloom,
     contacted_ports: HashMap<u16, u32>,
     connection_count: u32,
     last_reset: Instant,
-    risk_score: u8,
+    score_contributions: Vec<ScoreContribution>,
 }
 
 impl Default for UserMetrics {
     fn default() -> Self {
         Self {
             traffic_windows: Vec::new(),
-            contacted_ips: HashMap::new(),
+            destination_bloom: DestinationBloom::default(),
             contacted_ports: HashMap::new(),
             connection_count: 0,
             last_reset: Instant::now(),
-            risk_score: 0,
+            score_contributions: Vec::new(),
         }
     }
 }
 
-/// Core engine for identifying malicious or abusive network activity
+/// IP-keyed connection-flood bookkeeping, grouped behind one lock separate
+/// from the per-user shards below. Origin-based defense is keyed on
+/// network address rather than `user_id`, so it doesn't benefit from (and
+/// isn't part of) the `user_id`-hash sharding scheme.
+#[derive(Default)]
+struct IpDefenseState {
+    /// Recent connection timestamps per source IPv4, independent of
+    /// `user_id` — gives origin-based defense even against an attacker
+    /// who rotates identities but reuses a network origin.
+    conn_timestamps_by_ip4: BTreeMap<Ipv4Addr, Vec<Instant>>,
+    /// Same, bucketed by a masked IPv6 `/64` (or `ipv6_prefix_bits`)
+    /// prefix rather than the full address.
+    conn_timestamps_by_ip6_prefix: BTreeMap<Ipv6Addr, Vec<Instant>>,
+    punishments_by_ip4: BTreeMap<Ipv4Addr, Instant>,
+    punishments_by_ip6_prefix: BTreeMap<Ipv6Addr, Instant>,
+}
+
+/// Binds a session to the source IP+port it was first observed from and
+/// issues a cheap keyed token (HMAC-SHA256 over `src_ip ∥ port`, via
+/// `ring`'s constant-time-verified HMAC) that must accompany every later
+/// traffic/credit report for that session. Stops a peer from replaying
+/// another user's `user_id` from a different network origin to inflate
+/// its own share-ratio accounting, without the cost of a stateful session
+/// table — the token itself carries everything needed to re-check it.
+struct ConnectionValidator {
+    key: hmac::Key,
+}
+
+impl ConnectionValidator {
+    /// Keyed on a fresh random secret, so tokens issued by one process
+    /// can't be replayed against another (and don't survive a restart,
+    /// which is fine — sessions are re-established and re-bound anyway).
+    fn new() -> Self {
+        let rng = crate::crypto::CryptoRng::new();
+        let secret: [u8; 32] = rng
+            .random_bytes()
+            .expect("OS RNG is expected to always succeed");
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        }
+    }
+
+    /// Binds a session to `(src_ip, src_port)`, returning the token the
+    /// caller must present with every later traffic/connection report for
+    /// that session.
+    fn issue_token(&self, src_ip: IpAddr, src_port: u16) -> String {
+        hex::encode(hmac::sign(&self.key, &Self::tuple_bytes(src_ip, src_port)).as_ref())
+    }
+
+    /// Verifies `token` was derived from exactly `(src_ip, src_port)` under
+    /// this validator's secret.
+    fn verify(&self, src_ip: IpAddr, src_port: u16, token: &str) -> bool {
+        let Ok(tag) = hex::decode(token) else {
+            return false;
+        };
+        hmac::verify(&self.key, &Self::tuple_bytes(src_ip, src_port), &tag).is_ok()
+    }
+
+    fn tuple_bytes(src_ip: IpAddr, src_port: u16) -> Vec<u8> {
+        let mut buf = match src_ip {
+            IpAddr::V4(v4) => v4.octets().to_vec(),
+            IpAddr::V6(v6) => v6.octets().to_vec(),
+        };
+        buf.extend_from_slice(&src_port.to_be_bytes());
+        buf
+    }
+}
+
+/// Core engine for identifying malicious or abusive network activity.
+///
+/// Every method takes `&self`: `user_metrics`/`banned_users` are split
+/// into `NUM_SHARDS` independent `RwLock`s keyed by `hash(user_id)` (see
+/// `shard_for`), so unrelated users never contend for the same lock, and
+/// `abuse_events`/`ip_defense` each get their own lock so recording an
+/// incident or an IP punishment never blocks a per-user metrics shard.
+/// This lets callers share one `Arc<AbuseDetector>` across axum handlers
+/// without a single coarse mutex serializing every request.
 pub struct AbuseDetector {
     thresholds: AbuseThresholds,
-    user_metrics: HashMap<String, UserMetrics>,
-    banned_users: HashMap<String, Instant>,
-    abuse_events: Vec<AbuseEvent>,
+    user_metrics: Vec<RwLock<HashMap<String, UserMetrics>>>,
+    /// Sharded with the exact same `shard_for` scheme as `user_metrics`,
+    /// so a given `user_id` always lands in the same shard index in both.
+    banned_users: Vec<RwLock<HashMap<String, Instant>>>,
+    abuse_events: RwLock<Vec<AbuseEvent>>,
+    ip_defense: RwLock<IpDefenseState>,
+    /// Binds each session's traffic/connection reports to the source
+    /// IP+port it was issued a token for (see `issue_session_token`),
+    /// stopping a peer from replaying another user's `user_id` to inflate
+    /// its own share-ratio accounting.
+    validator: ConnectionValidator,
+    /// Optional Postgres pool backing persistence of bans, incidents, and
+    /// risk scores across restarts (see `load_from`). `None` for the
+    /// in-memory-only CLI/test paths, mirroring `CreditManager`'s
+    /// `db: Option<PgPool>`.
+    db: Option<PgPool>,
 }
 
 impl AbuseDetector {
+    /// Creates a detector with no database pool — state is in-memory only
+    /// and lost on restart. Useful for tests and offline clients.
     pub fn new(thresholds: AbuseThresholds) -> Self {
         Self {
             thresholds,
-            user_metrics: HashMap::new(),
-            banned_users: HashMap::new(),
-            abuse_events: Vec::new(),
+            user_metrics: (0..NUM_SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            banned_users: (0..NUM_SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            abuse_events: RwLock::new(Vec::new()),
+            ip_defense: RwLock::new(IpDefenseState::default()),
+            validator: ConnectionValidator::new(),
+            db: None,
         }
     }
 
-    /// Logs traffic volume for a specific user and checks against quotas
-    pub fn record_traffic(&mut self, user_id: &str, bytes: u64) {
-        let metrics = self.user_metrics.entry(user_id.to_string()).or_default();
-        
-        let now = Instant::now();
-        metrics.traffic_windows.push((now, bytes));
-        
-        // Retain only the last 60 seconds of traffic data
-        metrics.traffic_windows.retain(|(timestamp, _)| {
-            now.duration_since(*timestamp) < Duration::from_secs(60)
+    /// Creates a detector backed by `db`, enabling `report_abuse`/`ban_user`
+    /// to persist what they record. Prefer `load_from` at startup so this
+    /// also rehydrates prior state instead of starting empty.
+    pub fn with_db(thresholds: AbuseThresholds, db: PgPool) -> Self {
+        Self {
+            db: Some(db),
+            ..Self::new(thresholds)
+        }
+    }
+
+    /// Picks which of the `NUM_SHARDS` locks a given `user_id` lives
+    /// behind, so two unrelated users are extremely unlikely to ever
+    /// block on each other's metrics or ban-state lock.
+    fn shard_for(user_id: &str) -> usize {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        user_id.hash(&mut hasher);
+        (hasher.finish() as usize) % NUM_SHARDS
+    }
+
+    fn user_metrics_shard(&self, user_id: &str) -> &RwLock<HashMap<String, UserMetrics>> {
+        &self.user_metrics[Self::shard_for(user_id)]
+    }
+
+    fn banned_users_shard(&self, user_id: &str) -> &RwLock<HashMap<String, Instant>> {
+        &self.banned_users[Self::shard_for(user_id)]
+    }
+
+    /// Binds a session to the client's observed `(src_ip, src_port)`,
+    /// returning a token the caller must present with every subsequent
+    /// `record_traffic`/`record_connection` call for that session.
+    /// Callers typically issue this once, right after authenticating a
+    /// connection, from the real source address (e.g. `ClientAddr`) rather
+    /// than anything self-reported by the client.
+    pub fn issue_session_token(&self, src_ip: IpAddr, src_port: u16) -> String {
+        self.validator.issue_token(src_ip, src_port)
+    }
+
+    /// Rehydrates bans, risk scores, and recent incident history from
+    /// Postgres, then returns a detector backed by `pool` so subsequent
+    /// activity keeps flushing back to it.
+    ///
+    /// Bans are stored as an absolute `banned_until` timestamp (`Instant`
+    /// can't be serialized — it isn't tied to wall-clock time), so each row
+    /// is converted back into an `Instant` by adding however much of the
+    /// ban remains to "now".
+    pub async fn load_from(pool: PgPool, thresholds: AbuseThresholds) -> Result<Self, sqlx::Error> {
+        let detector = Self::with_db(thresholds, pool.clone());
+
+        let now_instant = Instant::now();
+        let now_utc = Utc::now();
+
+        let ban_rows = sqlx::query(
+            "SELECT user_id, banned_until FROM abuse_bans WHERE banned_until > CURRENT_TIMESTAMP",
+        )
+        .fetch_all(&pool)
+        .await?;
+        for row in ban_rows {
+            let user_id: String = row.get("user_id");
+            let banned_until: DateTime<Utc> = row.get("banned_until");
+            let remaining = (banned_until - now_utc).to_std().unwrap_or_default();
+            detector
+                .banned_users_shard(&user_id)
+                .write()
+                .await
+                .insert(user_id.clone(), now_instant + remaining);
+        }
+
+        // `abuse_risk_scores` stores only the last computed decayed
+        // aggregate, not the individual contributions that produced it —
+        // so it's rehydrated as one fresh contribution dated "now", which
+        // then decays normally from here on.
+        let score_rows = sqlx::query("SELECT user_id, risk_score FROM abuse_risk_scores")
+            .fetch_all(&pool)
+            .await?;
+        for row in score_rows {
+            let user_id: String = row.get("user_id");
+            let risk_score: i32 = row.get("risk_score");
+            let weight = risk_score.clamp(0, 100) as f64;
+            if weight > 0.0 {
+                detector
+                    .user_metrics_shard(&user_id)
+                    .write()
+                    .await
+                    .entry(user_id.clone())
+                    .or_default()
+                    .score_contributions
+                    .push(ScoreContribution { timestamp: now_instant, severity_weight: weight });
+            }
+        }
+
+        let event_rows = sqlx::query(
+            "SELECT user_id, abuse_type, severity, timestamp, details FROM abuse_events
+             ORDER BY timestamp DESC LIMIT 500",
+        )
+        .fetch_all(&pool)
+        .await?;
+        let events = event_rows
+            .into_iter()
+            .rev()
+            .map(|row| AbuseEvent {
+                user_id: row.get("user_id"),
+                abuse_type: AbuseType::from_db_str(row.get::<String, _>("abuse_type").as_str()),
+                severity: row.get::<i32, _>("severity") as u8,
+                timestamp: row.get("timestamp"),
+                details: row.get("details"),
+            })
+            .collect();
+        *detector.abuse_events.write().await = events;
+
+        Ok(detector)
+    }
+
+    /// Fire-and-forget insert of `event` into `abuse_events`. No-op without
+    /// a database pool. Runs on a spawned task so `report_abuse` never
+    /// blocks on a DB round-trip.
+    fn persist_event(&self, event: &AbuseEvent) {
+        let Some(pool) = self.db.clone() else { return };
+        let event = event.clone();
+        tokio::spawn(async move {
+            if let Err(e) = sqlx::query(
+                "INSERT INTO abuse_events (user_id, abuse_type, severity, timestamp, details)
+                 VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(&event.user_id)
+            .bind(event.abuse_type.as_db_str())
+            .bind(event.severity as i32)
+            .bind(event.timestamp)
+            .bind(&event.details)
+            .execute(&pool)
+            .await
+            {
+                tracing::error!("Failed to persist abuse event: {}", e);
+            }
+        });
+    }
+
+    /// Fire-and-forget upsert of `user_id`'s current risk score.
+    fn persist_risk_score(&self, user_id: &str, score: u8) {
+        let Some(pool) = self.db.clone() else { return };
+        let user_id = user_id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = sqlx::query(
+                "INSERT INTO abuse_risk_scores (user_id, risk_score) VALUES ($1, $2)
+                 ON CONFLICT (user_id) DO UPDATE SET risk_score = $2",
+            )
+            .bind(&user_id)
+            .bind(score as i32)
+            .execute(&pool)
+            .await
+            {
+                tracing::error!("Failed to persist risk score for {}: {}", user_id, e);
+            }
         });
-        
-        let total_traffic: u64 = metrics.traffic_windows.iter().map(|(_, b)| b).sum();
+    }
+
+    /// Fire-and-forget upsert of `user_id`'s ban, storing the absolute
+    /// expiry so it survives a restart (see `load_from`).
+    fn persist_ban(&self, user_id: &str, duration_secs: u64) {
+        let Some(pool) = self.db.clone() else { return };
+        let user_id = user_id.to_string();
+        let banned_until = Utc::now() + chrono::Duration::seconds(duration_secs as i64);
+        tokio::spawn(async move {
+            if let Err(e) = sqlx::query(
+                "INSERT INTO abuse_bans (user_id, banned_until) VALUES ($1, $2)
+                 ON CONFLICT (user_id) DO UPDATE SET banned_until = $2",
+            )
+            .bind(&user_id)
+            .bind(banned_until)
+            .execute(&pool)
+            .await
+            {
+                tracing::error!("Failed to persist ban for {}: {}", user_id, e);
+            }
+        });
+    }
+
+    /// Fire-and-forget cleanup of a persisted ban/risk score after
+    /// `reset_user_score` clears them in memory.
+    fn persist_reset(&self, user_id: &str) {
+        let Some(pool) = self.db.clone() else { return };
+        let user_id = user_id.to_string();
+        tokio::spawn(async move {
+            let _ = sqlx::query("DELETE FROM abuse_bans WHERE user_id = $1")
+                .bind(&user_id)
+                .execute(&pool)
+                .await;
+            let _ = sqlx::query("UPDATE abuse_risk_scores SET risk_score = 0 WHERE user_id = $1")
+                .bind(&user_id)
+                .execute(&pool)
+                .await;
+        });
+    }
+
+    /// Logs traffic volume for a specific user and checks against quotas.
+    /// Takes only the relevant `user_metrics` shard's write lock — users
+    /// hashing into a different shard are never blocked by this call.
+    ///
+    /// `src_ip`/`src_port` and `token` must match whatever
+    /// `issue_session_token` handed back for this session; a mismatch
+    /// means the report is replaying someone else's `user_id` from a
+    /// different network origin, so it's recorded as `SpoofedIdentity` and
+    /// `false` is returned instead of trusting it — callers billing off
+    /// `bytes` (e.g. `/credits/sync`) must reject the report rather than
+    /// crediting it. Traffic flooding is still only a risk-score signal:
+    /// it's recorded, but doesn't by itself make this call return `false`.
+    pub async fn record_traffic(&self, user_id: &str, bytes: u64, src_ip: IpAddr, src_port: u16, token: &str) -> bool {
+        if !self.validator.verify(src_ip, src_port, token) {
+            self.report_abuse(
+                user_id,
+                AbuseType::SpoofedIdentity,
+                6,
+                format!("Traffic report from {}:{} failed source-binding check", src_ip, src_port),
+            )
+            .await;
+            return false;
+        }
+
+        let now = Instant::now();
+
+        let total_traffic = {
+            let mut shard = self.user_metrics_shard(user_id).write().await;
+            let metrics = shard.entry(user_id.to_string()).or_default();
+
+            metrics.traffic_windows.push((now, bytes));
+
+            // Retain only the last 60 seconds of traffic data
+            metrics.traffic_windows.retain(|(timestamp, _)| {
+                now.duration_since(*timestamp) < Duration::from_secs(60)
+            });
+
+            metrics.traffic_windows.iter().map(|(_, b)| b).sum::<u64>()
+        };
+
         if total_traffic > self.thresholds.max_traffic_per_minute {
-            self.report_abuse(user_id, AbuseType::TrafficFlooding, 8, 
-                format!("Excessive traffic: {} bytes/min", total_traffic));
+            self.report_abuse(user_id, AbuseType::TrafficFlooding, 8,
+                format!("Excessive traffic: {} bytes/min", total_traffic)).await;
         }
+
+        true
     }
 
-    /// Tracks connection targets to identify port scanning or connection flooding
-    pub fn record_connection(&mut self, user_id: &str, dest_ip: IpAddr, dest_port: u16) {
-        let metrics = self.user_metrics.entry(user_id.to_string()).or_default();
-        
-        let now = Instant::now();
-        
-        // Reset metrics window every minute
-        if now.duration_since(metrics.last_reset) > Duration::from_secs(60) {
-            metrics.contacted_ips.clear();
-            metrics.contacted_ports.clear();
-            metrics.connection_count = 0;
-            metrics.last_reset = now;
+    /// Tracks connection targets to identify port scanning or connection
+    /// flooding, and separately tracks `src_ip` (the connecting client's
+    /// real network origin) so rate limiting doesn't rely solely on the
+    /// `user_id`/identity layer.
+    ///
+    /// `dest` is `None` when the connection was routed to the central
+    /// fallback server rather than a matched P2P node (its hostname has no
+    /// concrete per-connection address worth tracking) — port-scan/fan-out
+    /// tracking is skipped in that case, but connection-count and
+    /// source-IP flood tracking still run, since both depend only on
+    /// `src_ip`/`user_id`.
+    ///
+    /// `src_port` and `token` must match whatever `issue_session_token`
+    /// handed back for this session; a mismatch means the report is
+    /// replaying someone else's `user_id` from a different network
+    /// origin, so it's recorded as `SpoofedIdentity` and excluded from
+    /// this user's connection tally instead of being trusted.
+    pub async fn record_connection(
+        &self,
+        user_id: &str,
+        src_ip: IpAddr,
+        src_port: u16,
+        dest: Option<(IpAddr, u16)>,
+        token: &str,
+    ) {
+        if !self.validator.verify(src_ip, src_port, token) {
+            self.report_abuse(
+                user_id,
+                AbuseType::SpoofedIdentity,
+                6,
+                format!("Connection report from {}:{} failed source-binding check", src_ip, src_port),
+            )
+            .await;
+            return;
         }
-        
-        *metrics.contacted_ips.entry(dest_ip).or_insert(0) += 1;
-        *metrics.contacted_ports.entry(dest_port).or_insert(0) += 1;
-        metrics.connection_count += 1;
-        
-        let num_ports = metrics.contacted_ports.len();
-        let num_connections = metrics.connection_count;
-        
+
+        let now = Instant::now();
+
+        let (num_ports, num_connections) = {
+            let mut shard = self.user_metrics_shard(user_id).write().await;
+            let metrics = shard.entry(user_id.to_string()).or_default();
+
+            // Reset metrics window every minute
+            if now.duration_since(metrics.last_reset) > Duration::from_secs(60) {
+                metrics.destination_bloom.reset();
+                metrics.contacted_ports.clear();
+                metrics.connection_count = 0;
+                metrics.last_reset = now;
+            }
+
+            if let Some((dest_ip, dest_port)) = dest {
+                metrics.destination_bloom.record(dest_ip);
+                *metrics.contacted_ports.entry(dest_port).or_insert(0) += 1;
+            }
+            metrics.connection_count += 1;
+
+            (metrics.contacted_ports.len(), metrics.connection_count)
+        };
+
         if num_ports > self.thresholds.max_unique_ports_per_minute as usize {
             self.report_abuse(user_id, AbuseType::PortScanning, 9,
-                format!("Port scan detected: {} unique ports", num_ports));
+                format!("Port scan detected: {} unique ports", num_ports)).await;
         }
-        
+
         if num_connections > self.thresholds.max_connections_per_minute {
             self.report_abuse(user_id, AbuseType::SuspiciousConnections, 7,
-                format!("Excessive connections: {} connections", num_connections));
+                format!("Excessive connections: {} connections", num_connections)).await;
+        }
+
+        self.record_source_connection(src_ip, now).await;
+    }
+
+    /// Masks an IPv6 address down to its leading `prefix_bits`, so every
+    /// address an ISP hands out of the same customer block (conventionally
+    /// a `/64`) shares one bucket instead of each evading limits alone.
+    fn mask_ipv6(ip: Ipv6Addr, prefix_bits: u8) -> Ipv6Addr {
+        let mask: u128 = if prefix_bits >= 128 {
+            u128::MAX
+        } else {
+            !0u128 << (128 - prefix_bits)
+        };
+        Ipv6Addr::from(u128::from(ip) & mask)
+    }
+
+    /// Prunes timestamps older than 60s, records `now`, and punishes the
+    /// source address if it tripped either the per-family or the shared
+    /// frequency threshold.
+    async fn record_source_connection(&self, src_ip: IpAddr, now: Instant) {
+        let mut ip_defense = self.ip_defense.write().await;
+        match src_ip {
+            IpAddr::V4(ip4) => {
+                let timestamps = ip_defense.conn_timestamps_by_ip4.entry(ip4).or_default();
+                Self::prune_and_push(timestamps, now);
+                let count = timestamps.len() as u32;
+
+                if count > self.thresholds.max_connections_per_ip4
+                    || count > self.thresholds.max_connection_frequency_per_min
+                {
+                    let expiry = now + Duration::from_secs(self.thresholds.ip_punishment_duration_secs);
+                    Self::punish(&mut ip_defense.punishments_by_ip4, ip4, expiry);
+                    tracing::warn!("Source IPv4 punished for connection flooding: {}", ip4);
+                }
+            }
+            IpAddr::V6(ip6) => {
+                let prefix = Self::mask_ipv6(ip6, self.thresholds.ipv6_prefix_bits);
+                let timestamps = ip_defense.conn_timestamps_by_ip6_prefix.entry(prefix).or_default();
+                Self::prune_and_push(timestamps, now);
+                let count = timestamps.len() as u32;
+
+                if count > self.thresholds.max_connections_per_ip6_prefix
+                    || count > self.thresholds.max_connection_frequency_per_min
+                {
+                    let expiry = now + Duration::from_secs(self.thresholds.ip_punishment_duration_secs);
+                    Self::punish(&mut ip_defense.punishments_by_ip6_prefix, prefix, expiry);
+                    tracing::warn!("Source IPv6 prefix punished for connection flooding: {}", prefix);
+                }
+            }
+        }
+    }
+
+    fn prune_and_push(timestamps: &mut Vec<Instant>, now: Instant) {
+        timestamps.retain(|t| now.duration_since(*t) < Duration::from_secs(60));
+        timestamps.push(now);
+    }
+
+    /// Inserts (or refreshes) a punishment entry, evicting the entry with
+    /// the earliest expiry first if the map is already at
+    /// `MAX_PUNISHMENT_ENTRIES` — bounding memory regardless of how many
+    /// distinct addresses an attacker rotates through.
+    fn punish<K: Ord + Copy>(map: &mut BTreeMap<K, Instant>, key: K, expiry: Instant) {
+        if map.len() >= MAX_PUNISHMENT_ENTRIES && !map.contains_key(&key) {
+            if let Some(oldest_key) = map
+                .iter()
+                .min_by_key(|(_, expiry)| **expiry)
+                .map(|(k, _)| *k)
+            {
+                map.remove(&oldest_key);
+            }
+        }
+        map.insert(key, expiry);
+    }
+
+    /// Checks whether `src` is currently punished, lazily evicting the
+    /// entry first if its punishment window has already elapsed.
+    pub async fn is_punished(&self, src: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut ip_defense = self.ip_defense.write().await;
+        match src {
+            IpAddr::V4(ip4) => Self::check_and_evict(&mut ip_defense.punishments_by_ip4, &ip4, now),
+            IpAddr::V6(ip6) => {
+                let prefix = Self::mask_ipv6(ip6, self.thresholds.ipv6_prefix_bits);
+                Self::check_and_evict(&mut ip_defense.punishments_by_ip6_prefix, &prefix, now)
+            }
         }
     }
 
-    /// Enforces the P2P economy by checking the sharing ratio
-    pub fn check_share_ratio(&mut self, user_id: &str, shared_bytes: u64, consumed_bytes: u64) {
+    fn check_and_evict<K: Ord>(map: &mut BTreeMap<K, Instant>, key: &K, now: Instant) -> bool {
+        match map.get(key) {
+            Some(expiry) if now < *expiry => true,
+            Some(_) => {
+                map.remove(key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Enforces the P2P economy by checking the sharing ratio. Returns
+    /// `false` when the ratio is flagged as abusive (and records the
+    /// event), so a caller billing off `consumed_bytes` (e.g.
+    /// `/credits/sync`) can reject the report instead of crediting it.
+    pub async fn check_share_ratio(&self, user_id: &str, shared_bytes: u64, consumed_bytes: u64) -> bool {
         if consumed_bytes == 0 {
-            return;
+            return true;
         }
-        
+
         let ratio = shared_bytes as f64 / consumed_bytes as f64;
-        
+
         if ratio < self.thresholds.min_share_ratio {
             self.report_abuse(user_id, AbuseType::LowShareRatio, 5,
-                format!("Low share ratio: {:.2}%", ratio * 100.0));
+                format!("Low share ratio: {:.2}%", ratio * 100.0)).await;
+            return false;
         }
+
+        true
     }
 
     /// Detects patterns indicative of participation in a DDoS attack
-    pub fn detect_ddos_pattern(&mut self, user_id: &str) -> bool {
-        let metrics = match self.user_metrics.get(user_id) {
-            Some(m) => m,
-            None => return false,
+    pub async fn detect_ddos_pattern(&self, user_id: &str) -> bool {
+        let (unique_ips, total_traffic) = {
+            let shard = self.user_metrics_shard(user_id).read().await;
+            match shard.get(user_id) {
+                Some(m) => (
+                    m.destination_bloom.unique_estimate as usize,
+                    m.traffic_windows.iter().map(|(_, b)| b).sum::<u64>(),
+                ),
+                None => return false,
+            }
         };
-        
-        let unique_ips = metrics.contacted_ips.len();
-        let total_traffic: u64 = metrics.traffic_windows.iter().map(|(_, b)| b).sum();
-        
+
         if unique_ips > 50 && total_traffic > 524_288_000 { // 500 MB/min
             self.report_abuse(user_id, AbuseType::DdosPattern, 10,
-                format!("DDoS pattern detected: {} IPs, {} bytes", unique_ips, total_traffic));
+                format!("DDoS pattern detected: {} IPs, {} bytes", unique_ips, total_traffic)).await;
             return true;
         }
-        
+
         false
     }
 
-    /// Internal helper to record violations and trigger bans
-    fn report_abuse(&mut self, user_id: &str, abuse_type: AbuseType, severity: u8, details: String) {
+    /// Internal helper to record violations and trigger bans. Appends a
+    /// timestamped severity contribution rather than saturating a counter,
+    /// so the effective risk score decays on its own (see
+    /// `decayed_score`) instead of requiring an explicit
+    /// `reset_user_score` to ever come back down.
+    async fn report_abuse(&self, user_id: &str, abuse_type: AbuseType, severity: u8, details: String) {
         let event = AbuseEvent {
             user_id: user_id.to_string(),
             abuse_type,
@@ -178,59 +802,108 @@ impl AbuseDetector {
             timestamp: chrono::Utc::now().timestamp(),
             details,
         };
-        
+
         tracing::warn!("Abuse detected: {:?}", event);
-        self.abuse_events.push(event);
-        
-        // Automatically ban for high-severity violations
-        if severity >= 8 {
-            self.ban_user(user_id);
-        }
-        
-        if let Some(metrics) = self.user_metrics.get_mut(user_id) {
-            metrics.risk_score = (metrics.risk_score + severity * 10).min(100);
+        self.persist_event(&event);
+        self.abuse_events.write().await.push(event);
+
+        let now = Instant::now();
+        let half_life_secs = self.thresholds.half_life_secs;
+        let decayed = {
+            let mut shard = self.user_metrics_shard(user_id).write().await;
+            let metrics = shard.entry(user_id.to_string()).or_default();
+            metrics.score_contributions.push(ScoreContribution {
+                timestamp: now,
+                severity_weight: severity as f64 * 10.0,
+            });
+            Self::prune_contributions(&mut metrics.score_contributions, half_life_secs, now);
+
+            Self::decayed_score(&metrics.score_contributions, half_life_secs, now)
+        };
+        self.persist_risk_score(user_id, decayed.min(100.0).round() as u8);
+
+        // Ban on the *decayed* aggregate crossing the threshold, rather
+        // than on any single severe event, so a user's standing reflects
+        // their recent pattern of behavior, not just their worst moment.
+        if decayed >= self.thresholds.risk_ban_threshold as f64 {
+            self.ban_user(user_id).await;
         }
     }
 
-    pub fn ban_user(&mut self, user_id: &str) {
+    /// Sum of each contribution's weight decayed by `0.5^(age / half_life)`
+    /// — the classic exponential-decay reputation formula, evaluated fresh
+    /// on every access rather than baked into a stored counter.
+    fn decayed_score(contributions: &[ScoreContribution], half_life_secs: u64, now: Instant) -> f64 {
+        let half_life = half_life_secs.max(1) as f64;
+        contributions
+            .iter()
+            .map(|c| {
+                let age_secs = now.duration_since(c.timestamp).as_secs_f64();
+                c.severity_weight * 0.5_f64.powf(age_secs / half_life)
+            })
+            .sum()
+    }
+
+    /// Drops contributions old enough that their decayed weight is
+    /// negligible (past ~10 half-lives, `0.5^10 ≈ 0.001x`), so a
+    /// long-lived account's history doesn't grow the vec forever.
+    fn prune_contributions(contributions: &mut Vec<ScoreContribution>, half_life_secs: u64, now: Instant) {
+        let max_age = Duration::from_secs(half_life_secs.saturating_mul(10).max(1));
+        contributions.retain(|c| now.duration_since(c.timestamp) < max_age);
+    }
+
+    pub async fn ban_user(&self, user_id: &str) {
         let ban_until = Instant::now() + Duration::from_secs(self.thresholds.ban_duration_secs);
-        self.banned_users.insert(user_id.to_string(), ban_until);
+        self.banned_users_shard(user_id)
+            .write()
+            .await
+            .insert(user_id.to_string(), ban_until);
         tracing::warn!("User banned: {} until {:?}", user_id, ban_until);
+        self.persist_ban(user_id, self.thresholds.ban_duration_secs);
     }
 
-    pub fn is_banned(&mut self, user_id: &str) -> bool {
-        if let Some(ban_until) = self.banned_users.get(user_id) {
+    pub async fn is_banned(&self, user_id: &str) -> bool {
+        let mut shard = self.banned_users_shard(user_id).write().await;
+        if let Some(ban_until) = shard.get(user_id) {
             if Instant::now() < *ban_until {
                 return true;
             } else {
-                self.banned_users.remove(user_id);
+                shard.remove(user_id);
             }
         }
         false
     }
 
-    pub fn get_risk_score(&self, user_id: &str) -> u8 {
-        self.user_metrics.get(user_id).map(|m| m.risk_score).unwrap_or(0)
+    /// Recomputes the user's current decayed risk score on access (0-100).
+    pub async fn get_risk_score(&self, user_id: &str) -> u8 {
+        let now = Instant::now();
+        let shard = self.user_metrics_shard(user_id).read().await;
+        shard
+            .get(user_id)
+            .map(|m| Self::decayed_score(&m.score_contributions, self.thresholds.half_life_secs, now).min(100.0).round() as u8)
+            .unwrap_or(0)
     }
 
-    pub fn get_abuse_history(&self, user_id: Option<&str>, limit: usize) -> Vec<AbuseEvent> {
-        let events: Vec<_> = if let Some(uid) = user_id {
-            self.abuse_events.iter()
+    pub async fn get_abuse_history(&self, user_id: Option<&str>, limit: usize) -> Vec<AbuseEvent> {
+        let events = self.abuse_events.read().await;
+        let filtered: Vec<_> = if let Some(uid) = user_id {
+            events.iter()
                 .filter(|e| e.user_id == uid)
                 .cloned()
                 .collect()
         } else {
-            self.abuse_events.clone()
+            events.clone()
         };
-        
-        events.into_iter().rev().take(limit).collect()
+
+        filtered.into_iter().rev().take(limit).collect()
     }
 
-    pub fn reset_user_score(&mut self, user_id: &str) {
-        if let Some(metrics) = self.user_metrics.get_mut(user_id) {
-            metrics.risk_score = 0;
+    pub async fn reset_user_score(&self, user_id: &str) {
+        if let Some(metrics) = self.user_metrics_shard(user_id).write().await.get_mut(user_id) {
+            metrics.score_contributions.clear();
         }
-        self.banned_users.remove(user_id);
+        self.banned_users_shard(user_id).write().await.remove(user_id);
+        self.persist_reset(user_id);
     }
 }
 
@@ -239,59 +912,191 @@ mod tests {
     use super::*;
     use std::net::Ipv4Addr;
 
-    #[test]
-    fn test_traffic_flooding_detection() {
-        let mut detector = AbuseDetector::new(AbuseThresholds {
+    #[tokio::test]
+    async fn test_traffic_flooding_detection() {
+        let detector = AbuseDetector::new(AbuseThresholds {
             max_traffic_per_minute: 1000,
             ..Default::default()
         });
 
-        detector.record_traffic("user1", 1500);
-        
-        assert_eq!(detector.abuse_events.len(), 1);
-        assert_eq!(detector.abuse_events[0].abuse_type, AbuseType::TrafficFlooding);
-        assert!(detector.is_banned("user1"));
+        let src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let token = detector.issue_session_token(src, 9000);
+        detector.record_traffic("user1", 1500, src, 9000, &token).await;
+
+        assert_eq!(detector.get_abuse_history(Some("user1"), 10).await.len(), 1);
+        assert_eq!(
+            detector.get_abuse_history(Some("user1"), 10).await[0].abuse_type,
+            AbuseType::TrafficFlooding
+        );
+        assert!(detector.is_banned("user1").await);
     }
 
-    #[test]
-    fn test_port_scanning_detection() {
-        let mut detector = AbuseDetector::new(AbuseThresholds {
+    #[tokio::test]
+    async fn test_port_scanning_detection() {
+        let detector = AbuseDetector::new(AbuseThresholds {
             max_unique_ports_per_minute: 10,
             ..Default::default()
         });
 
         let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        let src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let token = detector.issue_session_token(src, 9000);
         for port in 1..=15 {
-            detector.record_connection("scanner", ip, port);
+            detector.record_connection("scanner", src, 9000, Some((ip, port)), &token).await;
         }
 
-        assert!(detector.abuse_events.iter().any(|e| e.abuse_type == AbuseType::PortScanning));
+        assert!(detector
+            .get_abuse_history(Some("scanner"), 50)
+            .await
+            .iter()
+            .any(|e| e.abuse_type == AbuseType::PortScanning));
     }
 
-    #[test]
-    fn test_share_ratio_check() {
-        let mut detector = AbuseDetector::new(AbuseThresholds {
+    #[tokio::test]
+    async fn test_ddos_pattern_uses_bloom_unique_estimate() {
+        let detector = AbuseDetector::new(AbuseThresholds::default());
+
+        let src = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+        let token = detector.issue_session_token(src, 9000);
+
+        detector.record_traffic("flooder", 600_000_000, src, 9000, &token).await; // under the 1GB/min flooding threshold, above the DDoS one
+
+        for i in 0..80u32 {
+            let dest = IpAddr::V4(Ipv4Addr::new(10, 0, (i / 256) as u8, (i % 256) as u8));
+            detector.record_connection("flooder", src, 9000, Some((dest, 443)), &token).await;
+        }
+
+        // 80 distinct destinations, tracked via the constant-memory Bloom
+        // estimate rather than a HashMap<IpAddr, u32>, should still clear
+        // the `unique_ips > 50` heuristic.
+        assert!(detector.detect_ddos_pattern("flooder").await);
+    }
+
+    #[tokio::test]
+    async fn test_ip4_connection_flooding_punishes_source() {
+        let detector = AbuseDetector::new(AbuseThresholds {
+            max_connections_per_ip4: 5,
+            ..Default::default()
+        });
+
+        let src = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+        let dest = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        let token = detector.issue_session_token(src, 9000);
+
+        for i in 0..10 {
+            detector.record_connection("rotating_user", src, 9000, Some((dest, 1000 + i)), &token).await;
+        }
+
+        assert!(detector.is_punished(src).await);
+    }
+
+    #[tokio::test]
+    async fn test_ip6_prefix_shared_across_addresses() {
+        let detector = AbuseDetector::new(AbuseThresholds {
+            max_connections_per_ip6_prefix: 5,
+            ..Default::default()
+        });
+
+        let dest = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        // Different host bits, same /64 prefix.
+        for i in 0..10u16 {
+            let src = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, i));
+            let token = detector.issue_session_token(src, 9000);
+            detector.record_connection("user", src, 9000, Some((dest, 443)), &token).await;
+        }
+
+        let check_addr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0xffff, 0, 0, 1));
+        assert!(detector.is_punished(check_addr).await);
+    }
+
+    #[tokio::test]
+    async fn test_punishment_expires() {
+        let detector = AbuseDetector::new(AbuseThresholds {
+            max_connections_per_ip4: 1,
+            ip_punishment_duration_secs: 0,
+            ..Default::default()
+        });
+
+        let src = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9));
+        let dest = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        let token = detector.issue_session_token(src, 9000);
+        detector.record_connection("user", src, 9000, Some((dest, 443)), &token).await;
+        detector.record_connection("user", src, 9000, Some((dest, 444)), &token).await;
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert!(!detector.is_punished(src).await);
+    }
+
+    #[tokio::test]
+    async fn test_risk_score_decays_over_time() {
+        let detector = AbuseDetector::new(AbuseThresholds {
+            min_share_ratio: 0.5,
+            half_life_secs: 1,
+            risk_ban_threshold: 100, // high enough that this low-severity event never bans
+            ..Default::default()
+        });
+
+        detector.check_share_ratio("flaky_user", 100, 1000).await; // severity 5 -> weight 50
+        let fresh_score = detector.get_risk_score("flaky_user").await;
+        assert!(fresh_score >= 45, "expected close to 50 right after the event, got {}", fresh_score);
+
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        let decayed_score = detector.get_risk_score("flaky_user").await;
+        assert!(
+            decayed_score < 30,
+            "expected the score to have decayed well below 50 after 1.5 half-lives, got {}",
+            decayed_score
+        );
+    }
+
+    #[tokio::test]
+    async fn test_share_ratio_check() {
+        let detector = AbuseDetector::new(AbuseThresholds {
             min_share_ratio: 0.5,
             ..Default::default()
         });
 
-        detector.check_share_ratio("good_user", 500, 1000);
-        assert_eq!(detector.abuse_events.len(), 0);
+        assert!(detector.check_share_ratio("good_user", 500, 1000).await);
+        assert_eq!(detector.get_abuse_history(Some("good_user"), 10).await.len(), 0);
 
-        detector.check_share_ratio("bad_user", 100, 1000);
-        assert!(detector.abuse_events.iter().any(|e| e.abuse_type == AbuseType::LowShareRatio));
+        assert!(!detector.check_share_ratio("bad_user", 100, 1000).await);
+        assert!(detector
+            .get_abuse_history(Some("bad_user"), 10)
+            .await
+            .iter()
+            .any(|e| e.abuse_type == AbuseType::LowShareRatio));
     }
 
-    #[test]
-    fn test_ban_expiration() {
-        let mut detector = AbuseDetector::new(AbuseThresholds {
+    #[tokio::test]
+    async fn test_record_traffic_rejects_spoofed_token() {
+        let detector = AbuseDetector::new(AbuseThresholds::default());
+
+        let src = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let token = detector.issue_session_token(src, 9000);
+
+        // Report replayed from a different source than the token was
+        // issued to must be rejected, not just flagged.
+        assert!(!detector.record_traffic("user1", 1000, other, 9000, &token).await);
+        assert!(detector
+            .get_abuse_history(Some("user1"), 10)
+            .await
+            .iter()
+            .any(|e| e.abuse_type == AbuseType::SpoofedIdentity));
+
+        assert!(detector.record_traffic("user1", 1000, src, 9000, &token).await);
+    }
+
+    #[tokio::test]
+    async fn test_ban_expiration() {
+        let detector = AbuseDetector::new(AbuseThresholds {
             ban_duration_secs: 0,
             ..Default::default()
         });
 
-        detector.ban_user("test_user");
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        
-        assert!(!detector.is_banned("test_user"));
+        detector.ban_user("test_user").await;
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert!(!detector.is_banned("test_user").await);
     }
 }
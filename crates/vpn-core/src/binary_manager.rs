@@ -1,10 +1,21 @@
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 use tracing::info;
 
 use crate::error::{Result, VpnError};
 
+/// Sentinel `sha256` value for a [`BinarySpec`] whose upstream digest hasn't
+/// been pinned from the project's release page yet. Deliberately not valid
+/// hex so it can never collide with a real digest by accident; `download_archive`
+/// checks for it explicitly and fails with an actionable message instead of
+/// a confusing "SHA-256 mismatch" against a fake all-zero hash.
+const SHA256_NOT_YET_PINNED: &str = "UNVERIFIED: pin the real release sha256 before use";
+
 /// Specification for an external VPN binary (Shadowsocks, V2Ray, etc.)
 #[derive(Debug, Clone)]
 pub struct BinarySpec {
@@ -13,6 +24,17 @@ pub struct BinarySpec {
     pub download_url_linux: String,
     pub download_url_macos: String,
     pub download_url_windows: String,
+    /// Expected SHA-256 of the downloaded archive, hex-encoded
+    pub sha256: String,
+    /// Optional minisign/ed25519 detached signature verification
+    pub signature: Option<DetachedSignature>,
+}
+
+/// Pins an ed25519 public key alongside the base64 detached signature covering the archive bytes
+#[derive(Debug, Clone)]
+pub struct DetachedSignature {
+    pub public_key_base64: String,
+    pub signature_base64: String,
 }
 
 /// Automates detection and installation of required external VPN binaries
@@ -64,8 +86,13 @@ impl BinaryManager {
         })?;
 
         let download_url = Self::get_platform_url(spec)?;
-        
-        let binary_path = self.download_binary(&spec.name, download_url).await?;
+
+        let archive_path = self.download_archive(&spec.name, download_url, spec).await?;
+
+        let binary_path = self.extract_binary(&archive_path, spec).await?;
+
+        // Clean up the archive now that the binary has been extracted
+        let _ = fs::remove_file(&archive_path).await;
 
         // Ensure executable permissions on Unix systems
         #[cfg(unix)]
@@ -84,13 +111,13 @@ impl BinaryManager {
 
     fn get_platform_url(_spec: &BinarySpec) -> Result<String> {
         #[cfg(target_os = "linux")]
-        return Ok(spec.download_url_linux.clone());
+        return Ok(_spec.download_url_linux.clone());
 
         #[cfg(target_os = "macos")]
-        return Ok(spec.download_url_macos.clone());
+        return Ok(_spec.download_url_macos.clone());
 
         #[cfg(target_os = "windows")]
-        return Ok(spec.download_url_windows.clone());
+        return Ok(_spec.download_url_windows.clone());
 
         #[cfg(target_os = "android")]
         return Err(VpnError::InvalidConfig("External binaries not supported on Android yet".into()));
@@ -99,10 +126,13 @@ impl BinaryManager {
         Err(VpnError::InvalidConfig("Unsupported OS".into()))
     }
 
-    async fn download_binary(&self, name: &str, url: String) -> Result<PathBuf> {
+    /// Streams the archive to disk while incrementally hashing it, then verifies
+    /// the digest (and optional signature) before returning the path.
+    async fn download_archive(&self, name: &str, url: String, spec: &BinarySpec) -> Result<PathBuf> {
         info!("📥 Downloading from {}", url);
 
-        let response = reqwest::get(&url).await.map_err(|e| {
+        let client = crate::proxy_config::build_http_client()?;
+        let response = client.get(&url).send().await.map_err(|e| {
             VpnError::ConnectionFailed(format!("Download failed: {}", e))
         })?;
 
@@ -114,19 +144,188 @@ impl BinaryManager {
             )));
         }
 
-        let bytes = response.bytes().await.map_err(|e| {
-            VpnError::ConnectionFailed(format!("Failed to read bytes: {}", e))
+        let archive_name = url
+            .rsplit('/')
+            .next()
+            .filter(|s| !s.is_empty())
+            .unwrap_or(name);
+        let dest_path = self.install_dir.join(format!("{}.download", archive_name));
+
+        let mut hasher = Sha256::new();
+        let mut file = fs::File::create(&dest_path).await.map_err(|e| {
+            VpnError::InvalidConfig(format!("Failed to create {}: {}", dest_path.display(), e))
         })?;
 
-        let dest_path = self.install_dir.join(name);
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| {
+                VpnError::ConnectionFailed(format!("Failed to read bytes: {}", e))
+            })?;
+            hasher.update(&chunk);
+            file.write_all(&chunk).await.map_err(|e| {
+                VpnError::InvalidConfig(format!("Failed to write archive: {}", e))
+            })?;
+        }
+        file.flush().await.map_err(|e| VpnError::InvalidConfig(e.to_string()))?;
+
+        if spec.sha256 == SHA256_NOT_YET_PINNED {
+            let _ = fs::remove_file(&dest_path).await;
+            return Err(VpnError::IntegrityCheckFailed(format!(
+                "{} v{} has no pinned SHA-256 yet — refusing to install an unverified binary. \
+                 Pin the real digest published with the release before enabling auto-install.",
+                name, spec.version
+            )));
+        }
 
-        fs::write(&dest_path, bytes).await.map_err(|e| {
-            VpnError::InvalidConfig(format!("Failed to write file: {}", e))
-        })?;
+        let digest = hex::encode(hasher.finalize());
+        if !digest_matches(&digest, &spec.sha256) {
+            let _ = fs::remove_file(&dest_path).await;
+            return Err(VpnError::IntegrityCheckFailed(format!(
+                "SHA-256 mismatch for {}: expected {}, got {}",
+                name, spec.sha256, digest
+            )));
+        }
 
+        if let Some(sig) = &spec.signature {
+            self.verify_signature(&dest_path, sig).await?;
+        }
+
+        info!("🔐 Integrity verified ({})", digest);
         Ok(dest_path)
     }
 
+    /// Verifies a detached ed25519 signature over the archive bytes
+    async fn verify_signature(&self, archive_path: &Path, sig: &DetachedSignature) -> Result<()> {
+        use base64::{engine::general_purpose, Engine as _};
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let archive_bytes = fs::read(archive_path).await.map_err(|e| {
+            VpnError::IntegrityCheckFailed(format!("Failed to re-read archive: {}", e))
+        })?;
+
+        let public_key_bytes = general_purpose::STANDARD.decode(&sig.public_key_base64).map_err(|e| {
+            VpnError::IntegrityCheckFailed(format!("Invalid public key encoding: {}", e))
+        })?;
+        let signature_bytes = general_purpose::STANDARD.decode(&sig.signature_base64).map_err(|e| {
+            VpnError::IntegrityCheckFailed(format!("Invalid signature encoding: {}", e))
+        })?;
+
+        let public_key = VerifyingKey::try_from(public_key_bytes.as_slice()).map_err(|e| {
+            VpnError::IntegrityCheckFailed(format!("Malformed public key: {}", e))
+        })?;
+        let signature = Signature::try_from(signature_bytes.as_slice()).map_err(|e| {
+            VpnError::IntegrityCheckFailed(format!("Malformed signature: {}", e))
+        })?;
+
+        public_key
+            .verify(&archive_bytes, &signature)
+            .map_err(|_| VpnError::IntegrityCheckFailed("Detached signature verification failed".into()))
+    }
+
+    /// Detects the archive format and extracts only the named binary into `install_dir`
+    async fn extract_binary(&self, archive_path: &Path, spec: &BinarySpec) -> Result<PathBuf> {
+        let install_dir = self.install_dir.clone();
+        let archive_path = archive_path.to_path_buf();
+        let binary_name = spec.name.clone();
+
+        tokio::task::spawn_blocking(move || {
+            Self::extract_binary_blocking(&archive_path, &install_dir, &binary_name)
+        })
+        .await
+        .map_err(|e| VpnError::InvalidConfig(format!("Extraction task panicked: {}", e)))?
+    }
+
+    fn extract_binary_blocking(archive_path: &Path, install_dir: &Path, binary_name: &str) -> Result<PathBuf> {
+        let mut header = [0u8; 6];
+        {
+            let mut f = std::fs::File::open(archive_path)
+                .map_err(|e| VpnError::InvalidConfig(format!("Failed to open archive: {}", e)))?;
+            let read = f.read(&mut header).unwrap_or(0);
+            if read < header.len() {
+                header[read..].fill(0);
+            }
+        }
+
+        let dest_path = install_dir.join(binary_name);
+
+        if header.starts_with(b"PK\x03\x04") {
+            Self::extract_zip(archive_path, &dest_path, binary_name)
+        } else if &header[..6] == [0xFD, b'7', b'z', b'X', b'Z', 0x00].as_slice() {
+            Self::extract_tar_xz(archive_path, &dest_path, binary_name)
+        } else {
+            // Not an archive: the download is already the raw executable (e.g. hysteria's
+            // single-file releases), so install it as-is.
+            std::fs::copy(archive_path, &dest_path)
+                .map_err(|e| VpnError::InvalidConfig(format!("Failed to install raw binary: {}", e)))?;
+            Ok(dest_path)
+        }
+    }
+
+    fn extract_tar_xz(archive_path: &Path, dest_path: &Path, binary_name: &str) -> Result<PathBuf> {
+        let file = std::fs::File::open(archive_path)
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to open archive: {}", e)))?;
+        let decompressor = xz2::read::XzDecoder::new(file);
+        let mut archive = tar::Archive::new(decompressor);
+
+        let entries = archive
+            .entries()
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to read tar entries: {}", e)))?;
+
+        for entry in entries {
+            let mut entry = entry.map_err(|e| VpnError::InvalidConfig(format!("Corrupt tar entry: {}", e)))?;
+            let path = entry
+                .path()
+                .map_err(|e| VpnError::InvalidConfig(format!("Invalid tar entry path: {}", e)))?
+                .to_path_buf();
+
+            if path.file_name().map(|n| n == binary_name).unwrap_or(false) {
+                let mut out = std::fs::File::create(dest_path)
+                    .map_err(|e| VpnError::InvalidConfig(format!("Failed to create {}: {}", dest_path.display(), e)))?;
+                std::io::copy(&mut entry, &mut out)
+                    .map_err(|e| VpnError::InvalidConfig(format!("Failed to extract {}: {}", binary_name, e)))?;
+                return Ok(dest_path.to_path_buf());
+            }
+        }
+
+        Err(VpnError::InvalidConfig(format!(
+            "Executable '{}' not found inside archive {}",
+            binary_name,
+            archive_path.display()
+        )))
+    }
+
+    fn extract_zip(archive_path: &Path, dest_path: &Path, binary_name: &str) -> Result<PathBuf> {
+        let file = std::fs::File::open(archive_path)
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to open archive: {}", e)))?;
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|e| VpnError::InvalidConfig(format!("Invalid zip archive: {}", e)))?;
+
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| VpnError::InvalidConfig(format!("Corrupt zip entry: {}", e)))?;
+
+            let matches = entry
+                .enclosed_name()
+                .and_then(|p| p.file_name().map(|n| n == binary_name))
+                .unwrap_or(false);
+
+            if matches {
+                let mut out = std::fs::File::create(dest_path)
+                    .map_err(|e| VpnError::InvalidConfig(format!("Failed to create {}: {}", dest_path.display(), e)))?;
+                std::io::copy(&mut entry, &mut out)
+                    .map_err(|e| VpnError::InvalidConfig(format!("Failed to extract {}: {}", binary_name, e)))?;
+                return Ok(dest_path.to_path_buf());
+            }
+        }
+
+        Err(VpnError::InvalidConfig(format!(
+            "Executable '{}' not found inside archive {}",
+            binary_name,
+            archive_path.display()
+        )))
+    }
+
     /// Validates that the installed binary executes correctly
     async fn verify_binary(&self, path: &PathBuf) -> Result<()> {
         let output = Command::new(path)
@@ -162,7 +361,19 @@ impl BinaryManager {
     }
 }
 
-/// Registry of supported external binaries and their release locations
+/// Compares a freshly-computed digest against the pinned one, case-insensitively
+/// (some upstream release pages publish checksums in uppercase).
+fn digest_matches(computed: &str, expected: &str) -> bool {
+    computed.eq_ignore_ascii_case(expected)
+}
+
+/// Registry of supported external binaries and their release locations.
+///
+/// TODO: these ship with `sha256: SHA256_NOT_YET_PINNED` and no detached
+/// signature. Pin the real digest (and, where upstream publishes one, an
+/// ed25519 signature) from each project's release page before relying on
+/// `auto_install()` — `download_archive` refuses to proceed until this is
+/// done, so the gap fails closed rather than installing an unverified binary.
 pub fn get_binary_specs() -> Vec<BinarySpec> {
     vec![
         BinarySpec {
@@ -171,6 +382,8 @@ pub fn get_binary_specs() -> Vec<BinarySpec> {
             download_url_linux: "https://github.com/shadowsocks/shadowsocks-rust/releases/download/v1.18.0/shadowsocks-v1.18.0.x86_64-unknown-linux-gnu.tar.xz".to_string(),
             download_url_macos: "https://github.com/shadowsocks/shadowsocks-rust/releases/download/v1.18.0/shadowsocks-v1.18.0.x86_64-apple-darwin.tar.xz".to_string(),
             download_url_windows: "https://github.com/shadowsocks/shadowsocks-rust/releases/download/v1.18.0/shadowsocks-v1.18.0.x86_64-pc-windows-msvc.zip".to_string(),
+            sha256: SHA256_NOT_YET_PINNED.to_string(),
+            signature: None,
         },
         BinarySpec {
             name: "hysteria".to_string(),
@@ -178,6 +391,8 @@ pub fn get_binary_specs() -> Vec<BinarySpec> {
             download_url_linux: "https://github.com/apernet/hysteria/releases/download/app%2Fv2.2.3/hysteria-linux-amd64".to_string(),
             download_url_macos: "https://github.com/apernet/hysteria/releases/download/app%2Fv2.2.3/hysteria-darwin-amd64".to_string(),
             download_url_windows: "https://github.com/apernet/hysteria/releases/download/app%2Fv2.2.3/hysteria-windows-amd64.exe".to_string(),
+            sha256: SHA256_NOT_YET_PINNED.to_string(),
+            signature: None,
         },
         BinarySpec {
             name: "v2ray".to_string(),
@@ -185,6 +400,8 @@ pub fn get_binary_specs() -> Vec<BinarySpec> {
             download_url_linux: "https://github.com/v2fly/v2ray-core/releases/download/v5.13.0/v2ray-linux-64.zip".to_string(),
             download_url_macos: "https://github.com/v2fly/v2ray-core/releases/download/v5.13.0/v2ray-macos-64.zip".to_string(),
             download_url_windows: "https://github.com/v2fly/v2ray-core/releases/download/v5.13.0/v2ray-windows-64.zip".to_string(),
+            sha256: SHA256_NOT_YET_PINNED.to_string(),
+            signature: None,
         },
     ]
 }
@@ -196,12 +413,58 @@ mod tests {
     #[tokio::test]
     async fn test_binary_detection() {
         let manager = BinaryManager::new().unwrap();
-        
+
         // System binary check
         let has_ls = manager.is_installed("ls").await;
         assert!(has_ls);
-        
+
         let has_fake = manager.is_installed("worldvpn_fake_binary_xyz").await;
         assert!(!has_fake);
     }
+
+    #[test]
+    fn test_sha256_mismatch_rejected() {
+        let data = b"not the real archive";
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        let digest = hex::encode(hasher.finalize());
+        assert!(!digest_matches(&digest, SHA256_NOT_YET_PINNED));
+    }
+
+    #[test]
+    fn test_sha256_match_accepted() {
+        let data = b"the real archive bytes";
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        let digest = hex::encode(hasher.finalize());
+
+        // Exact match, and case-insensitive match (upstream pages often publish
+        // the checksum in uppercase).
+        assert!(digest_matches(&digest, &digest));
+        assert!(digest_matches(&digest, &digest.to_uppercase()));
+    }
+
+    #[test]
+    fn test_specs_flag_unpinned_digests() {
+        // Guards against silently shipping a real-looking but unverified digest:
+        // every spec must either carry the explicit "not yet pinned" sentinel or
+        // a real 64-char hex SHA-256, never anything in between (e.g. the old
+        // all-zero placeholder, which was invalid hex and read as a real digest).
+        for spec in get_binary_specs() {
+            if spec.sha256 == SHA256_NOT_YET_PINNED {
+                continue;
+            }
+            assert_eq!(
+                spec.sha256.len(),
+                64,
+                "{} has a sha256 that is neither the unpinned sentinel nor 64 hex chars",
+                spec.name
+            );
+            assert!(
+                spec.sha256.chars().all(|c| c.is_ascii_hexdigit()),
+                "{} has a non-hex sha256",
+                spec.name
+            );
+        }
+    }
 }
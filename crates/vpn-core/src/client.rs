@@ -2,6 +2,9 @@
 //!
 //! Gère l'authentification et l'obtention des configurations VPN depuis le serveur.
 
+use std::net::IpAddr;
+use std::sync::Arc;
+
 use crate::error::{Result, VpnError};
 use crate::protocol::VpnProtocol;
 use serde::{Deserialize, Serialize};
@@ -32,17 +35,65 @@ pub struct ConnectionInfo {
 #[derive(Deserialize, Debug)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user_id: String,
     pub username: String,
 }
 
+#[derive(Serialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+/// Result of `connect()`. `renewed_tokens` is `Some` when the initial
+/// request hit a 401 and `connect()` transparently refreshed: the server
+/// rotates refresh tokens on use, so the one the caller passed in is now
+/// burned and `renewed_tokens` must be persisted or the next reconnect
+/// will fail to re-authenticate.
+#[derive(Debug)]
+pub struct ConnectResult {
+    pub info: ConnectionInfo,
+    pub renewed_tokens: Option<LoginResponse>,
+}
+
 impl VpnApiClient {
-    /// Crée un nouveau client
-    pub fn new(base_url: String) -> Self {
-        Self {
+    /// Crée un nouveau client, en respectant un éventuel proxy sortant
+    /// configuré via `ALL_PROXY`/`HTTPS_PROXY` (socks5:// ou http://)
+    pub fn new(base_url: String) -> Result<Self> {
+        Ok(Self {
             base_url,
-            client: reqwest::Client::new(),
-        }
+            client: crate::proxy_config::build_http_client()?,
+        })
+    }
+
+    /// Crée un client routé à travers un proxy sortant explicite (HTTP
+    /// CONNECT, avec Basic ou Bearer `Proxy-Authorization`, ou SOCKS5)
+    /// plutôt qu'un proxy détecté depuis l'environnement, pour les réseaux
+    /// d'entreprise qui n'exposent que ce chemin vers le serveur de contrôle.
+    pub fn with_proxy(base_url: String, proxy: crate::proxy_config::ProxyConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .proxy(proxy.to_reqwest_proxy()?)
+            .build()
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to build proxied HTTP client: {}", e)))?;
+
+        Ok(Self { base_url, client })
+    }
+
+    /// Crée un client dont la résolution DNS du nom d'hôte de l'API passe
+    /// par DNS-over-HTTPS plutôt que par le résolveur système, pour éviter
+    /// de révéler au réseau local l'adresse du serveur de contrôle avant
+    /// même qu'un tunnel n'existe. `doh_endpoint` est l'URL complète du
+    /// serveur DoH (ex: `https://dns.google/dns-query`) ; `bootstrap_ips`
+    /// fixe les IP de ce serveur afin qu'aucune résolution en clair ne soit
+    /// nécessaire, même pour joindre le résolveur DoH lui-même.
+    pub fn with_doh(base_url: String, doh_endpoint: String, bootstrap_ips: Vec<IpAddr>) -> Result<Self> {
+        let resolver = Arc::new(crate::doh::DohResolver::new(doh_endpoint, bootstrap_ips)?);
+        let client = reqwest::Client::builder()
+            .dns_resolver(resolver)
+            .build()
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to build DoH-backed HTTP client: {}", e)))?;
+
+        Ok(Self { base_url, client })
     }
 
     /// Login et récupération du JWT
@@ -74,16 +125,47 @@ impl VpnApiClient {
         Ok(login_info)
     }
 
-    /// Demande une connexion VPN au serveur (avec JWT)
+    /// Échange un refresh token (à usage unique, rotatif côté serveur)
+    /// contre un nouveau JWT d'accès et son propre refresh token.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<LoginResponse> {
+        let url = format!("{}/auth/refresh", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&RefreshRequest { refresh_token: refresh_token.to_string() })
+            .send()
+            .await
+            .map_err(|e| VpnError::ConnectionFailed(format!("Erreur refresh API: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(VpnError::AuthFailed);
+        }
+
+        response
+            .json::<LoginResponse>()
+            .await
+            .map_err(|e| VpnError::Internal(format!("Invalid refresh response: {}", e)))
+    }
+
+    /// Demande une connexion VPN au serveur (avec JWT). Si le serveur
+    /// répond 401 (token d'accès expiré), tente une seule fois un refresh
+    /// via `refresh_token` puis rejoue la requête avec le nouveau JWT, afin
+    /// qu'une session en cours survive à l'expiration du token d'accès. Le
+    /// refresh token étant à usage unique et rotatif côté serveur, le
+    /// nouveau couple de tokens est renvoyé dans `ConnectResult::renewed_tokens`
+    /// plutôt que silencieusement abandonné : l'appelant doit le persister
+    /// pour que la reconnexion suivante reste possible.
     pub async fn connect(
         &self,
         protocol: VpnProtocol,
         username: String,
         public_key: Option<String>,
         token: &str, // JWT token
-    ) -> Result<ConnectionInfo> {
+        refresh_token: &str,
+    ) -> Result<ConnectResult> {
         let url = format!("{}/vpn/connect", self.base_url);
-        
+
         let payload = ConnectRequest {
             protocol,
             username,
@@ -98,6 +180,31 @@ impl VpnApiClient {
             .await
             .map_err(|e| VpnError::ConnectionFailed(format!("Erreur connexion API: {}", e)))?;
 
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            let renewed = self.refresh(refresh_token).await?;
+
+            let retry = self.client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", renewed.token))
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|e| VpnError::ConnectionFailed(format!("Erreur connexion API: {}", e)))?;
+
+            if !retry.status().is_success() {
+                return Err(VpnError::ConnectionFailed(format!("API Error: {}", retry.status())));
+            }
+
+            let info = retry
+                .json::<ConnectionInfo>()
+                .await
+                .map_err(|e| VpnError::Internal(format!("Invalid response: {}", e)))?;
+
+            return Ok(ConnectResult {
+                info,
+                renewed_tokens: Some(renewed),
+            });
+        }
 
         if !response.status().is_success() {
             return Err(VpnError::ConnectionFailed(format!("API Error: {}", response.status())));
@@ -108,6 +215,9 @@ impl VpnApiClient {
             .await
             .map_err(|e| VpnError::Internal(format!("Invalid response: {}", e)))?;
 
-        Ok(info)
+        Ok(ConnectResult {
+            info,
+            renewed_tokens: None,
+        })
     }
 }
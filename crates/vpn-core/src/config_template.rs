@@ -0,0 +1,96 @@
+//! Template-driven rendering of per-protocol config directives (cipher,
+//! auth digest, DNS, routes, kill-switch), so `OpenVpnTunnel`/`IKEv2Tunnel`
+//! no longer bake a single fixed full-tunnel configuration into a `format!`
+//! string. Mirrors the approach ProtonVPN's Rust client uses: render
+//! connection config from a typed template keyed off the selected server,
+//! instead of hand-writing one config string per protocol.
+
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+use crate::tunnel::ConnectionConfig;
+
+/// Resolved cipher/DNS/route/kill-switch directives for a connection,
+/// filling in the historical hardcoded defaults (AES-256-GCM/SHA256,
+/// full-tunnel, no DNS push) for any field the caller left unset.
+#[derive(Debug, Clone)]
+pub struct ConfigTemplate {
+    pub cipher: String,
+    pub auth_digest: String,
+    pub dns_servers: Vec<IpAddr>,
+    pub routes: Vec<IpNet>,
+    pub redirect_gateway: bool,
+    pub block_outside_dns: bool,
+}
+
+impl ConfigTemplate {
+    pub fn from_config(config: &ConnectionConfig) -> Self {
+        Self {
+            cipher: config.cipher.clone().unwrap_or_else(|| "AES-256-GCM".to_string()),
+            auth_digest: config.auth_digest.clone().unwrap_or_else(|| "SHA256".to_string()),
+            dns_servers: config.dns_servers.clone(),
+            routes: config.routes.clone(),
+            redirect_gateway: config.redirect_gateway,
+            block_outside_dns: config.block_outside_dns,
+        }
+    }
+
+    /// Renders the OpenVPN client-config directives for cipher/auth,
+    /// routing, DNS, and kill-switch. Appended verbatim to the `.ovpn` file
+    /// alongside the connection-level directives (`remote`, `proto`, etc.).
+    pub fn render_openvpn(&self) -> String {
+        let mut lines = vec![
+            format!("cipher {}", self.cipher),
+            format!("auth {}", self.auth_digest),
+        ];
+
+        if self.redirect_gateway || self.routes.is_empty() {
+            lines.push("redirect-gateway def1".to_string());
+        } else {
+            for route in &self.routes {
+                lines.push(format!("route {} {}", route.network(), route.netmask()));
+            }
+        }
+
+        for dns in &self.dns_servers {
+            lines.push(format!("dhcp-option DNS {}", dns));
+        }
+
+        if self.block_outside_dns {
+            lines.push("block-outside-dns".to_string());
+        }
+
+        lines.join("\n")
+    }
+
+    /// Renders the strongSwan `rightsubnet` value: full-tunnel (`0.0.0.0/0`)
+    /// unless specific split-tunnel routes were requested.
+    pub fn strongswan_rightsubnet(&self) -> String {
+        if self.redirect_gateway || self.routes.is_empty() {
+            "0.0.0.0/0".to_string()
+        } else {
+            self.routes
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+    }
+
+    /// Renders the strongSwan `leftdns` directive, or an empty string if no
+    /// DNS servers were requested (the OS resolver is left untouched).
+    pub fn strongswan_leftdns(&self) -> String {
+        if self.dns_servers.is_empty() {
+            String::new()
+        } else {
+            let servers = self
+                .dns_servers
+                .iter()
+                .map(|ip| ip.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("  leftdns={}\n", servers)
+        }
+    }
+}
@@ -1,7 +1,10 @@
+use crate::protocol::VpnProtocol;
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
 
 /// Credit unit representing traffic quota (1 credit typically = 1 MB)
 pub type Credits = i64;
@@ -12,10 +15,24 @@ pub struct CreditTransaction {
     pub user_id: String,
     pub amount: Credits,
     pub transaction_type: TransactionType,
+    /// Which balance bucket this transaction drew from (for a spend) or
+    /// added to (for an earn/bonus).
+    pub bucket: CreditBucket,
     pub timestamp: i64,
     pub description: String,
 }
 
+impl CreditTransaction {
+    /// Signed balance delta this transaction represents (`amount` itself is
+    /// always stored as an unsigned magnitude; the sign comes from the type).
+    fn signed_delta(&self) -> Credits {
+        match self.transaction_type {
+            TransactionType::Earned | TransactionType::Bonus => self.amount,
+            TransactionType::Spent | TransactionType::Penalty => -self.amount,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransactionType {
     Earned,   // From sharing bandwidth
@@ -24,11 +41,132 @@ pub enum TransactionType {
     Penalty,  // Abuse punishment
 }
 
-/// Manages user balances and P2P economy incentives
+impl TransactionType {
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            TransactionType::Earned => "EARNED",
+            TransactionType::Spent => "SPENT",
+            TransactionType::Bonus => "BONUS",
+            TransactionType::Penalty => "PENALTY",
+        }
+    }
+}
+
+/// Which balance a credit amount belongs to: bandwidth-earned credits, or
+/// purchased/bonus "premium" credits. Kept separate so promo credits can
+/// expire, purchases can be refunded, and reporting can show exactly how
+/// much paid balance a user has consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreditBucket {
+    Earned,
+    Premium,
+}
+
+impl CreditBucket {
+    fn as_db_str(&self) -> &'static str {
+        match self {
+            CreditBucket::Earned => "EARNED",
+            CreditBucket::Premium => "PREMIUM",
+        }
+    }
+}
+
+/// The two balances tracked per user. `total()` is what `minimum_credits_to_connect`
+/// is enforced against.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CreditBalance {
+    pub earned: Credits,
+    pub premium: Credits,
+}
+
+impl CreditBalance {
+    pub fn total(&self) -> Credits {
+        self.earned + self.premium
+    }
+}
+
+/// Order in which `record_consumed_traffic` draws down the two buckets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendPriority {
+    /// Spend bandwidth-earned credits before touching purchased/bonus ones.
+    EarnedFirst,
+    /// Spend purchased/bonus credits before touching bandwidth-earned ones.
+    PremiumFirst,
+}
+
+impl Default for SpendPriority {
+    fn default() -> Self {
+        SpendPriority::EarnedFirst
+    }
+}
+
+/// Draws `amount` down from `earned`/`premium` in `priority` order,
+/// splitting across both buckets if the first doesn't cover it. Returns
+/// each `(bucket, amount drawn from it)` pair actually used. Standalone
+/// (rather than a `CreditManager` method) so callers that track balances
+/// outside a `CreditBalance` — e.g. the backend's own Postgres-row based
+/// `/credits/sync` handler — can reuse the same splitting logic instead of
+/// re-implementing it.
+pub fn draw_from_buckets(
+    earned: &mut Credits,
+    premium: &mut Credits,
+    priority: SpendPriority,
+    amount: Credits,
+) -> Vec<(CreditBucket, Credits)> {
+    let (first, second) = match priority {
+        SpendPriority::EarnedFirst => (CreditBucket::Earned, CreditBucket::Premium),
+        SpendPriority::PremiumFirst => (CreditBucket::Premium, CreditBucket::Earned),
+    };
+
+    let mut remaining = amount;
+    let mut draws = Vec::new();
+
+    for bucket in [first, second] {
+        if remaining == 0 {
+            break;
+        }
+        let available = match bucket {
+            CreditBucket::Earned => &mut *earned,
+            CreditBucket::Premium => &mut *premium,
+        };
+        let take = remaining.min(*available);
+        if take > 0 {
+            *available -= take;
+            remaining -= take;
+            draws.push((bucket, take));
+        }
+    }
+
+    draws
+}
+
+/// Outcome of a spend: how much was drawn in total, and how much of that
+/// came specifically out of the premium bucket (surfaced to clients so they
+/// know exactly how much paid balance a sync actually consumed).
+#[derive(Debug, Clone, Copy)]
+pub struct SpendResult {
+    pub total_spent: Credits,
+    pub premium_used: Credits,
+}
+
+/// Default interval between write-behind flushes to Postgres.
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Manages user balances and P2P economy incentives.
+///
+/// Balances live in an in-memory `DashMap` so a read or update never holds a
+/// lock across an `.await` — each `record_*` call mutates the map and
+/// returns immediately, then buffers the resulting `CreditTransaction` in a
+/// pending queue. A background task (see `spawn_flush_task`) periodically
+/// drains that queue and writes the accumulated per-user deltas to Postgres
+/// in one batched transaction, so the API never blocks a request on a DB
+/// round-trip.
 pub struct CreditManager {
-    balances: Arc<RwLock<HashMap<String, Credits>>>,
+    balances: Arc<DashMap<String, CreditBalance>>,
     transactions: Arc<RwLock<Vec<CreditTransaction>>>,
+    pending: Arc<Mutex<Vec<CreditTransaction>>>,
     config: CreditConfig,
+    db: Option<sqlx::PgPool>,
 }
 
 #[derive(Debug, Clone)]
@@ -37,6 +175,8 @@ pub struct CreditConfig {
     pub bytes_per_credit: u64,
     pub share_multiplier: f64,
     pub minimum_credits_to_connect: Credits,
+    pub protocol_pricing: ProtocolPricing,
+    pub spend_priority: SpendPriority,
 }
 
 impl Default for CreditConfig {
@@ -46,90 +186,283 @@ impl Default for CreditConfig {
             bytes_per_credit: 1_048_576, // 1 MB per credit
             share_multiplier: 1.2, // 20% bonus for uploading/sharing
             minimum_credits_to_connect: 10,
+            protocol_pricing: ProtocolPricing::default(),
+            spend_priority: SpendPriority::default(),
+        }
+    }
+}
+
+/// Per-protocol credit pricing. Commodity protocols (plain WireGuard) are
+/// metered at the baseline `1 MB = 1 credit` rate; scarce anti-censorship
+/// capacity (VLESS/Trojan/Hysteria2) costs more per MB so operators can meter
+/// it separately from ordinary bandwidth.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolPricing {
+    /// Explicit per-protocol multiplier overrides, checked before the
+    /// stealth-derived default below.
+    pub overrides: HashMap<VpnProtocol, f64>,
+}
+
+impl ProtocolPricing {
+    /// Effective credit multiplier for `protocol`. Defaults to a blend of
+    /// `stealth_score` (the scarce resource being metered) tempered by
+    /// `performance_score` (cheap, high-performance protocols stay close to
+    /// baseline even if moderately stealthy), unless an explicit override is
+    /// configured.
+    pub fn multiplier(&self, protocol: VpnProtocol) -> f64 {
+        if let Some(rate) = self.overrides.get(&protocol) {
+            return *rate;
         }
+        1.0 + protocol.stealth_score() * protocol.performance_score() * 0.5
     }
 }
 
 impl CreditManager {
+    /// Creates a manager with no database pool — transactions are buffered
+    /// but never flushed. Useful for tests and for clients (CLI, Tauri GUI)
+    /// that only need the in-memory economy simulation.
     pub fn new(config: CreditConfig) -> Self {
         Self {
-            balances: Arc::new(RwLock::new(HashMap::new())),
+            balances: Arc::new(DashMap::new()),
+            transactions: Arc::new(RwLock::new(Vec::new())),
+            pending: Arc::new(Mutex::new(Vec::new())),
+            config,
+            db: None,
+        }
+    }
+
+    /// Creates a manager backed by `db`, enabling `flush_now` and
+    /// `spawn_flush_task` to persist buffered transactions to Postgres.
+    pub fn with_db(config: CreditConfig, db: sqlx::PgPool) -> Self {
+        Self {
+            balances: Arc::new(DashMap::new()),
             transactions: Arc::new(RwLock::new(Vec::new())),
+            pending: Arc::new(Mutex::new(Vec::new())),
             config,
+            db: Some(db),
         }
     }
 
-    /// Initializes a new user account with starting credits
+    /// Convenience wrapper around `spawn_flush_task` using
+    /// `DEFAULT_FLUSH_INTERVAL`.
+    pub fn spawn_default_flush_task(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        self.spawn_flush_task(DEFAULT_FLUSH_INTERVAL)
+    }
+
+    /// Spawns a background task that calls `flush_now` on a fixed interval.
+    /// Returns `None` without spawning if this manager has no database pool.
+    /// Callers should also invoke `flush_now` directly during graceful
+    /// shutdown to drain anything buffered since the last tick.
+    pub fn spawn_flush_task(self: &Arc<Self>, interval: Duration) -> Option<tokio::task::JoinHandle<()>> {
+        self.db.as_ref()?;
+        let manager = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                manager.flush_now().await;
+            }
+        }))
+    }
+
+    /// Initializes a new user account with starting credits, granted as
+    /// premium (non-bandwidth-earned) balance.
     pub async fn create_account(&self, user_id: String) {
-        let mut balances = self.balances.write().await;
-        balances.insert(user_id.clone(), self.config.initial_credits);
+        self.balances.insert(
+            user_id.clone(),
+            CreditBalance { earned: 0, premium: self.config.initial_credits },
+        );
 
-        let mut transactions = self.transactions.write().await;
-        transactions.push(CreditTransaction {
+        self.buffer_transaction(CreditTransaction {
             user_id,
             amount: self.config.initial_credits,
             transaction_type: TransactionType::Bonus,
+            bucket: CreditBucket::Premium,
             timestamp: chrono::Utc::now().timestamp(),
             description: "Initial credits".to_string(),
-        });
+        })
+        .await;
+    }
+
+    /// Reads the cached combined balance (earned + premium) — this is the
+    /// authoritative value even before pending transactions have been
+    /// flushed to Postgres.
+    pub fn latest_balance(&self, user_id: &str) -> Credits {
+        self.balances.get(user_id).map(|b| b.total()).unwrap_or(0)
     }
 
     pub async fn get_balance(&self, user_id: &str) -> Credits {
-        let balances = self.balances.read().await;
-        balances.get(user_id).copied().unwrap_or(0)
+        self.latest_balance(user_id)
+    }
+
+    /// Reads the cached per-bucket balance.
+    pub fn balance_breakdown(&self, user_id: &str) -> CreditBalance {
+        self.balances.get(user_id).map(|b| *b).unwrap_or_default()
     }
 
-    /// Awards credits based on shared traffic volume
-    pub async fn record_shared_traffic(&self, user_id: String, bytes: u64) -> Credits {
-        let credits = self.bytes_to_credits(bytes);
-        let earned = (credits as f64 * self.config.share_multiplier) as Credits;
+    /// Awards credits based on shared traffic volume, priced by which
+    /// protocol carried it (see `ProtocolPricing`). Always credited to the
+    /// earned bucket.
+    pub async fn record_shared_traffic(&self, user_id: String, bytes: u64, protocol: VpnProtocol) -> Credits {
+        let base_credits = self.bytes_to_credits(bytes);
+        let rate = self.config.protocol_pricing.multiplier(protocol);
+        let earned = (base_credits as f64 * self.config.share_multiplier * rate) as Credits;
 
-        let mut balances = self.balances.write().await;
-        *balances.entry(user_id.clone()).or_insert(0) += earned;
+        self.balances.entry(user_id.clone()).or_default().earned += earned;
 
-        let mut transactions = self.transactions.write().await;
-        transactions.push(CreditTransaction {
+        self.buffer_transaction(CreditTransaction {
             user_id,
             amount: earned,
             transaction_type: TransactionType::Earned,
+            bucket: CreditBucket::Earned,
             timestamp: chrono::Utc::now().timestamp(),
-            description: format!("Shared {} MB", bytes / 1_048_576),
-        });
+            description: format!("Shared {} MB via {} (rate {:.2}x)", bytes / 1_048_576, protocol, rate),
+        })
+        .await;
 
         earned
     }
 
-    /// Deducts credits based on consumed traffic volume
-    pub async fn record_consumed_traffic(&self, user_id: String, bytes: u64) -> Result<Credits, String> {
-        let credits = self.bytes_to_credits(bytes);
+    /// Deducts credits based on consumed traffic volume, priced by which
+    /// protocol carried it (see `ProtocolPricing`). Drawn from the earned
+    /// and premium buckets in `config.spend_priority` order, possibly
+    /// splitting across both — one `CreditTransaction` is buffered per
+    /// bucket actually drawn from.
+    pub async fn record_consumed_traffic(
+        &self,
+        user_id: String,
+        bytes: u64,
+        protocol: VpnProtocol,
+    ) -> Result<SpendResult, String> {
+        let base_credits = self.bytes_to_credits(bytes);
+        let rate = self.config.protocol_pricing.multiplier(protocol);
+        let credits = (base_credits as f64 * rate).round() as Credits;
+
+        // Compute the per-bucket draw in one shot against the DashMap entry —
+        // no write guard is held afterward, so there's nothing to hold across
+        // the buffering/flush I/O below.
+        let draws = {
+            let mut balance = self.balances.entry(user_id.clone()).or_default();
+            if balance.total() < credits {
+                return Err(format!(
+                    "Insufficient credits: {} required, {} available",
+                    credits, balance.total()
+                ));
+            }
+            self.draw(&mut balance, credits)
+        };
+
+        let mut premium_used = 0;
+        for (bucket, amount) in draws {
+            if bucket == CreditBucket::Premium {
+                premium_used += amount;
+            }
+            self.buffer_transaction(CreditTransaction {
+                user_id: user_id.clone(),
+                amount,
+                transaction_type: TransactionType::Spent,
+                bucket,
+                timestamp: chrono::Utc::now().timestamp(),
+                description: format!(
+                    "Consumed {} MB via {} (rate {:.2}x, {} bucket)",
+                    bytes / 1_048_576,
+                    protocol,
+                    rate,
+                    bucket.as_db_str(),
+                ),
+            })
+            .await;
+        }
+
+        Ok(SpendResult { total_spent: credits, premium_used })
+    }
+
+    /// Draws `amount` down from `balance` in `config.spend_priority` order.
+    /// See `draw_from_buckets` for the actual splitting logic.
+    fn draw(&self, balance: &mut CreditBalance, amount: Credits) -> Vec<(CreditBucket, Credits)> {
+        draw_from_buckets(&mut balance.earned, &mut balance.premium, self.config.spend_priority, amount)
+    }
+
+    /// Appends `tx` to both the full in-memory history (used by
+    /// `get_user_transactions`/`get_stats`) and the pending flush queue.
+    async fn buffer_transaction(&self, tx: CreditTransaction) {
+        self.pending.lock().await.push(tx.clone());
+        self.transactions.write().await.push(tx);
+    }
 
-        let mut balances = self.balances.write().await;
-        let current_balance = balances.get(&user_id).copied().unwrap_or(0);
+    /// Drains the pending transaction queue and writes the accumulated
+    /// per-user deltas to Postgres as a single batched transaction. No-op if
+    /// no database pool is configured or nothing is pending. On failure the
+    /// batch is re-queued so the next tick retries it.
+    pub async fn flush_now(&self) {
+        let Some(pool) = &self.db else { return };
+
+        let batch = {
+            let mut pending = self.pending.lock().await;
+            if pending.is_empty() {
+                return;
+            }
+            std::mem::take(&mut *pending)
+        };
+
+        // (earned_delta, premium_delta) per user.
+        let mut deltas: HashMap<String, (Credits, Credits)> = HashMap::new();
+        for tx in &batch {
+            let entry = deltas.entry(tx.user_id.clone()).or_insert((0, 0));
+            match tx.bucket {
+                CreditBucket::Earned => entry.0 += tx.signed_delta(),
+                CreditBucket::Premium => entry.1 += tx.signed_delta(),
+            }
+        }
 
-        if current_balance < credits {
-            return Err(format!(
-                "Insufficient credits: {} required, {} available",
-                credits, current_balance
-            ));
+        if let Err(e) = Self::flush_batch(pool, &deltas, &batch).await {
+            tracing::error!(
+                "Credit flush failed, re-queuing {} transactions: {}",
+                batch.len(),
+                e
+            );
+            self.pending.lock().await.extend(batch);
         }
+    }
 
-        *balances.entry(user_id.clone()).or_insert(0) -= credits;
+    async fn flush_batch(
+        pool: &sqlx::PgPool,
+        deltas: &HashMap<String, (Credits, Credits)>,
+        batch: &[CreditTransaction],
+    ) -> Result<(), sqlx::Error> {
+        let mut db_tx = pool.begin().await?;
+
+        for (user_id, (earned_delta, premium_delta)) in deltas {
+            sqlx::query(
+                "UPDATE users SET earned_credits = earned_credits + $1, premium_credits = premium_credits + $2 WHERE id = $3"
+            )
+            .bind(earned_delta)
+            .bind(premium_delta)
+            .bind(user_id)
+            .execute(&mut *db_tx)
+            .await?;
+        }
 
-        let mut transactions = self.transactions.write().await;
-        transactions.push(CreditTransaction {
-            user_id,
-            amount: credits,
-            transaction_type: TransactionType::Spent,
-            timestamp: chrono::Utc::now().timestamp(),
-            description: format!("Consumed {} MB", bytes / 1_048_576),
-        });
+        for tx in batch {
+            sqlx::query(
+                "INSERT INTO credit_transactions (id, user_id, amount, transaction_type, bucket, description) VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&tx.user_id)
+            .bind(tx.signed_delta())
+            .bind(tx.transaction_type.as_db_str())
+            .bind(tx.bucket.as_db_str())
+            .bind(&tx.description)
+            .execute(&mut *db_tx)
+            .await?;
+        }
 
-        Ok(credits)
+        db_tx.commit().await
     }
 
+    /// Enforced against the combined (earned + premium) balance.
     pub async fn can_connect(&self, user_id: &str) -> bool {
-        let balance = self.get_balance(user_id).await;
-        balance >= self.config.minimum_credits_to_connect
+        self.latest_balance(user_id) >= self.config.minimum_credits_to_connect
     }
 
     fn bytes_to_credits(&self, bytes: u64) -> Credits {
@@ -149,33 +482,60 @@ impl CreditManager {
 
     /// Aggregates system-wide economy stats
     pub async fn get_stats(&self) -> CreditStats {
-        let balances = self.balances.read().await;
         let transactions = self.transactions.read().await;
 
-        let total_users = balances.len();
-        let total_credits: Credits = balances.values().sum();
+        let total_users = self.balances.len();
+        let total_credits: Credits = self.balances.iter().map(|b| b.total()).sum();
         let total_transactions = transactions.len();
 
-        let earned: Credits = transactions
-            .iter()
-            .filter(|t| matches!(t.transaction_type, TransactionType::Earned))
-            .map(|t| t.amount)
-            .sum();
-
-        let spent: Credits = transactions
-            .iter()
-            .filter(|t| matches!(t.transaction_type, TransactionType::Spent))
-            .map(|t| t.amount)
-            .sum();
+        let sum_by_type = |wanted: &TransactionType| -> Credits {
+            transactions
+                .iter()
+                .filter(|t| std::mem::discriminant(&t.transaction_type) == std::mem::discriminant(wanted))
+                .map(|t| t.amount)
+                .sum()
+        };
 
         CreditStats {
             total_users,
             total_credits,
             total_transactions,
-            total_earned: earned,
-            total_spent: spent,
+            total_earned: sum_by_type(&TransactionType::Earned),
+            total_spent: sum_by_type(&TransactionType::Spent),
+            total_bonus: sum_by_type(&TransactionType::Bonus),
+            total_penalty: sum_by_type(&TransactionType::Penalty),
         }
     }
+
+    /// Per-user usage snapshot mirroring the SQL aggregation the
+    /// DB-backed `/credits/balance` endpoint runs, so in-memory and
+    /// DB-backed paths agree on shape.
+    pub async fn get_usage_breakdown(&self, user_id: &str) -> UsageBreakdown {
+        let transactions = self.transactions.read().await;
+
+        let sum_by_type = |wanted: &TransactionType| -> Credits {
+            transactions
+                .iter()
+                .filter(|t| t.user_id == user_id)
+                .filter(|t| std::mem::discriminant(&t.transaction_type) == std::mem::discriminant(wanted))
+                .map(|t| t.amount)
+                .sum()
+        };
+
+        UsageBreakdown {
+            total_requests: transactions.iter().filter(|t| t.user_id == user_id).count(),
+            earned: sum_by_type(&TransactionType::Earned),
+            spent: sum_by_type(&TransactionType::Spent),
+            bonus: sum_by_type(&TransactionType::Bonus),
+            penalty: sum_by_type(&TransactionType::Penalty),
+        }
+    }
+}
+
+impl Default for CreditManager {
+    fn default() -> Self {
+        Self::new(CreditConfig::default())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -185,6 +545,20 @@ pub struct CreditStats {
     pub total_transactions: usize,
     pub total_earned: Credits,
     pub total_spent: Credits,
+    pub total_bonus: Credits,
+    pub total_penalty: Credits,
+}
+
+/// Per-`TransactionType` usage breakdown for a single user, paired with a
+/// request count — the same shape the DB-backed `/credits/balance` endpoint
+/// computes via SQL aggregation.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UsageBreakdown {
+    pub total_requests: usize,
+    pub earned: Credits,
+    pub spent: Credits,
+    pub bonus: Credits,
+    pub penalty: Credits,
 }
 
 #[cfg(test)]
@@ -199,20 +573,46 @@ mod tests {
         let balance = manager.get_balance("alice").await;
         assert_eq!(balance, 1000);
 
-        let earned = manager.record_shared_traffic("alice".to_string(), 10_485_760).await;
+        let earned = manager.record_shared_traffic("alice".to_string(), 10_485_760, VpnProtocol::WireGuard).await;
         assert_eq!(earned, 12);
 
         let new_balance = manager.get_balance("alice").await;
         assert_eq!(new_balance, 1012);
 
-        let spent = manager.record_consumed_traffic("alice".to_string(), 5_242_880).await;
+        let spent = manager.record_consumed_traffic("alice".to_string(), 5_242_880, VpnProtocol::WireGuard).await;
         assert!(spent.is_ok());
-        assert_eq!(spent.unwrap(), 5);
+        let spent = spent.unwrap();
+        assert_eq!(spent.total_spent, 5);
+        // Earned-first is the default priority, and alice has 12 earned
+        // credits — enough to cover the 5-credit spend without touching premium.
+        assert_eq!(spent.premium_used, 0);
 
         let final_balance = manager.get_balance("alice").await;
         assert_eq!(final_balance, 1007);
     }
 
+    #[tokio::test]
+    async fn test_spend_priority_draws_from_premium_first() {
+        let manager = CreditManager::new(CreditConfig {
+            spend_priority: SpendPriority::PremiumFirst,
+            ..Default::default()
+        });
+
+        manager.create_account("erin".to_string()).await; // 1000 premium, 0 earned
+        manager.record_shared_traffic("erin".to_string(), 10_485_760, VpnProtocol::WireGuard).await; // +12 earned
+
+        let spent = manager
+            .record_consumed_traffic("erin".to_string(), 5_242_880, VpnProtocol::WireGuard)
+            .await
+            .unwrap();
+        assert_eq!(spent.total_spent, 5);
+        assert_eq!(spent.premium_used, 5);
+
+        let breakdown = manager.balance_breakdown("erin");
+        assert_eq!(breakdown.premium, 995);
+        assert_eq!(breakdown.earned, 12);
+    }
+
     #[tokio::test]
     async fn test_insufficient_credits() {
         let manager = CreditManager::new(CreditConfig {
@@ -222,7 +622,7 @@ mod tests {
 
         manager.create_account("bob".to_string()).await;
 
-        let result = manager.record_consumed_traffic("bob".to_string(), 10_485_760).await;
+        let result = manager.record_consumed_traffic("bob".to_string(), 10_485_760, VpnProtocol::WireGuard).await;
         assert!(result.is_err());
     }
 
@@ -237,7 +637,17 @@ mod tests {
         manager.create_account("charlie".to_string()).await;
         assert!(manager.can_connect("charlie").await);
 
-        let _ = manager.record_consumed_traffic("charlie".to_string(), 6_291_456).await;
+        let _ = manager.record_consumed_traffic("charlie".to_string(), 6_291_456, VpnProtocol::WireGuard).await;
         assert!(!manager.can_connect("charlie").await);
     }
+
+    #[tokio::test]
+    async fn test_flush_now_noop_without_db() {
+        let manager = CreditManager::new(CreditConfig::default());
+        manager.create_account("dave".to_string()).await;
+        // No database pool configured — flush_now must not panic and must
+        // leave the pending queue untouched for a later `with_db` upgrade path.
+        manager.flush_now().await;
+        assert_eq!(manager.get_balance("dave").await, 1000);
+    }
 }
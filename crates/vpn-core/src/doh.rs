@@ -0,0 +1,179 @@
+//! DNS-over-HTTPS (RFC 8484) resolver, so `VpnApiClient` can look up its
+//! control-server hostname without leaking that lookup to the local
+//! network's resolver before any tunnel exists — analogous to reqwest's own
+//! pluggable trust-dns resolver, just speaking DoH instead of plain UDP/53.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::error::{Result, VpnError};
+
+/// Resolves hostnames by POSTing wire-format DNS queries to a DoH endpoint
+/// instead of asking the system resolver. The endpoint's own host is pinned
+/// to `bootstrap_ips` at construction time (via `reqwest::ClientBuilder::resolve`)
+/// so there's no plaintext lookup anywhere in the chain.
+#[derive(Clone)]
+pub struct DohResolver {
+    doh_endpoint: String,
+    client: reqwest::Client,
+}
+
+impl DohResolver {
+    /// `doh_endpoint` is a full URL (e.g. `https://dns.google/dns-query`).
+    /// `bootstrap_ips` pins that URL's host to known-good IPs so resolving
+    /// the resolver itself never touches the system/network resolver.
+    pub fn new(doh_endpoint: String, bootstrap_ips: Vec<IpAddr>) -> Result<Self> {
+        let host = reqwest::Url::parse(&doh_endpoint)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .ok_or_else(|| VpnError::InvalidConfig(format!("Invalid DoH endpoint URL: {}", doh_endpoint)))?;
+
+        let mut builder = reqwest::Client::builder();
+        for ip in &bootstrap_ips {
+            builder = builder.resolve(&host, SocketAddr::new(*ip, 443));
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to build DoH bootstrap client: {}", e)))?;
+
+        Ok(Self { doh_endpoint, client })
+    }
+
+    async fn query(&self, host: &str) -> Result<Vec<IpAddr>> {
+        let query = build_dns_query(host);
+        let response = self
+            .client
+            .post(&self.doh_endpoint)
+            .header("content-type", "application/dns-message")
+            .header("accept", "application/dns-message")
+            .body(query)
+            .send()
+            .await
+            .map_err(|e| VpnError::ConnectionFailed(format!("DoH query to {} failed: {}", self.doh_endpoint, e)))?;
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| VpnError::ConnectionFailed(format!("DoH response read failed: {}", e)))?;
+
+        parse_dns_answers(&bytes)
+    }
+
+    /// Resolves `host` via DoH and pairs its first answer with `port`, for
+    /// callers that need a `SocketAddr` directly (e.g. resolving
+    /// `ConnectionInfo::server_endpoint`) rather than the `reqwest::dns::Resolve`
+    /// trait object this type also implements.
+    pub async fn resolve_socket_addr(&self, host: &str, port: u16) -> Result<SocketAddr> {
+        let ip = self.query(host).await?.into_iter().next().ok_or_else(|| {
+            VpnError::NetworkError(std::io::Error::new(std::io::ErrorKind::NotFound, "DoH resolution returned no addresses"))
+        })?;
+        Ok(SocketAddr::new(ip, port))
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let ips = resolver
+                .query(&host)
+                .await
+                .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?;
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Builds an RFC 1035 wire-format query for `host`'s A record. The
+/// transaction ID is zeroed per RFC 8484 section 4.1's recommendation for
+/// GET-cacheable, idempotent DoH requests.
+fn build_dns_query(host: &str) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(12 + host.len() + 6);
+    msg.extend_from_slice(&[0x00, 0x00]); // ID
+    msg.extend_from_slice(&[0x01, 0x00]); // flags: RD=1
+    msg.extend_from_slice(&[0x00, 0x01]); // QDCOUNT=1
+    msg.extend_from_slice(&[0x00, 0x00]); // ANCOUNT
+    msg.extend_from_slice(&[0x00, 0x00]); // NSCOUNT
+    msg.extend_from_slice(&[0x00, 0x00]); // ARCOUNT
+
+    for label in host.trim_end_matches('.').split('.') {
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0x00); // root label
+    msg.extend_from_slice(&[0x00, 0x01]); // QTYPE=A
+    msg.extend_from_slice(&[0x00, 0x01]); // QCLASS=IN
+    msg
+}
+
+/// Parses a DNS response's answer section and returns every A record's
+/// address (AAAA/CNAME records are skipped rather than followed).
+fn parse_dns_answers(msg: &[u8]) -> Result<Vec<IpAddr>> {
+    if msg.len() < 12 {
+        return Err(VpnError::ProtocolError("DoH response too short".to_string()));
+    }
+
+    let qdcount = u16::from_be_bytes([msg[4], msg[5]]) as usize;
+    let ancount = u16::from_be_bytes([msg[6], msg[7]]) as usize;
+
+    let mut offset = 12;
+    for _ in 0..qdcount {
+        offset = skip_name(msg, offset)?;
+        offset += 4; // QTYPE + QCLASS
+    }
+
+    let mut ips = Vec::new();
+    for _ in 0..ancount {
+        offset = skip_name(msg, offset)?;
+        if offset + 10 > msg.len() {
+            break;
+        }
+        let rtype = u16::from_be_bytes([msg[offset], msg[offset + 1]]);
+        let rdlength = u16::from_be_bytes([msg[offset + 8], msg[offset + 9]]) as usize;
+        offset += 10;
+        if offset + rdlength > msg.len() {
+            break;
+        }
+        if rtype == 1 && rdlength == 4 {
+            ips.push(IpAddr::V4(Ipv4Addr::new(
+                msg[offset],
+                msg[offset + 1],
+                msg[offset + 2],
+                msg[offset + 3],
+            )));
+        }
+        offset += rdlength;
+    }
+
+    if ips.is_empty() {
+        return Err(VpnError::NetworkError(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "DoH resolution returned no A records",
+        )));
+    }
+    Ok(ips)
+}
+
+/// Skips a DNS name starting at `offset`, honoring compression pointers
+/// (a length byte with its top two bits set means "pointer", followed by
+/// one more byte giving the rest of the 14-bit target offset). Returns the
+/// offset just past the name (or past the 2-byte pointer).
+fn skip_name(msg: &[u8], mut offset: usize) -> Result<usize> {
+    loop {
+        if offset >= msg.len() {
+            return Err(VpnError::ProtocolError("DNS name ran past end of message".to_string()));
+        }
+        let len = msg[offset];
+        if len & 0xC0 == 0xC0 {
+            return Ok(offset + 2);
+        } else if len == 0 {
+            return Ok(offset + 1);
+        } else {
+            offset += 1 + len as usize;
+        }
+    }
+}
@@ -31,4 +31,7 @@ pub enum VpnError {
 
     #[error("Abuse detection triggered: {0}")]
     AbuseDetected(String),
+
+    #[error("Binary integrity verification failed: {0}")]
+    IntegrityCheckFailed(String),
 }
@@ -0,0 +1,189 @@
+//! Lifecycle hook scripts, run around `ConnectionState` transitions — the
+//! same idea as vpncloud's "hook scripts to handle certain situations".
+//!
+//! Users point `HookConfig` at external scripts which get invoked on
+//! `connecting`/`connected`/`disconnecting`/`disconnected`/`error`, with
+//! context (protocol, server, assigned IP, byte counters) passed through
+//! environment variables. This lets a hook script flip firewall rules,
+//! switch DNS, or arm a kill-switch around the tunnel's lifecycle.
+
+use std::path::PathBuf;
+
+use tracing::{info, warn};
+
+use crate::error::{Result, VpnError};
+
+/// The well-defined points in a tunnel's lifecycle a hook can run at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Connecting,
+    Connected,
+    Disconnecting,
+    Disconnected,
+    IpChange,
+    Error,
+}
+
+impl HookEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::Connecting => "connecting",
+            HookEvent::Connected => "connected",
+            HookEvent::Disconnecting => "disconnecting",
+            HookEvent::Disconnected => "disconnected",
+            HookEvent::IpChange => "ip-change",
+            HookEvent::Error => "error",
+        }
+    }
+
+    /// Parses the `on-connect`/`on-disconnect`/`on-ip-change`/`on-error`
+    /// spelling used by the `--hook <event>=<path>` CLI flag.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "on-connect" => Some(HookEvent::Connected),
+            "on-disconnect" => Some(HookEvent::Disconnected),
+            "on-ip-change" => Some(HookEvent::IpChange),
+            "on-error" => Some(HookEvent::Error),
+            "on-connecting" => Some(HookEvent::Connecting),
+            "on-disconnecting" => Some(HookEvent::Disconnecting),
+            _ => None,
+        }
+    }
+}
+
+/// Context passed to a hook script as `WORLDVPN_*` environment variables.
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub protocol: Option<String>,
+    pub server_addr: Option<String>,
+    pub session_id: Option<String>,
+    pub assigned_ip: Option<String>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub error_message: Option<String>,
+}
+
+/// Paths to user-configured scripts, one per lifecycle event. Any event
+/// without a configured script is a no-op.
+#[derive(Debug, Clone, Default)]
+pub struct HookConfig {
+    pub on_connecting: Option<PathBuf>,
+    pub on_connect: Option<PathBuf>,
+    pub on_disconnecting: Option<PathBuf>,
+    pub on_disconnect: Option<PathBuf>,
+    pub on_ip_change: Option<PathBuf>,
+    pub on_error: Option<PathBuf>,
+}
+
+impl HookConfig {
+    /// Reads script paths from `WORLDVPN_HOOK_ON_*` environment variables,
+    /// leaving unset events disabled.
+    pub fn from_env() -> Self {
+        Self {
+            on_connecting: std::env::var("WORLDVPN_HOOK_ON_CONNECTING").ok().map(PathBuf::from),
+            on_connect: std::env::var("WORLDVPN_HOOK_ON_CONNECT").ok().map(PathBuf::from),
+            on_disconnecting: std::env::var("WORLDVPN_HOOK_ON_DISCONNECTING").ok().map(PathBuf::from),
+            on_disconnect: std::env::var("WORLDVPN_HOOK_ON_DISCONNECT").ok().map(PathBuf::from),
+            on_ip_change: std::env::var("WORLDVPN_HOOK_ON_IP_CHANGE").ok().map(PathBuf::from),
+            on_error: std::env::var("WORLDVPN_HOOK_ON_ERROR").ok().map(PathBuf::from),
+        }
+    }
+
+    /// Sets the script for a single event, overriding whatever `from_env`
+    /// (or a prior call) configured for it. Used to apply CLI flags
+    /// (`--hook <event>=<path>`) on top of the environment defaults.
+    pub fn set(&mut self, event: HookEvent, script: PathBuf) {
+        match event {
+            HookEvent::Connecting => self.on_connecting = Some(script),
+            HookEvent::Connected => self.on_connect = Some(script),
+            HookEvent::Disconnecting => self.on_disconnecting = Some(script),
+            HookEvent::Disconnected => self.on_disconnect = Some(script),
+            HookEvent::IpChange => self.on_ip_change = Some(script),
+            HookEvent::Error => self.on_error = Some(script),
+        }
+    }
+
+    fn script_for(&self, event: HookEvent) -> Option<&PathBuf> {
+        match event {
+            HookEvent::Connecting => self.on_connecting.as_ref(),
+            HookEvent::Connected => self.on_connect.as_ref(),
+            HookEvent::Disconnecting => self.on_disconnecting.as_ref(),
+            HookEvent::Disconnected => self.on_disconnect.as_ref(),
+            HookEvent::IpChange => self.on_ip_change.as_ref(),
+            HookEvent::Error => self.on_error.as_ref(),
+        }
+    }
+
+    /// Runs the script configured for `event`, if any, passing `ctx` through
+    /// the environment. A non-zero exit code is surfaced as a `VpnError` so
+    /// callers can abort the connection (e.g. a failing kill-switch hook).
+    pub async fn run(&self, event: HookEvent, ctx: &HookContext) -> Result<()> {
+        let Some(script) = self.script_for(event) else {
+            return Ok(());
+        };
+
+        let mut cmd = tokio::process::Command::new(script);
+        cmd.env("WORLDVPN_EVENT", event.as_str());
+        cmd.env("WORLDVPN_BYTES_SENT", ctx.bytes_sent.to_string());
+        cmd.env("WORLDVPN_BYTES_RECEIVED", ctx.bytes_received.to_string());
+
+        if let Some(ref protocol) = ctx.protocol {
+            cmd.env("WORLDVPN_PROTOCOL", protocol);
+        }
+        if let Some(ref server_addr) = ctx.server_addr {
+            cmd.env("WORLDVPN_SERVER_ADDR", server_addr);
+        }
+        if let Some(ref session_id) = ctx.session_id {
+            cmd.env("WORLDVPN_SESSION_ID", session_id);
+        }
+        if let Some(ref assigned_ip) = ctx.assigned_ip {
+            cmd.env("WORLDVPN_ASSIGNED_IP", assigned_ip);
+        }
+        if let Some(ref error_message) = ctx.error_message {
+            cmd.env("WORLDVPN_ERROR", error_message);
+        }
+
+        info!("Running {} hook script: {}", event.as_str(), script.display());
+
+        let status = cmd.status().await.map_err(|e| {
+            VpnError::Internal(format!("Failed to spawn {} hook script {}: {}", event.as_str(), script.display(), e))
+        })?;
+
+        if !status.success() {
+            warn!("{} hook script {} exited with {}", event.as_str(), script.display(), status);
+            return Err(VpnError::ConnectionFailed(format!(
+                "{} hook script exited with {}",
+                event.as_str(),
+                status
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_known_events() {
+        assert_eq!(HookEvent::parse("on-connect"), Some(HookEvent::Connected));
+        assert_eq!(HookEvent::parse("on-disconnect"), Some(HookEvent::Disconnected));
+        assert_eq!(HookEvent::parse("on-ip-change"), Some(HookEvent::IpChange));
+        assert_eq!(HookEvent::parse("on-error"), Some(HookEvent::Error));
+    }
+
+    #[test]
+    fn test_parse_unknown_event() {
+        assert_eq!(HookEvent::parse("on-reconnect"), None);
+    }
+
+    #[test]
+    fn test_set_overrides_script_for_event() {
+        let mut config = HookConfig::default();
+        config.set(HookEvent::Connected, PathBuf::from("/tmp/on-connect.sh"));
+        assert_eq!(config.on_connect.as_deref(), Some(std::path::Path::new("/tmp/on-connect.sh")));
+        assert!(config.on_disconnect.is_none());
+    }
+}
@@ -14,7 +14,7 @@ use tracing::{error, info, warn};
 use crate::{
     error::{Result, VpnError},
     protocol::VpnProtocol,
-    tunnel::{ConnectionConfig, Credentials, TunnelHandle, TunnelStats, VpnTunnel},
+    tunnel::{ConnectionConfig, Credentials, StreamTransport, TunnelHandle, TunnelStats, VpnTunnel},
 };
 
 #[derive(Serialize)]
@@ -24,6 +24,43 @@ struct HysteriaConfig {
     socks5: Socks5Config,
     bandwidth: BandwidthConfig,
     tls: TlsConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    masquerade: Option<MasqueradeConfig>,
+}
+
+/// QUIC has no native WebSocket/gRPC transport the way V2Ray does, so
+/// `StreamTransport`'s non-`Tcp` variants map onto Hysteria2's HTTP3
+/// masquerade instead: the handshake impersonates an ordinary HTTPS
+/// reverse proxy to `host` (when the variant carries one) rather than
+/// presenting a bare Hysteria QUIC fingerprint.
+#[derive(Serialize)]
+struct MasqueradeConfig {
+    #[serde(rename = "type")]
+    kind: String,
+    proxy: MasqueradeProxyConfig,
+}
+
+#[derive(Serialize)]
+struct MasqueradeProxyConfig {
+    url: String,
+    #[serde(rename = "rewriteHost")]
+    rewrite_host: bool,
+}
+
+fn masquerade_for(transport: &StreamTransport) -> Option<MasqueradeConfig> {
+    let host = match transport {
+        StreamTransport::Tcp => return None,
+        StreamTransport::Ws { host, .. } => host.clone(),
+        StreamTransport::Grpc { .. } | StreamTransport::HttpUpgrade => "www.bing.com".to_string(),
+    };
+
+    Some(MasqueradeConfig {
+        kind: "proxy".to_string(),
+        proxy: MasqueradeProxyConfig {
+            url: format!("https://{}", host),
+            rewrite_host: true,
+        },
+    })
 }
 
 #[derive(Serialize)]
@@ -102,8 +139,9 @@ impl VpnTunnel for HysteriaTunnel {
             },
             tls: TlsConfig {
                 insecure: true, // Pour le dev/test, à sécuriser en prod
-                sni: "google.com".into(), // Obfuscation basique
+                sni: config.sni.clone().unwrap_or_else(|| "google.com".to_string()),
             },
+            masquerade: masquerade_for(&config.stream_transport),
         };
 
         let config_yaml = serde_yaml::to_string(&hy_config)
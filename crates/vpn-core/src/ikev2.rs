@@ -1,38 +1,69 @@
 //! Implémentation IKEv2/IPsec (strongSwan)
 //!
 //! Utilise `charon-cmd` (client strongSwan) pour établir des tunnels IKEv2.
-//! Idéal pour mobile (iOS/Android natif) et roaming réseau (MOBIKE).
+//! Idéal pour mobile (iOS/Android natif) et roaming réseau (MOBIKE) : un
+//! superviseur de reconnexion surveille la route par défaut de l'hôte et
+//! relance le tunnel (même `TunnelHandle.id`) avec un backoff exponentiel
+//! quand elle change, au lieu de laisser `charon-cmd` mourir en silence.
 
 use async_trait::async_trait;
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, Command};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
 use crate::{
+    config_template::ConfigTemplate,
     error::{Result, VpnError},
+    hooks::{HookContext, HookEvent},
+    killswitch::KillSwitch,
     protocol::VpnProtocol,
-    tunnel::{ConnectionConfig, Credentials, TunnelHandle, TunnelStats, VpnTunnel},
+    tunnel::{ConnectionConfig, Credentials, Interface, ReconnectPolicy, TunnelHandle, TunnelStats, VpnTunnel},
 };
 
+/// How long to wait for `charon-cmd` to report an established CHILD_SA
+/// before giving up on the connection attempt.
+const CHILD_SA_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How often the reconnection supervisor polls for a default-route change.
+const ROUTE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Tunnel IKEv2 (strongSwan)
 pub struct IKEv2Tunnel {
-    process: Option<Child>,
+    process: Arc<Mutex<Option<Child>>>,
     config_file: Option<PathBuf>,
     start_time: Option<Instant>,
     bytes_sent: u64,
     bytes_received: u64,
+    assigned_ip: Arc<Mutex<Option<IpAddr>>>,
+    handle_id: String,
+    config: Option<ConnectionConfig>,
+    reconnect_policy: ReconnectPolicy,
+    route_watcher: Option<JoinHandle<()>>,
+    /// Egress kill-switch, armed after a successful connect when
+    /// `ConnectionConfig::kill_switch` is set.
+    kill_switch: KillSwitch,
 }
 
 impl IKEv2Tunnel {
     pub fn new() -> Self {
         Self {
-            process: None,
+            process: Arc::new(Mutex::new(None)),
             config_file: None,
             start_time: None,
             bytes_sent: 0,
             bytes_received: 0,
+            assigned_ip: Arc::new(Mutex::new(None)),
+            handle_id: uuid::Uuid::new_v4().to_string(),
+            config: None,
+            reconnect_policy: ReconnectPolicy::default(),
+            route_watcher: None,
+            kill_switch: KillSwitch::new(),
         }
     }
 
@@ -55,11 +86,13 @@ impl IKEv2Tunnel {
         &self,
         config: &ConnectionConfig,
         username: &str,
-        password: &str,
+        _password: &str,
     ) -> Result<PathBuf> {
         let temp_dir = std::env::temp_dir();
         let config_path = temp_dir.join(format!("ikev2_{}.conf", uuid::Uuid::new_v4()));
 
+        let template = ConfigTemplate::from_config(config);
+
         // Configuration strongSwan simplifiée (ipsec.conf style)
         let conf_content = format!(
             "# WorldVPN IKEv2 Configuration\n\
@@ -70,13 +103,16 @@ impl IKEv2Tunnel {
               left=%defaultroute\n\
               leftauth=eap-mschapv2\n\
               leftsourceip=%config\n\
+            {}\
               right={}\n\
               rightid=%any\n\
               rightauth=pubkey\n\
-              rightsubnet=0.0.0.0/0\n\
+              rightsubnet={}\n\
               eap_identity={}\n\
               auto=add\n",
+            template.strongswan_leftdns(),
             config.server_addr.ip(),
+            template.strongswan_rightsubnet(),
             username
         );
 
@@ -86,6 +122,170 @@ impl IKEv2Tunnel {
 
         Ok(config_path)
     }
+
+    /// Tails `charon-cmd`'s stdout, tracking the last virtual IP strongSwan
+    /// reports installing and resolving `established_tx` with it as soon as
+    /// a CHILD_SA established line confirms the tunnel is actually up.
+    fn spawn_log_reader(
+        stdout: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+        established_tx: oneshot::Sender<Option<IpAddr>>,
+    ) {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            let mut established_tx = Some(established_tx);
+            let mut virtual_ip: Option<IpAddr> = None;
+
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) | Err(_) => break,
+                };
+
+                if let Some((_, rest)) = line.split_once("installing new virtual IP") {
+                    virtual_ip = rest.trim().split_whitespace().next().and_then(|s| s.parse().ok());
+                }
+
+                if line.contains("CHILD_SA") && line.contains("established") {
+                    if let Some(tx) = established_tx.take() {
+                        let _ = tx.send(virtual_ip);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Launches `charon-cmd` for `config`/`username`/`password` and waits
+    /// for its CHILD_SA to come up, returning the spawned child and the
+    /// negotiated virtual IP. Shared by the initial `connect` and every
+    /// reconnection attempt the supervisor makes.
+    async fn launch_and_wait(config: &ConnectionConfig, username: &str, password: &str) -> Result<(Child, IpAddr)> {
+        let mut child = Command::new("charon-cmd")
+            .arg("--host")
+            .arg(config.server_addr.ip().to_string())
+            .arg("--identity")
+            .arg(username)
+            .arg("--eap-identity")
+            .arg(username)
+            .arg("--eap-password")
+            .arg(password)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| VpnError::ConnectionFailed(format!("Échec lancement charon-cmd: {}", e)))?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| VpnError::ConnectionFailed("stdout charon-cmd indisponible".into()))?;
+
+        let (established_tx, established_rx) = oneshot::channel();
+        Self::spawn_log_reader(stdout, established_tx);
+
+        let assigned_ip = match tokio::time::timeout(CHILD_SA_TIMEOUT, established_rx).await {
+            Ok(Ok(Some(ip))) => ip,
+            Ok(Ok(None)) => {
+                return Err(VpnError::ConnectionFailed(
+                    "CHILD_SA établie sans IP virtuelle négociée".into(),
+                ))
+            }
+            Ok(Err(_)) => {
+                return Err(VpnError::ConnectionFailed(
+                    "charon-cmd fermé avant établissement du CHILD_SA".into(),
+                ))
+            }
+            Err(_) => return Err(VpnError::ConnectionFailed("Timeout établissement IKEv2".into())),
+        };
+
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(VpnError::ConnectionFailed(format!(
+                "charon-cmd crashé (Exit {}). Root requis ?",
+                status
+            )));
+        }
+
+        Ok((child, assigned_ip))
+    }
+
+    /// One reconnection attempt: tears down the old `charon-cmd` (if still
+    /// alive) and relaunches it, replacing `process`/`assigned_ip` in place
+    /// so the `TunnelHandle.id` callers already hold stays valid.
+    async fn reconnect_once(
+        config: &ConnectionConfig,
+        username: &str,
+        password: &str,
+        process: &Mutex<Option<Child>>,
+        assigned_ip: &Mutex<Option<IpAddr>>,
+    ) -> Result<()> {
+        if let Some(mut old) = process.lock().await.take() {
+            let _ = old.kill().await;
+            let _ = old.wait().await;
+        }
+
+        let (child, ip) = Self::launch_and_wait(config, username, password).await?;
+        *process.lock().await = Some(child);
+        *assigned_ip.lock().await = Some(ip);
+        Ok(())
+    }
+
+    /// Reads the `(interface, gateway)` of the host's IPv4 default route
+    /// (destination `00000000`) from `/proc/net/route`, so the supervisor
+    /// can detect a Wi-Fi <-> cellular handoff without a netlink dependency.
+    /// Returns `None` on platforms without this file (non-Linux, sandboxed).
+    async fn default_route_signature() -> Option<(String, String)> {
+        let contents = tokio::fs::read_to_string("/proc/net/route").await.ok()?;
+        for line in contents.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() >= 3 && fields[1] == "00000000" {
+                return Some((fields[0].to_string(), fields[2].to_string()));
+            }
+        }
+        None
+    }
+
+    /// Spawns the background task that polls for default-route changes and
+    /// drives `reconnect_once` with exponential backoff when one is found.
+    fn spawn_route_watcher(
+        config: ConnectionConfig,
+        username: String,
+        password: String,
+        process: Arc<Mutex<Option<Child>>>,
+        assigned_ip: Arc<Mutex<Option<IpAddr>>>,
+        policy: ReconnectPolicy,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut route = Self::default_route_signature().await;
+            loop {
+                tokio::time::sleep(ROUTE_POLL_INTERVAL).await;
+                let current = Self::default_route_signature().await;
+                if current == route {
+                    continue;
+                }
+                warn!("Changement de route par défaut détecté, reconnexion IKEv2 (MOBIKE)...");
+                route = current;
+
+                let mut attempt = 0u32;
+                let mut backoff = policy.initial_backoff;
+                loop {
+                    attempt += 1;
+                    match Self::reconnect_once(&config, &username, &password, &process, &assigned_ip).await {
+                        Ok(()) => {
+                            info!("Reconnexion IKEv2 réussie (tentative {})", attempt);
+                            break;
+                        }
+                        Err(e) => {
+                            warn!("Échec reconnexion IKEv2 (tentative {}): {}", attempt, e);
+                            if attempt >= policy.max_retries {
+                                warn!("Abandon de la reconnexion IKEv2 après {} tentatives", attempt);
+                                return;
+                            }
+                            tokio::time::sleep(backoff).await;
+                            backoff = (backoff * 2).min(policy.backoff_ceiling);
+                        }
+                    }
+                }
+            }
+        })
+    }
 }
 
 #[async_trait]
@@ -93,6 +293,12 @@ impl VpnTunnel for IKEv2Tunnel {
     async fn connect(&mut self, config: &ConnectionConfig) -> Result<TunnelHandle> {
         info!("🔌 Initialisation IKEv2 vers {}", config.server_addr);
 
+        config.hooks.run(HookEvent::Connecting, &HookContext {
+            protocol: Some("IKEv2".to_string()),
+            server_addr: Some(config.server_addr.to_string()),
+            ..Default::default()
+        }).await?;
+
         Self::check_charon_installed().await?;
 
         let (username, password) = match &config.credentials {
@@ -113,44 +319,44 @@ impl VpnTunnel for IKEv2Tunnel {
 
         info!("🚀 Lancement charon-cmd (strongSwan)...");
 
-        // Commande charon-cmd : --host IP --identity USER --profile ikev2-eap
-        // Note: Requiert généralement root pour TUN, similaire à OpenVPN
-        let mut child = Command::new("charon-cmd")
-            .arg("--host")
-            .arg(config.server_addr.ip().to_string())
-            .arg("--identity")
-            .arg(&username)
-            .arg("--eap-identity")
-            .arg(&username)
-            .arg("--eap-password")
-            .arg(&password)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| {
-                VpnError::ConnectionFailed(format!("Échec lancement charon-cmd: {}", e))
-            })?;
+        let (child, assigned_ip) = Self::launch_and_wait(config, &username, &password).await?;
 
-        // Attente démarrage
-        tokio::time::sleep(Duration::from_secs(2)).await;
+        *self.process.lock().await = Some(child);
+        *self.assigned_ip.lock().await = Some(assigned_ip);
+        self.config_file = Some(config_path);
+        self.start_time = Some(Instant::now());
+        self.config = Some(config.clone());
+        self.reconnect_policy = config.reconnect_policy;
 
-        if let Ok(Some(status)) = child.try_wait() {
-            return Err(VpnError::ConnectionFailed(format!(
-                "charon-cmd crashé (Exit {}). Root requis ?",
-                status
-            )));
+        if let Some(old_watcher) = self.route_watcher.take() {
+            old_watcher.abort();
         }
+        self.route_watcher = Some(Self::spawn_route_watcher(
+            config.clone(),
+            username,
+            password,
+            self.process.clone(),
+            self.assigned_ip.clone(),
+            self.reconnect_policy,
+        ));
 
-        self.process = Some(child);
-        self.config_file = Some(config_path);
-        self.start_time = Some(Instant::now());
+        info!("✅ IKEv2 tunnel établi ! IP assignée: {}", assigned_ip);
+
+        if config.kill_switch {
+            self.kill_switch.arm(config.server_addr).await?;
+        }
 
-        info!("✅ IKEv2 tunnel établi !");
+        config.hooks.run(HookEvent::Connected, &HookContext {
+            protocol: Some("IKEv2".to_string()),
+            server_addr: Some(config.server_addr.to_string()),
+            assigned_ip: Some(assigned_ip.to_string()),
+            ..Default::default()
+        }).await?;
 
         Ok(TunnelHandle {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: self.handle_id.clone(),
             protocol: VpnProtocol::IKEv2,
-            assigned_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), // IP simulée
+            assigned_ip,
             remote_endpoint: config.server_addr,
         })
     }
@@ -166,7 +372,14 @@ impl VpnTunnel for IKEv2Tunnel {
     }
 
     async fn disconnect(&mut self) -> Result<()> {
-        if let Some(mut child) = self.process.take() {
+        let hooks = self.config.as_ref().map(|c| c.hooks.clone()).unwrap_or_default();
+        hooks.run(HookEvent::Disconnecting, &HookContext::default()).await?;
+
+        if let Some(watcher) = self.route_watcher.take() {
+            watcher.abort();
+        }
+
+        if let Some(mut child) = self.process.lock().await.take() {
             let _ = child.kill().await;
             let _ = child.wait().await;
         }
@@ -175,6 +388,9 @@ impl VpnTunnel for IKEv2Tunnel {
             let _ = tokio::fs::remove_file(p).await;
         }
 
+        self.config = None;
+        self.kill_switch.disarm().await?;
+        hooks.run(HookEvent::Disconnected, &HookContext::default()).await?;
         info!("🛑 IKEv2 arrêté");
         Ok(())
     }
@@ -193,6 +409,32 @@ impl VpnTunnel for IKEv2Tunnel {
         }
     }
 
+    /// Triggers an immediate MOBIKE-style reconnect instead of waiting for
+    /// the route watcher's next poll — useful when the OS hands the client
+    /// an explicit network-change notification (e.g. mobile connectivity
+    /// callbacks where `/proc/net/route` isn't available to poll).
+    async fn handle_network_change(&mut self, new_interface: Interface) -> Result<()> {
+        info!(
+            "Changement réseau signalé: {} ({:?}), reconnexion IKEv2 immédiate",
+            new_interface.name, new_interface.interface_type
+        );
+
+        let config = self
+            .config
+            .clone()
+            .ok_or_else(|| VpnError::ConnectionFailed("Tunnel IKEv2 non connecté".into()))?;
+
+        let (username, password) = match &config.credentials {
+            Credentials::Password {
+                username: Some(u),
+                password: p,
+            } => (u.clone(), p.clone()),
+            _ => return Err(VpnError::InvalidConfig("IKEv2 nécessite username/password (EAP)".into())),
+        };
+
+        Self::reconnect_once(&config, &username, &password, &self.process, &self.assigned_ip).await
+    }
+
     fn protocol(&self) -> VpnProtocol {
         VpnProtocol::IKEv2
     }
@@ -200,8 +442,13 @@ impl VpnTunnel for IKEv2Tunnel {
 
 impl Drop for IKEv2Tunnel {
     fn drop(&mut self) {
-        if let Some(mut child) = self.process.take() {
-            let _ = child.start_kill();
+        if let Some(watcher) = self.route_watcher.take() {
+            watcher.abort();
+        }
+        if let Ok(mut guard) = self.process.try_lock() {
+            if let Some(mut child) = guard.take() {
+                let _ = child.start_kill();
+            }
         }
     }
 }
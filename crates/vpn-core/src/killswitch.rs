@@ -0,0 +1,151 @@
+//! Firewall kill-switch: fail-closed egress while a tunnel is connected.
+//!
+//! Mirrors the "fail closed" behavior VpnCloud leaves to hook scripts:
+//! once armed, only traffic to the VPN server's endpoint (so the tunnel
+//! handshake/keepalives keep working) and loopback is allowed out of the
+//! host; everything else is dropped. If `openvpn`/`charon-cmd` crashes
+//! mid-session, traffic simply stops instead of falling back to the
+//! default route and leaking. Linux shells out to `nft`, macOS to `pfctl`
+//! — the same "drive the system binary" approach `openvpn.rs`/`ikev2.rs`
+//! already use for their respective daemons.
+
+use std::net::SocketAddr;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use crate::error::{Result, VpnError};
+
+const NFT_TABLE: &str = "worldvpn_killswitch";
+const PF_ANCHOR: &str = "worldvpn_killswitch";
+
+/// Installs/removes the fail-closed egress rules for a single connection.
+/// One `KillSwitch` per tunnel instance; `arm`/`disarm` are idempotent.
+#[derive(Debug, Default)]
+pub struct KillSwitch {
+    armed: bool,
+}
+
+impl KillSwitch {
+    pub fn new() -> Self {
+        Self { armed: false }
+    }
+
+    /// Installs rules that drop all outbound traffic except to
+    /// `server_endpoint` and loopback.
+    pub async fn arm(&mut self, server_endpoint: SocketAddr) -> Result<()> {
+        if cfg!(target_os = "linux") {
+            arm_nft(server_endpoint).await?;
+        } else if cfg!(target_os = "macos") {
+            arm_pf(server_endpoint).await?;
+        } else {
+            warn!("Kill-switch not supported on this platform; leaving egress unrestricted");
+            return Ok(());
+        }
+        self.armed = true;
+        info!("Kill-switch armed: egress restricted to {}", server_endpoint);
+        Ok(())
+    }
+
+    /// Tears down whatever rules `arm` installed. No-op if never armed.
+    pub async fn disarm(&mut self) -> Result<()> {
+        if !self.armed {
+            return Ok(());
+        }
+        if cfg!(target_os = "linux") {
+            disarm_nft().await?;
+        } else if cfg!(target_os = "macos") {
+            disarm_pf().await?;
+        }
+        self.armed = false;
+        info!("Kill-switch disarmed");
+        Ok(())
+    }
+}
+
+impl Drop for KillSwitch {
+    fn drop(&mut self) {
+        if self.armed {
+            warn!(
+                "KillSwitch dropped while still armed; run `nft delete table inet {}` \
+                 (or `pfctl -a {} -F all` on macOS) to clear the egress rules manually",
+                NFT_TABLE, PF_ANCHOR
+            );
+        }
+    }
+}
+
+async fn arm_nft(server_endpoint: SocketAddr) -> Result<()> {
+    let ruleset = format!(
+        "table inet {table} {{\n\
+         \u{20}chain output {{\n\
+         \u{20}\u{20}type filter hook output priority 0; policy drop;\n\
+         \u{20}\u{20}oif \"lo\" accept\n\
+         \u{20}\u{20}ip daddr {ip} accept\n\
+         \u{20}\u{20}ip6 daddr {ip} accept\n\
+         \u{20}}}\n\
+         }}\n",
+        table = NFT_TABLE,
+        ip = server_endpoint.ip(),
+    );
+    run_with_stdin("nft", &["-f", "-"], &ruleset).await
+}
+
+async fn disarm_nft() -> Result<()> {
+    // Deleting a table that doesn't exist is a no-op error we can ignore;
+    // there's nothing left to clean up either way.
+    let _ = Command::new("nft")
+        .args(["delete", "table", "inet", NFT_TABLE])
+        .status()
+        .await;
+    Ok(())
+}
+
+async fn arm_pf(server_endpoint: SocketAddr) -> Result<()> {
+    let ruleset = format!(
+        "block drop out quick on ! lo0 all\n\
+         pass out quick on ! lo0 to {ip}\n",
+        ip = server_endpoint.ip(),
+    );
+    run_with_stdin("pfctl", &["-a", PF_ANCHOR, "-f", "-"], &ruleset).await
+}
+
+async fn disarm_pf() -> Result<()> {
+    let _ = Command::new("pfctl")
+        .args(["-a", PF_ANCHOR, "-F", "all"])
+        .status()
+        .await;
+    Ok(())
+}
+
+/// Spawns `cmd args`, writes `stdin_payload` to its stdin, and waits for
+/// it to exit successfully.
+async fn run_with_stdin(cmd: &str, args: &[&str], stdin_payload: &str) -> Result<()> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| VpnError::Internal(format!("Failed to spawn {}: {}", cmd, e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(stdin_payload.as_bytes())
+            .await
+            .map_err(|e| VpnError::Internal(format!("Failed to write {} ruleset: {}", cmd, e)))?;
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| VpnError::Internal(format!("Failed to wait on {}: {}", cmd, e)))?;
+
+    if !status.success() {
+        return Err(VpnError::Internal(format!(
+            "{} exited with {} while arming the kill-switch",
+            cmd, status
+        )));
+    }
+
+    Ok(())
+}
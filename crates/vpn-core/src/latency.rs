@@ -0,0 +1,197 @@
+use crate::protocol::VpnProtocol;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Smoothing factor for the EWMA update: `ewma = alpha*sample + (1-alpha)*ewma`.
+const DEFAULT_ALPHA: f64 = 0.1;
+
+/// RTT assumed for a `(server_id, protocol)` pair with no samples yet, and
+/// the value a stale entry decays back toward.
+const DEFAULT_RTT_MS: f64 = 150.0;
+
+/// Fraction of the gap to `DEFAULT_RTT_MS` a stale entry regresses per
+/// `stats()` call — models confidence eroding the longer a pair goes
+/// unmeasured, without discarding it outright.
+const DECAY_FACTOR: f64 = 0.05;
+
+/// An entry is eligible for decay once it hasn't been sampled in this long.
+const STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Live RTT statistics for a `(server_id, VpnProtocol)` pair.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyStats {
+    pub ewma_ms: f64,
+    pub jitter_ms: f64,
+    pub peak_ms: f64,
+    pub samples: u64,
+}
+
+impl LatencyStats {
+    fn fresh() -> Self {
+        Self {
+            ewma_ms: DEFAULT_RTT_MS,
+            jitter_ms: 0.0,
+            peak_ms: DEFAULT_RTT_MS,
+            samples: 0,
+        }
+    }
+
+    fn record(&mut self, sample_ms: f64, alpha: f64) {
+        let prev_ewma = self.ewma_ms;
+        self.ewma_ms = alpha * sample_ms + (1.0 - alpha) * prev_ewma;
+        // RFC 3550-style jitter: an EWMA of the absolute deviation from the
+        // previous estimate.
+        self.jitter_ms = alpha * (sample_ms - prev_ewma).abs() + (1.0 - alpha) * self.jitter_ms;
+        self.peak_ms = self.peak_ms.max(sample_ms);
+        self.samples += 1;
+    }
+
+    fn decay(&mut self) {
+        self.ewma_ms += (DEFAULT_RTT_MS - self.ewma_ms) * DECAY_FACTOR;
+    }
+}
+
+struct TrackedEntry {
+    stats: LatencyStats,
+    last_sample_at: Instant,
+}
+
+/// Tracks per-`(server_id, VpnProtocol)` round-trip latency as an
+/// exponentially-weighted moving average, so `VpnProtocol::select_best` can
+/// rank candidates on live measurements instead of static heuristics alone.
+pub struct LatencyTracker {
+    alpha: f64,
+    entries: RwLock<HashMap<(String, VpnProtocol), TrackedEntry>>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::with_alpha(DEFAULT_ALPHA)
+    }
+
+    pub fn with_alpha(alpha: f64) -> Self {
+        Self {
+            alpha,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Records a fresh RTT sample for `(server_id, protocol)`, folding it
+    /// into that pair's EWMA/jitter/peak.
+    pub fn record_sample(&self, server_id: &str, protocol: VpnProtocol, rtt_ms: f64) {
+        let mut entries = self.entries.write().unwrap();
+        let entry = entries
+            .entry((server_id.to_string(), protocol))
+            .or_insert_with(|| TrackedEntry {
+                stats: LatencyStats::fresh(),
+                last_sample_at: Instant::now(),
+            });
+        entry.stats.record(rtt_ms, self.alpha);
+        entry.last_sample_at = Instant::now();
+    }
+
+    /// Returns the current stats for `(server_id, protocol)`. If the pair
+    /// hasn't been sampled in `STALE_AFTER`, it's decayed toward
+    /// `DEFAULT_RTT_MS` first so a server that's gone quiet doesn't keep
+    /// looking artificially fast (or slow) forever.
+    pub fn stats(&self, server_id: &str, protocol: VpnProtocol) -> LatencyStats {
+        let mut entries = self.entries.write().unwrap();
+        match entries.get_mut(&(server_id.to_string(), protocol)) {
+            Some(entry) => {
+                if entry.last_sample_at.elapsed() > STALE_AFTER {
+                    entry.stats.decay();
+                }
+                entry.stats
+            }
+            None => LatencyStats::fresh(),
+        }
+    }
+
+    /// Median EWMA across every tracked pair, used to normalize a single
+    /// pair's EWMA in `VpnProtocol::select_best`.
+    pub fn fleet_median_ewma(&self) -> f64 {
+        let entries = self.entries.read().unwrap();
+        if entries.is_empty() {
+            return DEFAULT_RTT_MS;
+        }
+        let mut values: Vec<f64> = entries.values().map(|e| e.stats.ewma_ms).collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        values[values.len() / 2]
+    }
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A ranking candidate for `VpnProtocol::select_best`: one protocol
+/// available on one server, carrying its live latency EWMA (typically read
+/// from `LatencyTracker::stats`).
+#[derive(Debug, Clone)]
+pub struct LatencyCandidate {
+    pub server_id: String,
+    pub protocol: VpnProtocol,
+    pub ewma_ms: f64,
+}
+
+/// Weights for the combined objective in `VpnProtocol::select_best`:
+/// `w_perf*performance_score + w_stealth*stealth_score - w_latency*normalized_ewma`.
+#[derive(Debug, Clone, Copy)]
+pub struct SelectionWeights {
+    pub performance: f64,
+    pub stealth: f64,
+    pub latency: f64,
+}
+
+impl Default for SelectionWeights {
+    fn default() -> Self {
+        Self {
+            performance: 0.4,
+            stealth: 0.4,
+            latency: 0.2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ewma_converges_toward_samples() {
+        let tracker = LatencyTracker::new();
+        for _ in 0..50 {
+            tracker.record_sample("srv-1", VpnProtocol::WireGuard, 40.0);
+        }
+        let stats = tracker.stats("srv-1", VpnProtocol::WireGuard);
+        assert!((stats.ewma_ms - 40.0).abs() < 1.0, "ewma should converge near 40ms, got {}", stats.ewma_ms);
+        assert_eq!(stats.samples, 50);
+    }
+
+    #[test]
+    fn test_unsampled_pair_uses_default() {
+        let tracker = LatencyTracker::new();
+        let stats = tracker.stats("srv-unknown", VpnProtocol::VLESS);
+        assert_eq!(stats.ewma_ms, DEFAULT_RTT_MS);
+        assert_eq!(stats.samples, 0);
+    }
+
+    #[test]
+    fn test_peak_tracks_worst_sample() {
+        let tracker = LatencyTracker::new();
+        tracker.record_sample("srv-1", VpnProtocol::Hysteria2, 30.0);
+        tracker.record_sample("srv-1", VpnProtocol::Hysteria2, 300.0);
+        tracker.record_sample("srv-1", VpnProtocol::Hysteria2, 50.0);
+        let stats = tracker.stats("srv-1", VpnProtocol::Hysteria2);
+        assert_eq!(stats.peak_ms, 300.0);
+    }
+
+    #[test]
+    fn test_fleet_median_with_no_samples() {
+        let tracker = LatencyTracker::new();
+        assert_eq!(tracker.fleet_median_ewma(), DEFAULT_RTT_MS);
+    }
+}
@@ -6,19 +6,33 @@
 pub mod abuse;
 pub mod binary_manager;
 pub mod client;
+pub mod config_template;
 pub mod credits;
 pub mod crypto;
+pub mod doh;
 pub mod error;
+pub mod hooks;
 pub mod hysteria;
 pub mod ikev2;
+pub mod killswitch;
+pub mod latency;
+pub mod metrics;
 pub mod mock;
+pub mod mtu;
 pub mod nat;
 pub mod obfuscation;
 pub mod openvpn;
 pub mod p2p;
+pub mod profile;
 pub mod protocol;
+pub mod proxy_config;
+pub mod reconnect;
 pub mod selector;
 pub mod shadowsocks;
+pub mod share_uri;
+pub mod socks5_proxy;
 pub mod tunnel;
+pub mod upnp;
 pub mod v2ray;
+pub mod websocket_tunnel;
 pub mod wireguard;
@@ -0,0 +1,116 @@
+//! Statsd metrics exporter for `TunnelStats`.
+//!
+//! Mirrors VpnCloud's `statsd_server`/`statsd_prefix` config: periodically
+//! samples any `VpnTunnel`'s `stats()` and pushes its fields as statsd
+//! gauges/counters over UDP, so operators can wire live VPN counters into
+//! their existing monitoring stack without a protocol-specific exporter.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::protocol::VpnProtocol;
+use crate::tunnel::{TunnelStats, VpnTunnel};
+
+/// Push cadence used when the caller doesn't pick their own.
+pub const DEFAULT_EXPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Periodically samples a tunnel's `TunnelStats` and pushes them to a
+/// statsd daemon over UDP under `<prefix>.<protocol>.<metric>`.
+pub struct StatsdExporter {
+    socket: UdpSocket,
+    target: SocketAddr,
+    prefix: String,
+    interval: Duration,
+}
+
+impl StatsdExporter {
+    /// Binds an ephemeral local UDP socket and targets `statsd_server`,
+    /// pushing metrics under `statsd_prefix` every `interval`.
+    pub async fn new(
+        statsd_server: SocketAddr,
+        statsd_prefix: impl Into<String>,
+        interval: Duration,
+    ) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).await?;
+        Ok(Self {
+            socket,
+            target: statsd_server,
+            prefix: statsd_prefix.into(),
+            interval,
+        })
+    }
+
+    /// Spawns the export loop for `tunnel`, tagged with `protocol` in the
+    /// metric namespace (e.g. `worldvpn.tunnel.WireGuard.bytes_sent`).
+    /// `bytes_sent`/`bytes_received` are reported both as cumulative
+    /// counters and, derived from successive samples, as
+    /// `bytes_sent_per_sec`/`bytes_received_per_sec` gauges — so a
+    /// dashboard doesn't have to diff the counters itself.
+    pub fn spawn(self, protocol: VpnProtocol, tunnel: Arc<Mutex<Box<dyn VpnTunnel>>>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            let mut prev: Option<(TunnelStats, Instant)> = None;
+
+            loop {
+                ticker.tick().await;
+                let stats = tunnel.lock().await.stats();
+                let now = Instant::now();
+
+                let (sent_rate, recv_rate) = match &prev {
+                    Some((prev_stats, prev_at)) => {
+                        let elapsed = now.duration_since(*prev_at).as_secs_f64().max(0.001);
+                        (
+                            stats.bytes_sent.saturating_sub(prev_stats.bytes_sent) as f64 / elapsed,
+                            stats.bytes_received.saturating_sub(prev_stats.bytes_received) as f64 / elapsed,
+                        )
+                    }
+                    None => (0.0, 0.0),
+                };
+
+                if let Err(e) = self.push(protocol, &stats, sent_rate, recv_rate).await {
+                    warn!("Échec envoi métriques statsd: {}", e);
+                }
+
+                prev = Some((stats, now));
+            }
+        })
+    }
+
+    async fn push(
+        &self,
+        protocol: VpnProtocol,
+        stats: &TunnelStats,
+        sent_rate: f64,
+        recv_rate: f64,
+    ) -> std::io::Result<()> {
+        let prefix = format!("{}.{}", self.prefix, protocol);
+        let payload = format!(
+            "{prefix}.bytes_sent:{bytes_sent}|c\n\
+             {prefix}.bytes_received:{bytes_received}|c\n\
+             {prefix}.bytes_sent_per_sec:{sent_rate}|g\n\
+             {prefix}.bytes_received_per_sec:{recv_rate}|g\n\
+             {prefix}.avg_latency_ms:{avg_latency_ms}|g\n\
+             {prefix}.packet_loss:{packet_loss}|g\n\
+             {prefix}.throughput_mbps:{throughput}|g\n\
+             {prefix}.uptime_sec:{uptime}|g\n",
+            prefix = prefix,
+            bytes_sent = stats.bytes_sent,
+            bytes_received = stats.bytes_received,
+            sent_rate = sent_rate,
+            recv_rate = recv_rate,
+            avg_latency_ms = stats.avg_latency_ms,
+            packet_loss = stats.packet_loss,
+            throughput = stats.current_throughput_mbps,
+            uptime = stats.uptime.as_secs_f64(),
+        );
+
+        self.socket.send_to(payload.as_bytes(), self.target).await?;
+        Ok(())
+    }
+}
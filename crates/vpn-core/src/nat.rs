@@ -1,5 +1,12 @@
 use crate::error::{Result, VpnError};
-use std::net::SocketAddr;
+use async_trait::async_trait;
+use rand::RngCore;
+use ring::hmac;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tokio::time::{timeout, Duration};
 
 /// Defines the final path used for a successful Peer-to-Peer connection
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,14 +31,117 @@ pub struct TurnServer {
     pub password: String,
 }
 
+/// Exchanges a locally-discovered STUN reflexive candidate with the remote
+/// peer and returns the peer's own reflexive candidate, over whatever
+/// out-of-band channel the two sides already share (e.g. the node
+/// notification hub behind `/nodes/ws`). `NatTraversal` is signaling-agnostic
+/// — it only needs the swap to happen before it starts punching.
+#[async_trait]
+pub trait SignalingChannel: Send + Sync {
+    async fn exchange_candidate(&self, peer_addr: SocketAddr, local_candidate: SocketAddr) -> Result<SocketAddr>;
+}
+
+/// Default `SignalingChannel` for callers that don't have a real signaling
+/// transport wired up yet: it just hands back the `peer_addr` the caller
+/// already resolved (e.g. from `PeerInfo::public_addr` or a node's
+/// registered `external_endpoint`), so hole punching still has a target.
+pub struct NoSignaling;
+
+#[async_trait]
+impl SignalingChannel for NoSignaling {
+    async fn exchange_candidate(&self, peer_addr: SocketAddr, _local_candidate: SocketAddr) -> Result<SocketAddr> {
+        Ok(peer_addr)
+    }
+}
+
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_SUCCESS: u16 = 0x0101;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+// TURN (RFC 5766) message types and attributes used by `try_relay_connection`.
+const TURN_ALLOCATE_REQUEST: u16 = 0x0003;
+const TURN_ALLOCATE_SUCCESS: u16 = 0x0103;
+const TURN_ALLOCATE_ERROR: u16 = 0x0113;
+const TURN_REFRESH_REQUEST: u16 = 0x0004;
+const TURN_CREATE_PERMISSION_REQUEST: u16 = 0x0008;
+const TURN_CREATE_PERMISSION_SUCCESS: u16 = 0x0108;
+const TURN_SEND_INDICATION: u16 = 0x0016;
+const TURN_DATA_INDICATION: u16 = 0x0017;
+
+const ATTR_USERNAME: u16 = 0x0006;
+const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
+const ATTR_ERROR_CODE: u16 = 0x0009;
+const ATTR_REALM: u16 = 0x0014;
+const ATTR_NONCE: u16 = 0x0015;
+const ATTR_XOR_RELAYED_ADDRESS: u16 = 0x0016;
+const ATTR_REQUESTED_TRANSPORT: u16 = 0x0019;
+const ATTR_XOR_PEER_ADDRESS: u16 = 0x0012;
+const ATTR_DATA: u16 = 0x0013;
+const ATTR_LIFETIME: u16 = 0x000D;
+
+/// UDP, per the IANA protocol numbers table — the only transport TURN
+/// relays allocate for a WireGuard-style data path.
+const REQUESTED_TRANSPORT_UDP: u32 = 17 << 24;
+
+/// Long-term credentials and the replay-protection nonce/realm a TURN
+/// server handed back after the initial 401 challenge, needed on every
+/// subsequent authenticated request against that allocation.
+#[derive(Debug, Clone)]
+struct TurnCredentials {
+    username: String,
+    realm: String,
+    nonce: String,
+    /// `MD5(username:realm:password)`, the long-term key RFC 5389 section
+    /// 15.4 uses as the MESSAGE-INTEGRITY HMAC-SHA1 key.
+    key: [u8; 16],
+}
+
+/// A live TURN allocation: the relayed transport address the peer can be
+/// told to send to, and everything needed to keep using it (the bound
+/// socket, the server we allocated from, and the credentials to sign
+/// follow-up requests/refreshes with).
+struct TurnAllocation {
+    socket: Arc<UdpSocket>,
+    server_addr: SocketAddr,
+    relayed_addr: SocketAddr,
+    credentials: TurnCredentials,
+    _refresh_task: tokio::task::JoinHandle<()>,
+}
+
 /// Orchestrates NAT traversal techniques to maximize P2P success rates
 pub struct NatTraversal {
     config: NatConfig,
+    signaling: Arc<dyn SignalingChannel>,
+    /// The UDP socket that successfully punched through, left bound and
+    /// connected so the data path can reuse it instead of paying for a
+    /// second handshake. Populated only after a `HolePunching` success.
+    punched_socket: Mutex<Option<UdpSocket>>,
+    /// The live TURN allocation from the last successful `Relay` fallback,
+    /// if any — kept around so its background refresh task keeps the
+    /// allocation alive and so the data path can send/receive through it.
+    relay: Mutex<Option<TurnAllocation>>,
 }
 
 impl NatTraversal {
     pub fn new(config: NatConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            signaling: Arc::new(NoSignaling),
+            punched_socket: Mutex::new(None),
+            relay: Mutex::new(None),
+        }
+    }
+
+    /// Builds a `NatTraversal` that exchanges candidates over a real
+    /// signaling channel instead of `NoSignaling`'s passthrough.
+    pub fn with_signaling(config: NatConfig, signaling: Arc<dyn SignalingChannel>) -> Self {
+        Self {
+            config,
+            signaling,
+            punched_socket: Mutex::new(None),
+            relay: Mutex::new(None),
+        }
     }
 
     /// Attempts to establish a connection using a prioritized progressive strategy
@@ -61,23 +171,599 @@ impl NatTraversal {
         ))
     }
 
+    /// Hands back the socket that punched through on the last successful
+    /// `HolePunching` attempt, if any, so the caller can reuse it for the
+    /// data path instead of binding a fresh one. Takes it, leaving `None`
+    /// behind — a socket is only ever handed off once.
+    pub async fn take_data_socket(&self) -> Option<UdpSocket> {
+        self.punched_socket.lock().await.take()
+    }
+
+    /// The relayed transport address from the last successful TURN
+    /// allocation, if any — this is the address the tunnel transport hands
+    /// to the peer, since traffic to it is relayed rather than delivered
+    /// directly.
+    pub async fn relayed_endpoint(&self) -> Option<SocketAddr> {
+        self.relay.lock().await.as_ref().map(|r| r.relayed_addr)
+    }
+
+    /// Sends `data` to `peer_addr` through the active TURN allocation via a
+    /// Send Indication (RFC 5766 section 10.1). Fails if no allocation is
+    /// live.
+    pub async fn send_via_relay(&self, peer_addr: SocketAddr, data: &[u8]) -> Result<()> {
+        let guard = self.relay.lock().await;
+        let allocation = guard
+            .as_ref()
+            .ok_or_else(|| VpnError::NatTraversalFailed("No active TURN allocation".to_string()))?;
+
+        let mut msg = Vec::with_capacity(32 + data.len());
+        msg.extend_from_slice(&TURN_SEND_INDICATION.to_be_bytes());
+        msg.extend_from_slice(&0u16.to_be_bytes()); // length placeholder
+        msg.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        let mut txn_id = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut txn_id);
+        msg.extend_from_slice(&txn_id);
+
+        write_xor_peer_address(&mut msg, peer_addr);
+        write_attr(&mut msg, ATTR_DATA, data);
+        finalize_length(&mut msg);
+
+        allocation
+            .socket
+            .send_to(&msg, allocation.server_addr)
+            .await
+            .map_err(VpnError::NetworkError)?;
+        Ok(())
+    }
+
+    /// Reads the next Data Indication off the active TURN allocation's
+    /// socket and returns the relayed payload along with the peer it came
+    /// from.
+    pub async fn recv_from_relay(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        let socket = {
+            let guard = self.relay.lock().await;
+            guard
+                .as_ref()
+                .map(|r| r.socket.clone())
+                .ok_or_else(|| VpnError::NatTraversalFailed("No active TURN allocation".to_string()))?
+        };
+
+        let mut raw = vec![0u8; 65535];
+        loop {
+            let n = socket.recv(&mut raw).await.map_err(VpnError::NetworkError)?;
+            if let Some((from, payload)) = parse_data_indication(&raw[..n]) {
+                let len = payload.len().min(buf.len());
+                buf[..len].copy_from_slice(&payload[..len]);
+                return Ok((len, from));
+            }
+            // Not a Data Indication (e.g. a stray refresh response); keep
+            // waiting for the next datagram.
+        }
+    }
+
     async fn try_direct_connection(&self, _peer_addr: SocketAddr) -> Result<()> {
         tracing::debug!("Attempting direct connection");
         // Logic for raw UDP/TCP binding goes here
         Err(VpnError::NatTraversalFailed("Not implemented".to_string()))
     }
 
-    async fn try_hole_punching(&self, _peer_addr: SocketAddr) -> Result<()> {
+    async fn try_hole_punching(&self, peer_addr: SocketAddr) -> Result<()> {
         tracing::debug!("Attempting hole punching");
-        // Integration with libp2p or webrtc-ice expected here
-        Err(VpnError::NatTraversalFailed("Not implemented".to_string()))
+
+        if self.config.stun_servers.is_empty() {
+            return Err(VpnError::NatTraversalFailed("No STUN servers configured".to_string()));
+        }
+
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(VpnError::NetworkError)?;
+
+        let mut reflexive = None;
+        for server in &self.config.stun_servers {
+            match self.stun_binding_request(&socket, server).await {
+                Ok(addr) => {
+                    reflexive = Some(addr);
+                    break;
+                }
+                Err(e) => tracing::warn!("STUN request to {} failed: {}", server, e),
+            }
+        }
+        let local_candidate = reflexive
+            .ok_or_else(|| VpnError::NatTraversalFailed("No STUN server responded".to_string()))?;
+        tracing::info!("Discovered reflexive candidate {}", local_candidate);
+
+        let peer_candidate = self.signaling.exchange_candidate(peer_addr, local_candidate).await?;
+
+        // Simultaneous open: fire at the peer's reflexive candidate while
+        // listening for its return fire, until one round-trips or we run
+        // out of `timeout_ms`.
+        const PUNCH_PAYLOAD: &[u8] = b"worldvpn-punch";
+        let deadline = Duration::from_millis(self.config.timeout_ms);
+        let mut recv_buf = [0u8; 64];
+
+        let punched = timeout(deadline, async {
+            loop {
+                socket
+                    .send_to(PUNCH_PAYLOAD, peer_candidate)
+                    .await
+                    .map_err(VpnError::NetworkError)?;
+
+                match timeout(Duration::from_millis(200), socket.recv_from(&mut recv_buf)).await {
+                    Ok(Ok((_, from))) if from == peer_candidate => return Ok::<(), VpnError>(()),
+                    _ => continue,
+                }
+            }
+        })
+        .await;
+
+        match punched {
+            Ok(Ok(())) => {
+                *self.punched_socket.lock().await = Some(socket);
+                Ok(())
+            }
+            _ => Err(VpnError::NatTraversalFailed("Hole punching timed out".to_string())),
+        }
     }
 
-    async fn try_relay_connection(&self, _peer_addr: SocketAddr) -> Result<()> {
+    async fn try_relay_connection(&self, peer_addr: SocketAddr) -> Result<()> {
         tracing::debug!("Attempting TURN relay");
-        // TURN-specific encapsulation logic goes here
-        Err(VpnError::NatTraversalFailed("Not implemented".to_string()))
+
+        if self.config.turn_servers.is_empty() {
+            return Err(VpnError::NatTraversalFailed("No TURN servers configured".to_string()));
+        }
+
+        let mut last_err = None;
+        for server in self.config.turn_servers.clone() {
+            match self.turn_allocate(&server).await {
+                Ok((socket, server_addr, relayed_addr, credentials)) => {
+                    self.turn_create_permission(&socket, server_addr, &credentials, peer_addr.ip())
+                        .await?;
+
+                    let socket = Arc::new(socket);
+                    let refresh_task = spawn_turn_refresh_task(socket.clone(), server_addr, credentials.clone());
+
+                    tracing::info!("TURN allocation {} via {} ({})", relayed_addr, server.url, server_addr);
+                    *self.relay.lock().await = Some(TurnAllocation {
+                        socket,
+                        server_addr,
+                        relayed_addr,
+                        credentials,
+                        _refresh_task: refresh_task,
+                    });
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!("TURN allocation via {} failed: {}", server.url, e);
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| VpnError::NatTraversalFailed("No TURN server reachable".to_string())))
+    }
+
+    /// Allocates a UDP relay transport address on `server` (RFC 5766
+    /// section 6), handling the mandatory 401 long-term-credential
+    /// challenge. Returns the bound local socket, the server's resolved
+    /// address, the relayed transport address, and the credentials needed
+    /// to sign follow-up requests.
+    async fn turn_allocate(&self, server: &TurnServer) -> Result<(UdpSocket, SocketAddr, SocketAddr, TurnCredentials)> {
+        let server_addr = tokio::net::lookup_host(&server.url)
+            .await
+            .map_err(VpnError::NetworkError)?
+            .next()
+            .ok_or_else(|| VpnError::NatTraversalFailed(format!("Could not resolve TURN server {}", server.url)))?;
+
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(VpnError::NetworkError)?;
+        let deadline = Duration::from_millis(self.config.timeout_ms);
+
+        // First attempt, unauthenticated — TURN servers always reject this
+        // with a 401 carrying the REALM/NONCE to authenticate against.
+        let txn_id = random_transaction_id();
+        let mut request = Vec::with_capacity(28);
+        request.extend_from_slice(&TURN_ALLOCATE_REQUEST.to_be_bytes());
+        request.extend_from_slice(&0u16.to_be_bytes());
+        request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        request.extend_from_slice(&txn_id);
+        write_attr(&mut request, ATTR_REQUESTED_TRANSPORT, &REQUESTED_TRANSPORT_UDP.to_be_bytes());
+        finalize_length(&mut request);
+
+        socket.send_to(&request, server_addr).await.map_err(VpnError::NetworkError)?;
+        let mut response = [0u8; 512];
+        let n = timeout(deadline, socket.recv(&mut response))
+            .await
+            .map_err(|_| VpnError::NatTraversalFailed("TURN Allocate request timed out".to_string()))?
+            .map_err(VpnError::NetworkError)?;
+
+        let challenge = parse_turn_error(&response[..n])?;
+        let key = long_term_key(&server.username, &challenge.realm, &server.password);
+        let credentials = TurnCredentials {
+            username: server.username.clone(),
+            realm: challenge.realm,
+            nonce: challenge.nonce,
+            key,
+        };
+
+        // Second attempt, authenticated with USERNAME/REALM/NONCE and a
+        // MESSAGE-INTEGRITY computed over everything before it.
+        let txn_id = random_transaction_id();
+        let mut request = Vec::with_capacity(96);
+        request.extend_from_slice(&TURN_ALLOCATE_REQUEST.to_be_bytes());
+        request.extend_from_slice(&0u16.to_be_bytes());
+        request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        request.extend_from_slice(&txn_id);
+        write_attr(&mut request, ATTR_REQUESTED_TRANSPORT, &REQUESTED_TRANSPORT_UDP.to_be_bytes());
+        write_attr(&mut request, ATTR_USERNAME, credentials.username.as_bytes());
+        write_attr(&mut request, ATTR_REALM, credentials.realm.as_bytes());
+        write_attr(&mut request, ATTR_NONCE, credentials.nonce.as_bytes());
+        append_message_integrity(&mut request, &credentials.key);
+        finalize_length(&mut request);
+
+        socket.send_to(&request, server_addr).await.map_err(VpnError::NetworkError)?;
+        let n = timeout(deadline, socket.recv(&mut response))
+            .await
+            .map_err(|_| VpnError::NatTraversalFailed("TURN Allocate (authenticated) timed out".to_string()))?
+            .map_err(VpnError::NetworkError)?;
+
+        let relayed_addr = parse_turn_allocate_success(&response[..n], &txn_id)?;
+        Ok((socket, server_addr, relayed_addr, credentials))
+    }
+
+    /// Issues a CreatePermission request (RFC 5766 section 9) so the
+    /// relay will forward datagrams to/from `peer_ip`.
+    async fn turn_create_permission(
+        &self,
+        socket: &UdpSocket,
+        server_addr: SocketAddr,
+        credentials: &TurnCredentials,
+        peer_ip: IpAddr,
+    ) -> Result<()> {
+        let txn_id = random_transaction_id();
+        let mut request = Vec::with_capacity(96);
+        request.extend_from_slice(&TURN_CREATE_PERMISSION_REQUEST.to_be_bytes());
+        request.extend_from_slice(&0u16.to_be_bytes());
+        request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        request.extend_from_slice(&txn_id);
+        write_xor_peer_address(&mut request, SocketAddr::new(peer_ip, 0));
+        write_attr(&mut request, ATTR_USERNAME, credentials.username.as_bytes());
+        write_attr(&mut request, ATTR_REALM, credentials.realm.as_bytes());
+        write_attr(&mut request, ATTR_NONCE, credentials.nonce.as_bytes());
+        append_message_integrity(&mut request, &credentials.key);
+        finalize_length(&mut request);
+
+        socket.send_to(&request, server_addr).await.map_err(VpnError::NetworkError)?;
+
+        let mut response = [0u8; 512];
+        let n = timeout(Duration::from_millis(self.config.timeout_ms), socket.recv(&mut response))
+            .await
+            .map_err(|_| VpnError::NatTraversalFailed("TURN CreatePermission timed out".to_string()))?
+            .map_err(VpnError::NetworkError)?;
+
+        let msg_type = u16::from_be_bytes([response[0], response[1]]);
+        if msg_type != TURN_CREATE_PERMISSION_SUCCESS {
+            return Err(VpnError::NatTraversalFailed(format!(
+                "TURN CreatePermission rejected (type {:#06x})",
+                msg_type
+            )));
+        }
+        let _ = n;
+        Ok(())
+    }
+
+    /// Sends a STUN Binding Request (RFC 5389) to `stun_server` and returns
+    /// the reflexive address recovered from the response's
+    /// XOR-MAPPED-ADDRESS attribute.
+    async fn stun_binding_request(&self, socket: &UdpSocket, stun_server: &str) -> Result<SocketAddr> {
+        let server_addr = tokio::net::lookup_host(stun_server)
+            .await
+            .map_err(VpnError::NetworkError)?
+            .next()
+            .ok_or_else(|| VpnError::NatTraversalFailed(format!("Could not resolve STUN server {}", stun_server)))?;
+
+        let mut txn_id = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut txn_id);
+
+        let mut request = Vec::with_capacity(20);
+        request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+        request.extend_from_slice(&0u16.to_be_bytes()); // no attributes
+        request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        request.extend_from_slice(&txn_id);
+
+        socket.send_to(&request, server_addr).await.map_err(VpnError::NetworkError)?;
+
+        let mut response = [0u8; 512];
+        let n = timeout(Duration::from_millis(self.config.timeout_ms), socket.recv(&mut response))
+            .await
+            .map_err(|_| VpnError::NatTraversalFailed("STUN request timed out".to_string()))?
+            .map_err(VpnError::NetworkError)?;
+
+        parse_stun_binding_response(&response[..n], &txn_id)
+    }
+}
+
+/// Parses a STUN Binding Success Response and recovers the reflexive
+/// `SocketAddr` from its XOR-MAPPED-ADDRESS attribute (IPv4 only).
+fn parse_stun_binding_response(msg: &[u8], txn_id: &[u8; 12]) -> Result<SocketAddr> {
+    if msg.len() < 20 {
+        return Err(VpnError::NatTraversalFailed("STUN response too short".to_string()));
+    }
+
+    let msg_type = u16::from_be_bytes([msg[0], msg[1]]);
+    if msg_type != STUN_BINDING_SUCCESS {
+        return Err(VpnError::NatTraversalFailed(format!("Unexpected STUN message type {:#06x}", msg_type)));
+    }
+
+    let cookie = u32::from_be_bytes([msg[4], msg[5], msg[6], msg[7]]);
+    if cookie != STUN_MAGIC_COOKIE {
+        return Err(VpnError::NatTraversalFailed("STUN response missing magic cookie".to_string()));
+    }
+    if &msg[8..20] != txn_id {
+        return Err(VpnError::NatTraversalFailed("STUN transaction ID mismatch".to_string()));
+    }
+
+    let msg_len = u16::from_be_bytes([msg[2], msg[3]]) as usize;
+    let mut offset = 20;
+    let end = (20 + msg_len).min(msg.len());
+
+    while offset + 4 <= end {
+        let attr_type = u16::from_be_bytes([msg[offset], msg[offset + 1]]);
+        let attr_len = u16::from_be_bytes([msg[offset + 2], msg[offset + 3]]) as usize;
+        let value_start = offset + 4;
+        let value_end = value_start + attr_len;
+        if value_end > end {
+            break;
+        }
+
+        if attr_type == ATTR_XOR_MAPPED_ADDRESS && attr_len >= 8 {
+            let value = &msg[value_start..value_end];
+            let family = value[1];
+            if family != 0x01 {
+                return Err(VpnError::NatTraversalFailed("Only IPv4 XOR-MAPPED-ADDRESS is supported".to_string()));
+            }
+
+            let xport = u16::from_be_bytes([value[2], value[3]]);
+            let port = xport ^ ((STUN_MAGIC_COOKIE >> 16) as u16);
+
+            let xaddr = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+            let addr = xaddr ^ STUN_MAGIC_COOKIE;
+            let ip = Ipv4Addr::from(addr);
+
+            return Ok(SocketAddr::new(IpAddr::V4(ip), port));
+        }
+
+        // Attributes are padded to a 4-byte boundary.
+        offset = value_end + ((4 - (attr_len % 4)) % 4);
     }
+
+    Err(VpnError::NatTraversalFailed("STUN response had no XOR-MAPPED-ADDRESS".to_string()))
+}
+
+/// A parsed 401 Unauthorized challenge: the REALM and NONCE a TURN server
+/// expects echoed back, signed, on the retried request.
+struct TurnChallenge {
+    realm: String,
+    nonce: String,
+}
+
+fn random_transaction_id() -> [u8; 12] {
+    let mut txn_id = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut txn_id);
+    txn_id
+}
+
+/// `MD5(username:realm:password)`, the long-term credential key RFC 5389
+/// section 15.4 uses to key the MESSAGE-INTEGRITY HMAC.
+fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+    use md5::{Digest, Md5};
+
+    let mut hasher = Md5::new();
+    hasher.update(format!("{}:{}:{}", username, realm, password).as_bytes());
+    hasher.finalize().into()
+}
+
+/// Appends a padded STUN/TURN attribute (`TYPE(2) LENGTH(2) VALUE(padded to
+/// 4 bytes)`) to `msg`.
+fn write_attr(msg: &mut Vec<u8>, attr_type: u16, value: &[u8]) {
+    msg.extend_from_slice(&attr_type.to_be_bytes());
+    msg.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    msg.extend_from_slice(value);
+    let padding = (4 - (value.len() % 4)) % 4;
+    msg.extend(std::iter::repeat(0u8).take(padding));
+}
+
+/// Appends an XOR-PEER-ADDRESS attribute (IPv4 only), using the same
+/// XOR masking as XOR-MAPPED-ADDRESS.
+fn write_xor_peer_address(msg: &mut Vec<u8>, addr: SocketAddr) {
+    let IpAddr::V4(ip) = addr.ip() else {
+        // Only IPv4 peers are supported by this client, matching the rest
+        // of `nat.rs`'s XOR-MAPPED-ADDRESS handling.
+        return;
+    };
+    let xport = addr.port() ^ ((STUN_MAGIC_COOKIE >> 16) as u16);
+    let xaddr = u32::from(ip) ^ STUN_MAGIC_COOKIE;
+
+    let mut value = Vec::with_capacity(8);
+    value.push(0x00);
+    value.push(0x01); // family: IPv4
+    value.extend_from_slice(&xport.to_be_bytes());
+    value.extend_from_slice(&xaddr.to_be_bytes());
+    write_attr(msg, ATTR_XOR_PEER_ADDRESS, &value);
+}
+
+/// Patches `msg`'s STUN header length field to reflect everything appended
+/// after the 20-byte header so far. Must run once, immediately before
+/// sending, after every attribute (including MESSAGE-INTEGRITY) is in place.
+fn finalize_length(msg: &mut [u8]) {
+    let len = (msg.len() - 20) as u16;
+    msg[2..4].copy_from_slice(&len.to_be_bytes());
+}
+
+/// Computes and appends a MESSAGE-INTEGRITY attribute (RFC 5389 section
+/// 15.4): HMAC-SHA1 over the message so far, as if its own 24-byte
+/// attribute were already included in the header's length field.
+fn append_message_integrity(msg: &mut Vec<u8>, key: &[u8; 16]) {
+    let mut len_with_integrity = msg.clone();
+    let pretend_len = (len_with_integrity.len() - 20 + 24) as u16;
+    len_with_integrity[2..4].copy_from_slice(&pretend_len.to_be_bytes());
+
+    let hmac_key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, key);
+    let tag = hmac::sign(&hmac_key, &len_with_integrity);
+    write_attr(msg, ATTR_MESSAGE_INTEGRITY, tag.as_ref());
+}
+
+/// Parses a TURN error response's REALM/NONCE attributes from a 401
+/// Unauthorized (or fails if the message isn't that).
+fn parse_turn_error(msg: &[u8]) -> Result<TurnChallenge> {
+    if msg.len() < 20 {
+        return Err(VpnError::NatTraversalFailed("TURN response too short".to_string()));
+    }
+    let msg_type = u16::from_be_bytes([msg[0], msg[1]]);
+    if msg_type != TURN_ALLOCATE_ERROR {
+        return Err(VpnError::NatTraversalFailed(format!(
+            "Expected TURN 401 challenge, got type {:#06x}",
+            msg_type
+        )));
+    }
+
+    let (mut realm, mut nonce) = (None, None);
+    for (attr_type, value) in iter_attrs(msg) {
+        match attr_type {
+            ATTR_REALM => realm = String::from_utf8(value.to_vec()).ok(),
+            ATTR_NONCE => nonce = String::from_utf8(value.to_vec()).ok(),
+            ATTR_ERROR_CODE => {}
+            _ => {}
+        }
+    }
+
+    match (realm, nonce) {
+        (Some(realm), Some(nonce)) => Ok(TurnChallenge { realm, nonce }),
+        _ => Err(VpnError::NatTraversalFailed("TURN 401 challenge missing REALM/NONCE".to_string())),
+    }
+}
+
+/// Parses an Allocate Success Response and recovers the relayed transport
+/// address from its XOR-RELAYED-ADDRESS attribute (IPv4 only).
+fn parse_turn_allocate_success(msg: &[u8], txn_id: &[u8; 12]) -> Result<SocketAddr> {
+    if msg.len() < 20 {
+        return Err(VpnError::NatTraversalFailed("TURN response too short".to_string()));
+    }
+    let msg_type = u16::from_be_bytes([msg[0], msg[1]]);
+    if msg_type != TURN_ALLOCATE_SUCCESS {
+        return Err(VpnError::NatTraversalFailed(format!("TURN Allocate rejected (type {:#06x})", msg_type)));
+    }
+    if &msg[8..20] != txn_id {
+        return Err(VpnError::NatTraversalFailed("TURN transaction ID mismatch".to_string()));
+    }
+
+    let mut relayed = None;
+    let mut lifetime_secs = None;
+    for (attr_type, value) in iter_attrs(msg) {
+        match attr_type {
+            ATTR_XOR_RELAYED_ADDRESS if value.len() >= 8 => relayed = Some(xor_address_from_attr(value)),
+            ATTR_LIFETIME if value.len() == 4 => {
+                lifetime_secs = Some(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+            }
+            _ => {}
+        }
+    }
+
+    let relayed = relayed.ok_or_else(|| VpnError::NatTraversalFailed("TURN Allocate response missing XOR-RELAYED-ADDRESS".to_string()))?;
+    if let Some(lifetime) = lifetime_secs {
+        tracing::debug!("TURN allocation lifetime: {}s", lifetime);
+    }
+    Ok(relayed)
+}
+
+/// Recovers a Data Indication's peer address and payload, or `None` if
+/// `msg` isn't one (e.g. a stray response on the same socket).
+fn parse_data_indication(msg: &[u8]) -> Option<(SocketAddr, &[u8])> {
+    if msg.len() < 20 || u16::from_be_bytes([msg[0], msg[1]]) != TURN_DATA_INDICATION {
+        return None;
+    }
+
+    let (mut from, mut data) = (None, None);
+    for (attr_type, value) in iter_attrs(msg) {
+        match attr_type {
+            ATTR_XOR_PEER_ADDRESS if value.len() >= 8 => from = Some(xor_address_from_attr(value)),
+            ATTR_DATA => data = Some(value),
+            _ => {}
+        }
+    }
+
+    match (from, data) {
+        (Some(from), Some(data)) => Some((from, data)),
+        _ => None,
+    }
+}
+
+/// Un-masks an XOR-MAPPED/XOR-RELAYED/XOR-PEER-ADDRESS attribute value
+/// (IPv4 only — the common case for this client's candidates).
+fn xor_address_from_attr(value: &[u8]) -> SocketAddr {
+    let xport = u16::from_be_bytes([value[2], value[3]]);
+    let port = xport ^ ((STUN_MAGIC_COOKIE >> 16) as u16);
+    let xaddr = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+    let ip = Ipv4Addr::from(xaddr ^ STUN_MAGIC_COOKIE);
+    SocketAddr::new(IpAddr::V4(ip), port)
+}
+
+/// Walks a STUN/TURN message's TLV attribute list starting at offset 20,
+/// honoring the 4-byte padding rule and the header's declared length.
+fn iter_attrs(msg: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let msg_len = u16::from_be_bytes([msg[2], msg[3]]) as usize;
+    let end = (20 + msg_len).min(msg.len());
+    let mut offset = 20;
+    std::iter::from_fn(move || {
+        if offset + 4 > end {
+            return None;
+        }
+        let attr_type = u16::from_be_bytes([msg[offset], msg[offset + 1]]);
+        let attr_len = u16::from_be_bytes([msg[offset + 2], msg[offset + 3]]) as usize;
+        let value_start = offset + 4;
+        let value_end = value_start + attr_len;
+        if value_end > end {
+            return None;
+        }
+        let value = &msg[value_start..value_end];
+        offset = value_end + ((4 - (attr_len % 4)) % 4);
+        Some((attr_type, value))
+    })
+}
+
+/// Background task that re-sends a Refresh request (RFC 5766 section 7)
+/// at 80% of the allocation's 600-second default lifetime, keeping it
+/// alive for as long as the `NatTraversal` (and this task) stays alive.
+fn spawn_turn_refresh_task(
+    socket: Arc<UdpSocket>,
+    server_addr: SocketAddr,
+    credentials: TurnCredentials,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        // RFC 5766 section 2.2 defaults an allocation's lifetime to 600s
+        // absent an explicit LIFETIME attribute in the request; refresh
+        // comfortably before that.
+        let refresh_every = Duration::from_secs(480);
+        loop {
+            tokio::time::sleep(refresh_every).await;
+
+            let txn_id = random_transaction_id();
+            let mut request = Vec::with_capacity(96);
+            request.extend_from_slice(&TURN_REFRESH_REQUEST.to_be_bytes());
+            request.extend_from_slice(&0u16.to_be_bytes());
+            request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+            request.extend_from_slice(&txn_id);
+            write_attr(&mut request, ATTR_USERNAME, credentials.username.as_bytes());
+            write_attr(&mut request, ATTR_REALM, credentials.realm.as_bytes());
+            write_attr(&mut request, ATTR_NONCE, credentials.nonce.as_bytes());
+            append_message_integrity(&mut request, &credentials.key);
+            finalize_length(&mut request);
+
+            if let Err(e) = socket.send_to(&request, server_addr).await {
+                tracing::warn!("TURN refresh send failed: {}", e);
+            }
+            // Best-effort: the response (if any) is read opportunistically
+            // by whatever next calls `recv_from_relay`; a dropped refresh
+            // just means the allocation expires and the caller has to
+            // fall back again.
+        }
+    })
 }
 
 impl Default for NatConfig {
@@ -8,9 +8,23 @@ pub enum ObfuscationStrategy {
     RandomPadding,       // Hides packet size
     TlsWrapping,         // Makes traffic look like standard HTTPS
     Http2Mimicry,        // Imitates modern web browser behavior
+    WebSocket,           // Wraps payloads as RFC 6455 masked binary frames
     Full,                // Combines multiple techniques for maximum stealth
 }
 
+/// Selectable carrier for a tunnel's byte stream, orthogonal to both the VPN
+/// protocol and the `ObfuscationStrategy` applied to individual packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransportMode {
+    /// Raw UDP/TCP straight to the server
+    #[default]
+    Direct,
+    /// Carries the protocol's byte stream inside a WebSocket-over-TLS
+    /// connection so it looks like ordinary HTTPS traffic on port 443 and
+    /// passes firewalls that only allow outbound 443.
+    WebSocketTls,
+}
+
 #[derive(Debug, Clone)]
 pub struct ObfuscationConfig {
     pub strategy: ObfuscationStrategy,
@@ -18,6 +32,11 @@ pub struct ObfuscationConfig {
     pub max_padding: usize,
     pub min_delay_ms: u64,
     pub max_delay_ms: u64,
+    /// Negotiated path MTU, usually set from [`crate::mtu::discover_path_mtu`].
+    /// When set, `obfuscate()` segments inputs so that no wrapped record
+    /// exceeds this size and `deobfuscate()` reassembles them. `None` (the
+    /// default) disables segmentation entirely, matching prior behavior.
+    pub path_mtu: Option<usize>,
 }
 
 impl Default for ObfuscationConfig {
@@ -28,6 +47,7 @@ impl Default for ObfuscationConfig {
             max_padding: 128,
             min_delay_ms: 0,
             max_delay_ms: 50,
+            path_mtu: None,
         }
     }
 }
@@ -36,6 +56,12 @@ impl Default for ObfuscationConfig {
 pub struct ObfuscationEngine {
     config: ObfuscationConfig,
     rng: rand::rngs::ThreadRng,
+    /// Next odd stream id `apply_http2_mimicry` will open, per RFC 7540 §5.1.1
+    /// (client-initiated streams use odd ids).
+    http2_next_stream_id: u32,
+    /// Whether the connection preface + initial SETTINGS exchange has
+    /// already been emitted for this engine's lifetime.
+    http2_preface_sent: bool,
 }
 
 impl ObfuscationEngine {
@@ -43,16 +69,29 @@ impl ObfuscationEngine {
         Self {
             config,
             rng: rand::thread_rng(),
+            http2_next_stream_id: 1,
+            http2_preface_sent: false,
         }
     }
 
-    /// Wraps data into an obfuscated format before sending
+    /// Wraps data into an obfuscated format before sending. If a
+    /// [`ObfuscationConfig::path_mtu`] is set, `data` is first segmented so
+    /// that each individually-wrapped record stays within it.
     pub fn obfuscate(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(data.len() + 32);
+        for chunk in self.segment(data) {
+            result.extend(self.obfuscate_one(&chunk));
+        }
+        result
+    }
+
+    fn obfuscate_one(&mut self, data: &[u8]) -> Vec<u8> {
         match self.config.strategy {
             ObfuscationStrategy::None => data.to_vec(),
             ObfuscationStrategy::RandomPadding => self.apply_random_padding(data),
             ObfuscationStrategy::TlsWrapping => self.apply_tls_wrapping(data),
             ObfuscationStrategy::Http2Mimicry => self.apply_http2_mimicry(data),
+            ObfuscationStrategy::WebSocket => self.apply_websocket_framing(data),
             ObfuscationStrategy::Full => {
                 let padded = self.apply_random_padding(data);
                 self.apply_tls_wrapping(&padded)
@@ -60,6 +99,41 @@ impl ObfuscationEngine {
         }
     }
 
+    /// Splits `data` into chunks that each fit within `path_mtu` once this
+    /// engine's per-record framing overhead is added. Returns `data`
+    /// unsplit when no `path_mtu` is configured or it already fits.
+    fn segment(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let Some(mtu) = self.config.path_mtu else {
+            return vec![data.to_vec()];
+        };
+
+        let max_payload = mtu.saturating_sub(self.per_record_overhead()).max(1);
+        if data.len() <= max_payload {
+            vec![data.to_vec()]
+        } else {
+            data.chunks(max_payload).map(|c| c.to_vec()).collect()
+        }
+    }
+
+    /// Worst-case bytes [`Self::obfuscate_one`] adds on top of a single
+    /// record's payload for the current strategy, used by [`Self::segment`]
+    /// to keep wrapped records within the path MTU.
+    fn per_record_overhead(&self) -> usize {
+        const TLS_RECORD_HEADER: usize = 5;
+        const HTTP2_FRAME_HEADER: usize = 9;
+        const WEBSOCKET_FRAME_HEADER: usize = 14; // 2-byte header + 64-bit extended length + 4-byte mask key
+        const PADDING_HEADER: usize = 4; // original-length + padding-length, each u16
+
+        match self.config.strategy {
+            ObfuscationStrategy::None => 0,
+            ObfuscationStrategy::RandomPadding => PADDING_HEADER + self.config.max_padding,
+            ObfuscationStrategy::TlsWrapping => TLS_RECORD_HEADER,
+            ObfuscationStrategy::Http2Mimicry => HTTP2_FRAME_HEADER,
+            ObfuscationStrategy::WebSocket => WEBSOCKET_FRAME_HEADER,
+            ObfuscationStrategy::Full => TLS_RECORD_HEADER + PADDING_HEADER + self.config.max_padding,
+        }
+    }
+
     /// Restores original data from an obfuscated packet
     pub fn deobfuscate(&mut self, data: &[u8]) -> Vec<u8> {
         match self.config.strategy {
@@ -67,6 +141,7 @@ impl ObfuscationEngine {
             ObfuscationStrategy::RandomPadding => self.remove_random_padding(data),
             ObfuscationStrategy::TlsWrapping => self.remove_tls_wrapping(data),
             ObfuscationStrategy::Http2Mimicry => self.remove_http2_mimicry(data),
+            ObfuscationStrategy::WebSocket => self.remove_websocket_framing(data),
             ObfuscationStrategy::Full => {
                 let unwrapped = self.remove_tls_wrapping(data);
                 self.remove_random_padding(&unwrapped)
@@ -80,34 +155,63 @@ impl ObfuscationEngine {
         Duration::from_millis(delay_ms)
     }
 
+    /// Applies a path MTU (typically from [`crate::mtu::discover_path_mtu`])
+    /// so subsequent `obfuscate()` calls segment oversized inputs instead of
+    /// emitting a single record that would exceed it.
+    pub fn set_path_mtu(&mut self, mtu: usize) {
+        self.config.path_mtu = Some(mtu);
+    }
+
+    /// The path MTU currently applied, if any.
+    pub fn path_mtu(&self) -> Option<usize> {
+        self.config.path_mtu
+    }
+
     fn apply_random_padding(&mut self, data: &[u8]) -> Vec<u8> {
         let padding_size = self.rng.gen_range(self.config.min_padding..=self.config.max_padding);
-        
+
         let original_len = data.len() as u16;
-        let mut result = Vec::with_capacity(2 + data.len() + padding_size);
-        
-        // Structure: [OriginalLength(2)][Payload][RandomPadding]
+        let mut result = Vec::with_capacity(4 + data.len() + padding_size);
+
+        // Structure: [OriginalLength(2)][PaddingLength(2)][Payload][RandomPadding].
+        // The padding length is carried explicitly (rather than implied by
+        // "whatever's left") so multiple records can be concatenated, e.g.
+        // by MTU segmentation, and parsed back out one at a time.
         result.extend_from_slice(&original_len.to_be_bytes());
+        result.extend_from_slice(&(padding_size as u16).to_be_bytes());
         result.extend_from_slice(data);
         for _ in 0..padding_size {
             result.push(self.rng.gen());
         }
-        
+
         result
     }
 
+    /// Parses every `[OriginalLength][PaddingLength][Payload][Padding]`
+    /// record in `data` and concatenates their payloads, so this also
+    /// reassembles inputs `apply_random_padding` segmented across records.
     fn remove_random_padding(&mut self, data: &[u8]) -> Vec<u8> {
-        if data.len() < 2 {
-            return data.to_vec();
+        let mut result = Vec::new();
+        let mut cursor = 0;
+
+        while cursor + 4 <= data.len() {
+            let original_len = u16::from_be_bytes([data[cursor], data[cursor + 1]]) as usize;
+            let padding_len = u16::from_be_bytes([data[cursor + 2], data[cursor + 3]]) as usize;
+            let payload_start = cursor + 4;
+
+            if payload_start + original_len + padding_len > data.len() {
+                break;
+            }
+
+            result.extend_from_slice(&data[payload_start..payload_start + original_len]);
+            cursor = payload_start + original_len + padding_len;
         }
-        
-        let original_len = u16::from_be_bytes([data[0], data[1]]) as usize;
-        
-        if data.len() < 2 + original_len {
+
+        if result.is_empty() && !data.is_empty() {
             return data.to_vec();
         }
-        
-        data[2..2 + original_len].to_vec()
+
+        result
     }
 
     /// Encapsulates data into a fake TLS Application Data record (0x17)
@@ -125,57 +229,219 @@ impl ObfuscationEngine {
         result
     }
 
+    /// Parses every TLS Application Data record in `data` and concatenates
+    /// their payloads, so this also reassembles inputs `apply_tls_wrapping`
+    /// segmented across records.
     fn remove_tls_wrapping(&mut self, data: &[u8]) -> Vec<u8> {
-        if data.len() < 5 {
-            return data.to_vec();
-        }
-        
-        if data[0] == 0x17 && data[1] == 0x03 && data[2] == 0x03 {
-            let payload_len = u16::from_be_bytes([data[3], data[4]]) as usize;
-            if data.len() >= 5 + payload_len {
-                return data[5..5 + payload_len].to_vec();
+        let mut result = Vec::new();
+        let mut cursor = 0;
+
+        while cursor + 5 <= data.len() && data[cursor] == 0x17 && data[cursor + 1] == 0x03 && data[cursor + 2] == 0x03 {
+            let payload_len = u16::from_be_bytes([data[cursor + 3], data[cursor + 4]]) as usize;
+            let payload_start = cursor + 5;
+
+            if payload_start + payload_len > data.len() {
+                break;
             }
+
+            result.extend_from_slice(&data[payload_start..payload_start + payload_len]);
+            cursor = payload_start + payload_len;
         }
-        
-        data.to_vec()
+
+        if result.is_empty() && !data.is_empty() {
+            return data.to_vec();
+        }
+
+        result
+    }
+
+    /// The 24-byte client connection preface every HTTP/2 connection opens with.
+    const HTTP2_PREFACE: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+    /// Appends a 9-byte HTTP/2 frame header (RFC 7540 §4.1) followed by `payload`.
+    fn push_http2_frame(buf: &mut Vec<u8>, frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) {
+        let len = payload.len() as u32;
+        buf.push(((len >> 16) & 0xFF) as u8);
+        buf.push(((len >> 8) & 0xFF) as u8);
+        buf.push((len & 0xFF) as u8);
+        buf.push(frame_type);
+        buf.push(flags);
+        buf.extend_from_slice(&(stream_id & 0x7FFF_FFFF).to_be_bytes());
+        buf.extend_from_slice(payload);
     }
 
-    /// Encapsulates data into a fake HTTP/2 DATA frame
+    /// Encapsulates `data` as a realistic HTTP/2 frame sequence: the
+    /// connection preface and initial SETTINGS exchange (once per engine
+    /// lifetime), then a HEADERS frame carrying a minimal HPACK-encoded
+    /// `:method`/`:path`/`:authority` block opening a fresh odd stream, and
+    /// a DATA frame on that stream carrying the actual payload.
     fn apply_http2_mimicry(&mut self, data: &[u8]) -> Vec<u8> {
-        let mut result = Vec::with_capacity(9 + data.len());
-        
-        // Frame Header
-        let len = data.len() as u32;
-        result.push(((len >> 16) & 0xFF) as u8);
-        result.push(((len >> 8) & 0xFF) as u8);
-        result.push((len & 0xFF) as u8);
-        
-        result.push(0x00); // Type: DATA
-        result.push(0x01); // Flags: END_STREAM
-        
-        let stream_id = (self.rng.gen::<u32>() | 1) & 0x7FFFFFFF;
-        result.extend_from_slice(&stream_id.to_be_bytes());
-        
-        result.extend_from_slice(data);
-        
+        let mut result = Vec::with_capacity(64 + data.len());
+
+        if !self.http2_preface_sent {
+            result.extend_from_slice(Self::HTTP2_PREFACE);
+            // Empty initial SETTINGS frame (stream 0) and its ACK.
+            Self::push_http2_frame(&mut result, 0x04, 0x00, 0, &[]);
+            Self::push_http2_frame(&mut result, 0x04, 0x01, 0, &[]);
+            self.http2_preface_sent = true;
+        }
+
+        let stream_id = self.http2_next_stream_id;
+        self.http2_next_stream_id = self.http2_next_stream_id.wrapping_add(2);
+
+        // Minimal HPACK block: indexed `:method: GET` (62), indexed `:path: /` (4),
+        // then a literal `:authority` (indexed name, static table index 1).
+        let authority = b"example.com";
+        let mut header_block = vec![0x82, 0x84, 0x41, authority.len() as u8];
+        header_block.extend_from_slice(authority);
+
+        Self::push_http2_frame(&mut result, 0x01, 0x04, stream_id, &header_block); // HEADERS, END_HEADERS
+        Self::push_http2_frame(&mut result, 0x00, 0x01, stream_id, data); // DATA, END_STREAM
+
         result
     }
 
+    /// Walks the HTTP/2 frame sequence produced by `apply_http2_mimicry`,
+    /// skipping the preface and any non-DATA frames, and reassembles the
+    /// concatenated DATA payloads.
     fn remove_http2_mimicry(&mut self, data: &[u8]) -> Vec<u8> {
-        if data.len() < 9 {
-            return data.to_vec();
+        let mut cursor = if data.starts_with(Self::HTTP2_PREFACE) {
+            Self::HTTP2_PREFACE.len()
+        } else {
+            0
+        };
+
+        let mut result = Vec::new();
+
+        while cursor + 9 <= data.len() {
+            let len = ((data[cursor] as usize) << 16) | ((data[cursor + 1] as usize) << 8) | (data[cursor + 2] as usize);
+            let frame_type = data[cursor + 3];
+            let payload_start = cursor + 9;
+
+            if payload_start + len > data.len() {
+                break;
+            }
+
+            if frame_type == 0x00 {
+                result.extend_from_slice(&data[payload_start..payload_start + len]);
+            }
+
+            cursor = payload_start + len;
         }
-        
-        let len = ((data[0] as usize) << 16) | ((data[1] as usize) << 8) | (data[2] as usize);
-        
-        if data.len() >= 9 + len {
-            return data[9..9 + len].to_vec();
+
+        result
+    }
+
+    /// Encapsulates `data` as a single masked RFC 6455 binary client frame
+    /// (FIN=1, opcode=0x2, MASK=1), using the 7/16/64-bit extended length
+    /// encoding depending on payload size.
+    fn apply_websocket_framing(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(data.len() + 14);
+
+        // Byte 0: FIN(1) + RSV(000) + Opcode(0010 = binary)
+        frame.push(0b1000_0010);
+
+        let len = data.len();
+        if len <= 125 {
+            frame.push(0x80 | len as u8); // MASK bit set + 7-bit length
+        } else if len <= u16::MAX as usize {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        } else {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
         }
-        
-        data.to_vec()
+
+        let mask_key: [u8; 4] = self.rng.gen();
+        frame.extend_from_slice(&mask_key);
+
+        frame.extend(data.iter().enumerate().map(|(i, b)| b ^ mask_key[i % 4]));
+
+        frame
+    }
+
+    /// Parses every (optionally masked) RFC 6455 frame in `data` and
+    /// concatenates their unmasked payloads, so this also reassembles
+    /// inputs `apply_websocket_framing` segmented across frames.
+    fn remove_websocket_framing(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut result = Vec::new();
+        let mut cursor = 0;
+
+        while cursor + 2 <= data.len() {
+            let masked = data[cursor + 1] & 0x80 != 0;
+            let mut len = (data[cursor + 1] & 0x7F) as usize;
+            let mut offset = cursor + 2;
+
+            if len == 126 {
+                if data.len() < offset + 2 {
+                    break;
+                }
+                len = u16::from_be_bytes([data[offset], data[offset + 1]]) as usize;
+                offset += 2;
+            } else if len == 127 {
+                if data.len() < offset + 8 {
+                    break;
+                }
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&data[offset..offset + 8]);
+                len = u64::from_be_bytes(bytes) as usize;
+                offset += 8;
+            }
+
+            let mask_key = if masked {
+                if data.len() < offset + 4 {
+                    break;
+                }
+                let key = [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]];
+                offset += 4;
+                Some(key)
+            } else {
+                None
+            };
+
+            if data.len() < offset + len {
+                break;
+            }
+
+            let payload = &data[offset..offset + len];
+            match mask_key {
+                Some(key) => result.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4])),
+                None => result.extend_from_slice(payload),
+            }
+
+            cursor = offset + len;
+        }
+
+        if result.is_empty() && !data.is_empty() {
+            return data.to_vec();
+        }
+
+        result
     }
 }
 
+/// RFC 6455 §1.3 handshake magic GUID appended to the client's `Sec-WebSocket-Key`.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Generates a random, base64-encoded 16-byte `Sec-WebSocket-Key` for the client handshake.
+pub fn generate_websocket_key() -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    let key: [u8; 16] = rand::thread_rng().gen();
+    general_purpose::STANDARD.encode(key)
+}
+
+/// Computes the expected `Sec-WebSocket-Accept` value for a given client key:
+/// `base64(SHA1(key + magic GUID))`, per RFC 6455 §1.3.
+pub fn compute_websocket_accept(client_key: &str) -> String {
+    use base64::{engine::general_purpose, Engine as _};
+    use sha1::{Digest, Sha1};
+
+    let mut hasher = Sha1::new();
+    hasher.update(client_key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    general_purpose::STANDARD.encode(hasher.finalize())
+}
+
 pub struct ObfuscationStats {
     pub packets_obfuscated: u64,
     pub packets_deobfuscated: u64,
@@ -231,7 +497,13 @@ mod tests {
         let extracted = engine.deobfuscate(&mimicked);
 
         assert_eq!(extracted, original);
-        assert_eq!(mimicked[3], 0x00);
+        assert!(mimicked.starts_with(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"));
+
+        // A second packet on the same engine shouldn't repeat the preface,
+        // and should open the next odd stream id.
+        let second = engine.obfuscate(b"follow-up packet");
+        assert!(!second.starts_with(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"));
+        assert_eq!(engine.deobfuscate(&second), b"follow-up packet");
     }
 
     #[test]
@@ -250,4 +522,97 @@ mod tests {
         assert_eq!(deobfuscated, original);
         assert!(obfuscated.len() > original.len() + 5);
     }
+
+    #[test]
+    fn test_websocket_framing() {
+        let mut engine = ObfuscationEngine::new(ObfuscationConfig {
+            strategy: ObfuscationStrategy::WebSocket,
+            ..Default::default()
+        });
+
+        let original = b"WebSocket disguised payload";
+        let framed = engine.obfuscate(original);
+        let extracted = engine.deobfuscate(&framed);
+
+        assert_eq!(extracted, original);
+        assert_eq!(framed[0], 0b1000_0010);
+        assert_eq!(framed[1] & 0x80, 0x80);
+    }
+
+    #[test]
+    fn test_websocket_framing_large_payload() {
+        let mut engine = ObfuscationEngine::new(ObfuscationConfig {
+            strategy: ObfuscationStrategy::WebSocket,
+            ..Default::default()
+        });
+
+        let original = vec![0x42u8; 70_000];
+        let framed = engine.obfuscate(&original);
+        let extracted = engine.deobfuscate(&framed);
+
+        assert_eq!(extracted, original);
+        assert_eq!(framed[1] & 0x7F, 127);
+    }
+
+    #[test]
+    fn test_websocket_accept_computation() {
+        // Example from RFC 6455 §1.3
+        let accept = compute_websocket_accept("dGhlIHNhbXBsZSBub25jZQ==");
+        assert_eq!(accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_mtu_segmentation_roundtrip_tls() {
+        let mut engine = ObfuscationEngine::new(ObfuscationConfig {
+            strategy: ObfuscationStrategy::TlsWrapping,
+            path_mtu: Some(64),
+            ..Default::default()
+        });
+
+        let original = vec![0x5Au8; 500];
+        let obfuscated = engine.obfuscate(&original);
+        let deobfuscated = engine.deobfuscate(&obfuscated);
+
+        assert_eq!(deobfuscated, original);
+        // Every TLS record in the wire format must respect the MTU.
+        let mut cursor = 0;
+        while cursor + 5 <= obfuscated.len() {
+            let record_len = u16::from_be_bytes([obfuscated[cursor + 3], obfuscated[cursor + 4]]) as usize;
+            assert!(5 + record_len <= 64);
+            cursor += 5 + record_len;
+        }
+    }
+
+    #[test]
+    fn test_mtu_segmentation_roundtrip_full() {
+        let mut engine = ObfuscationEngine::new(ObfuscationConfig {
+            strategy: ObfuscationStrategy::Full,
+            min_padding: 5,
+            max_padding: 20,
+            path_mtu: Some(80),
+            ..Default::default()
+        });
+
+        let original = vec![0x11u8; 300];
+        let obfuscated = engine.obfuscate(&original);
+        let deobfuscated = engine.deobfuscate(&obfuscated);
+
+        assert_eq!(deobfuscated, original);
+    }
+
+    #[test]
+    fn test_no_mtu_preserves_single_record() {
+        // Without a path_mtu, behavior is unchanged: one input produces
+        // exactly one wrapped record.
+        let mut engine = ObfuscationEngine::new(ObfuscationConfig {
+            strategy: ObfuscationStrategy::TlsWrapping,
+            ..Default::default()
+        });
+
+        let original = vec![0x7Fu8; 2000];
+        let obfuscated = engine.obfuscate(&original);
+
+        assert_eq!(obfuscated.len(), 5 + original.len());
+        assert_eq!(engine.deobfuscate(&obfuscated), original);
+    }
 }
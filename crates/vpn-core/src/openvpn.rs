@@ -5,26 +5,49 @@
 //! sauf si lancé avec des capabilities spécifiques.
 
 use async_trait::async_trait;
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
 use tokio::process::{Child, Command};
-use tracing::{error, info, warn};
+use tokio::sync::{oneshot, Mutex};
+use tracing::{info, warn};
 
 use crate::{
+    config_template::ConfigTemplate,
     error::{Result, VpnError},
+    hooks::{HookConfig, HookContext, HookEvent},
+    killswitch::KillSwitch,
     protocol::VpnProtocol,
     tunnel::{ConnectionConfig, Credentials, TunnelHandle, TunnelStats, VpnTunnel},
 };
 
+/// How long to wait for the management interface to report `CONNECTED`
+/// (with a negotiated IP) before giving up on the connection attempt.
+const MANAGEMENT_CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
 /// Tunnel OpenVPN
 pub struct OpenVpnTunnel {
     process: Option<Child>,
     config_file: Option<PathBuf>,
     auth_file: Option<PathBuf>,
     start_time: Option<Instant>,
-    bytes_sent: u64,
-    bytes_received: u64,
+    /// Updated by the management-interface reader task as `>BYTECOUNT:` lines
+    /// stream in, so `stats()` reports true throughput instead of zeros.
+    stats: Arc<Mutex<TunnelStats>>,
+    /// Set by the reader task when a `RECONNECTING`/`EXITING` state line
+    /// arrives after the initial connect, so `send`/`recv` can surface it
+    /// instead of silently operating on a dead tunnel.
+    failure: Arc<Mutex<Option<String>>>,
+    /// ifup/ifdown-style lifecycle scripts, captured from `ConnectionConfig`
+    /// at `connect()` time so `disconnect()` can run `on_disconnect`
+    /// without needing the config again.
+    hooks: HookConfig,
+    /// Egress kill-switch, armed after a successful connect when
+    /// `ConnectionConfig::kill_switch` is set.
+    kill_switch: KillSwitch,
 }
 
 impl OpenVpnTunnel {
@@ -34,8 +57,17 @@ impl OpenVpnTunnel {
             config_file: None,
             auth_file: None,
             start_time: None,
-            bytes_sent: 0,
-            bytes_received: 0,
+            stats: Arc::new(Mutex::new(TunnelStats {
+                bytes_sent: 0,
+                bytes_received: 0,
+                avg_latency_ms: 50,
+                packet_loss: 0.0,
+                uptime: Duration::from_secs(0),
+                current_throughput_mbps: 0.0,
+            })),
+            failure: Arc::new(Mutex::new(None)),
+            hooks: HookConfig::default(),
+            kill_switch: KillSwitch::new(),
         }
     }
 
@@ -60,7 +92,7 @@ impl OpenVpnTunnel {
     ) -> Result<(PathBuf, PathBuf)> {
         let temp_dir = std::env::temp_dir();
         let uuid = uuid::Uuid::new_v4();
-        
+
         let config_path = temp_dir.join(format!("ovpn_{}.ovpn", uuid));
         let auth_path = temp_dir.join(format!("ovpn_{}.auth", uuid));
 
@@ -76,6 +108,8 @@ impl OpenVpnTunnel {
             _ => "tcp",
         };
 
+        let template = ConfigTemplate::from_config(config);
+
         let ovpn_content = format!(
             "client\n\
             dev tun\n\
@@ -86,15 +120,15 @@ impl OpenVpnTunnel {
             persist-key\n\
             persist-tun\n\
             auth-user-pass {}\n\
-            cipher AES-256-GCM\n\
-            auth SHA256\n\
+            {}\n\
             verb 3\n\
             # Obfuscation basique si supporté par serveur\n\
             # scramble obfuscate password\n",
             proto,
             config.server_addr.ip(),
             config.server_addr.port(),
-            auth_path.to_string_lossy()
+            auth_path.to_string_lossy(),
+            template.render_openvpn(),
         );
 
         tokio::fs::write(&config_path, ovpn_content).await
@@ -102,13 +136,83 @@ impl OpenVpnTunnel {
 
         Ok((config_path, auth_path))
     }
+
+    /// Spawns the reader task that drives the management interface:
+    /// enables state/bytecount streaming, resolves the first `CONNECTED`
+    /// event's assigned IP through `connected_tx`, and keeps `stats`/
+    /// `failure` updated for the lifetime of the tunnel.
+    fn spawn_management_reader(
+        mut writer: impl tokio::io::AsyncWrite + Unpin + Send + 'static,
+        reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+        stats: Arc<Mutex<TunnelStats>>,
+        failure: Arc<Mutex<Option<String>>>,
+        connected_tx: oneshot::Sender<std::result::Result<Option<IpAddr>, String>>,
+    ) {
+        tokio::spawn(async move {
+            if let Err(e) = writer.write_all(b"state on\n").await {
+                warn!("Échec activation state management: {}", e);
+            }
+            if let Err(e) = writer.write_all(b"bytecount 1\n").await {
+                warn!("Échec activation bytecount management: {}", e);
+            }
+
+            let mut lines = BufReader::new(reader).lines();
+            let mut connected_tx = Some(connected_tx);
+
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) | Err(_) => break,
+                };
+
+                if let Some(rest) = line.strip_prefix(">STATE:") {
+                    // >STATE:<time>,<state>,<detail>,<local_ip>,<remote_ip>,...
+                    let fields: Vec<&str> = rest.split(',').collect();
+                    let Some(state) = fields.get(1).copied() else { continue };
+                    match state {
+                        "CONNECTED" => {
+                            let local_ip = fields.get(3).and_then(|ip| ip.parse::<IpAddr>().ok());
+                            if let Some(tx) = connected_tx.take() {
+                                let _ = tx.send(Ok(local_ip));
+                            }
+                        }
+                        "RECONNECTING" | "EXITING" => {
+                            let reason = fields.get(2).copied().unwrap_or(state).to_string();
+                            if let Some(tx) = connected_tx.take() {
+                                let _ = tx.send(Err(reason));
+                            } else {
+                                *failure.lock().await = Some(reason);
+                            }
+                        }
+                        _ => {}
+                    }
+                } else if let Some(rest) = line.strip_prefix(">BYTECOUNT:") {
+                    let mut parts = rest.split(',');
+                    if let (Some(inb), Some(outb)) = (parts.next(), parts.next()) {
+                        if let (Ok(inb), Ok(outb)) = (inb.parse::<u64>(), outb.parse::<u64>()) {
+                            let mut stats = stats.lock().await;
+                            stats.bytes_received = inb;
+                            stats.bytes_sent = outb;
+                        }
+                    }
+                }
+            }
+        });
+    }
 }
 
 #[async_trait]
 impl VpnTunnel for OpenVpnTunnel {
     async fn connect(&mut self, config: &ConnectionConfig) -> Result<TunnelHandle> {
         info!("🔌 Initialisation OpenVPN vers {}", config.server_addr);
-        
+
+        self.hooks = config.hooks.clone();
+        self.hooks.run(HookEvent::Connecting, &HookContext {
+            protocol: Some(format!("{:?}", config.protocol)),
+            server_addr: Some(config.server_addr.to_string()),
+            ..Default::default()
+        }).await?;
+
         Self::check_openvpn_installed().await?;
 
         let (username, password) = match &config.credentials {
@@ -117,74 +221,126 @@ impl VpnTunnel for OpenVpnTunnel {
         };
 
         let (config_path, auth_path) = self.create_config_files(config, &username, &password).await?;
-        
+
+        // Bind the management socket before spawning openvpn so we don't race
+        // its `--management-client` connect attempt. No management password
+        // is set (loopback-only, openvpn's default for an unauthenticated
+        // local management interface), so `--management-query-passwords`
+        // isn't needed here.
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await
+            .map_err(|e| VpnError::ConnectionFailed(format!("Échec ouverture socket management: {}", e)))?;
+        let management_port = listener.local_addr()
+            .map_err(|e| VpnError::ConnectionFailed(e.to_string()))?
+            .port();
+
         info!("🚀 Lancement openvpn (sudo requis pour TUN)...");
-        
+
         // Note: Sur Linux, openvpn nécessite souvent root pour ouvrir /dev/net/tun
         // Dans une app desktop, on utiliserait pkexec ou un service helper.
         // Ici on tente l'appel direct (échouera si non-root sauf si cap_net_admin set)
-        
+
         let mut child = Command::new("openvpn")
             .arg("--config")
             .arg(&config_path)
+            .arg("--management")
+            .arg("127.0.0.1")
+            .arg(management_port.to_string())
+            .arg("--management-client")
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
             .spawn()
             .map_err(|e| VpnError::ConnectionFailed(format!("Échec lancement openvpn: {}", e)))?;
 
-        // Wait to see if it crashes immediately
-        tokio::time::sleep(Duration::from_secs(1)).await;
-        
+        let (stream, _) = tokio::time::timeout(MANAGEMENT_CONNECT_TIMEOUT, listener.accept())
+            .await
+            .map_err(|_| VpnError::ConnectionFailed("Timeout connexion interface management".into()))?
+            .map_err(|e| VpnError::ConnectionFailed(format!("Échec accept management: {}", e)))?;
+
         if let Ok(Some(status)) = child.try_wait() {
-             return Err(VpnError::ConnectionFailed(format!("OpenVPN crashé (Exit {}). Root requis ?", status)));
+            return Err(VpnError::ConnectionFailed(format!("OpenVPN crashé (Exit {}). Root requis ?", status)));
         }
 
+        let (read_half, write_half) = tokio::io::split(stream);
+        let (connected_tx, connected_rx) = oneshot::channel();
+        Self::spawn_management_reader(
+            write_half,
+            read_half,
+            self.stats.clone(),
+            self.failure.clone(),
+            connected_tx,
+        );
+
+        let assigned_ip = match connected_rx.await {
+            Ok(Ok(Some(ip))) => ip,
+            Ok(Ok(None)) => return Err(VpnError::ConnectionFailed("CONNECTED sans IP assignée".into())),
+            Ok(Err(reason)) => return Err(VpnError::ConnectionFailed(format!("OpenVPN: {}", reason))),
+            Err(_) => return Err(VpnError::ConnectionFailed("Interface management fermée avant CONNECTED".into())),
+        };
+
         self.process = Some(child);
         self.config_file = Some(config_path);
         self.auth_file = Some(auth_path);
         self.start_time = Some(Instant::now());
 
+        info!("✅ OpenVPN tunnel établi ! IP assignée: {}", assigned_ip);
+
+        if config.kill_switch {
+            self.kill_switch.arm(config.server_addr).await?;
+        }
+
+        self.hooks.run(HookEvent::Connected, &HookContext {
+            protocol: Some(format!("{:?}", config.protocol)),
+            server_addr: Some(config.server_addr.to_string()),
+            assigned_ip: Some(assigned_ip.to_string()),
+            ..Default::default()
+        }).await?;
+
         Ok(TunnelHandle {
             id: uuid::Uuid::new_v4().to_string(),
             protocol: config.protocol,
-            assigned_ip: IpAddr::V4(Ipv4Addr::new(10, 8, 0, 2)), // IP devinée
+            assigned_ip,
             remote_endpoint: config.server_addr,
         })
     }
 
     async fn send(&mut self, data: &[u8]) -> Result<usize> {
-        self.bytes_sent += data.len() as u64;
+        if let Some(reason) = self.failure.lock().await.clone() {
+            return Err(VpnError::ConnectionFailed(reason));
+        }
         Ok(data.len())
     }
 
     async fn recv(&mut self, _buf: &mut [u8]) -> Result<usize> {
+        if let Some(reason) = self.failure.lock().await.clone() {
+            return Err(VpnError::ConnectionFailed(reason));
+        }
         tokio::time::sleep(Duration::from_millis(100)).await;
         Ok(0)
     }
 
     async fn disconnect(&mut self) -> Result<()> {
+        self.hooks.run(HookEvent::Disconnecting, &HookContext::default()).await?;
+
         if let Some(mut child) = self.process.take() {
             let _ = child.kill().await;
             let _ = child.wait().await;
         }
-        
+
         // Cleanup files
         if let Some(p) = self.config_file.take() { let _ = tokio::fs::remove_file(p).await; }
         if let Some(p) = self.auth_file.take() { let _ = tokio::fs::remove_file(p).await; }
-        
+
+        self.kill_switch.disarm().await?;
+        self.hooks.run(HookEvent::Disconnected, &HookContext::default()).await?;
+
         info!("🛑 OpenVPN arrêté");
         Ok(())
     }
 
     fn stats(&self) -> TunnelStats {
-        TunnelStats {
-            bytes_sent: self.bytes_sent,
-            bytes_received: self.bytes_received,
-            avg_latency_ms: 50,
-            packet_loss: 0.0,
-            uptime: self.start_time.map(|t| t.elapsed()).unwrap_or_default(),
-            current_throughput_mbps: 0.0,
-        }
+        let mut stats = futures::executor::block_on(async { self.stats.lock().await.clone() });
+        stats.uptime = self.start_time.map(|t| t.elapsed()).unwrap_or_default();
+        stats
     }
 
     fn protocol(&self) -> VpnProtocol {
@@ -0,0 +1,61 @@
+//! Named connection profiles persisted under `~/.worldvpn/profiles/`, so the
+//! CLI doesn't have to re-type `--api`/`--user`/`--proto` and the
+//! `SelectionContext` fields on every invocation. Follows the same
+//! `~/.worldvpn/` config-dir convention `BinaryManager` already uses for
+//! installed binaries, one YAML file per profile.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, VpnError};
+use crate::selector::{DeviceType, FirewallProfile};
+
+/// Reusable defaults for `RemoteConnect`/`Select`, loaded by name via
+/// `--profile <name>` and overridable by any explicit flag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub api: String,
+    pub user: String,
+    pub proto: String,
+    pub country: String,
+    pub firewall_profile: FirewallProfile,
+    pub device_type: DeviceType,
+}
+
+impl Profile {
+    /// `~/.worldvpn/profiles/`, created on first `save()`.
+    fn profiles_dir() -> Result<PathBuf> {
+        let home = std::env::var_os("HOME")
+            .ok_or_else(|| VpnError::InvalidConfig("HOME environment variable not set".to_string()))?;
+        Ok(PathBuf::from(home).join(".worldvpn").join("profiles"))
+    }
+
+    fn path_for(name: &str) -> Result<PathBuf> {
+        Ok(Self::profiles_dir()?.join(format!("{}.yaml", name)))
+    }
+
+    /// Loads the profile named `name`, erroring if it doesn't exist or fails to parse.
+    pub fn load(name: &str) -> Result<Self> {
+        let path = Self::path_for(name)?;
+        let contents = std::fs::read_to_string(&path).map_err(|e| {
+            VpnError::InvalidConfig(format!("Failed to read profile '{}' at {}: {}", name, path.display(), e))
+        })?;
+        serde_yaml::from_str(&contents)
+            .map_err(|e| VpnError::InvalidConfig(format!("Invalid profile '{}': {}", name, e)))
+    }
+
+    /// Writes this profile to `~/.worldvpn/profiles/<name>.yaml`, creating the directory if needed.
+    pub fn save(&self, name: &str) -> Result<()> {
+        let dir = Self::profiles_dir()?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to create profile directory {}: {}", dir.display(), e)))?;
+
+        let contents = serde_yaml::to_string(self)
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to serialize profile: {}", e)))?;
+
+        let path = Self::path_for(name)?;
+        std::fs::write(&path, contents)
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to write profile to {}: {}", path.display(), e)))
+    }
+}
@@ -21,6 +21,12 @@ pub enum VpnProtocol {
     Trojan,
     /// Advanced stealth protocol for high-censorship areas
     VLESS,
+    /// Predecessor to VLESS, still widely deployed on provider subscriptions
+    VMess,
+    /// VPN payload tunneled inside a WSS (WebSocket-over-TLS) connection on
+    /// port 443 — a last-resort transport for captive portals/firewalls
+    /// that permit nothing but outbound HTTPS
+    WebSocketTls,
 }
 
 impl VpnProtocol {
@@ -29,7 +35,11 @@ impl VpnProtocol {
         match self {
             VpnProtocol::WireGuard | VpnProtocol::WireGuardObfuscated => 51820,
             VpnProtocol::Shadowsocks => 8388,
-            VpnProtocol::OpenVpnTcp | VpnProtocol::Trojan | VpnProtocol::VLESS => 443,
+            VpnProtocol::OpenVpnTcp
+            | VpnProtocol::Trojan
+            | VpnProtocol::VLESS
+            | VpnProtocol::VMess
+            | VpnProtocol::WebSocketTls => 443,
             VpnProtocol::OpenVpnUdp => 1194,
             VpnProtocol::IKEv2 => 500,
             VpnProtocol::Hysteria2 => 32400,
@@ -45,6 +55,8 @@ impl VpnProtocol {
                 | VpnProtocol::Hysteria2
                 | VpnProtocol::Trojan
                 | VpnProtocol::VLESS
+                | VpnProtocol::VMess
+                | VpnProtocol::WebSocketTls
         )
     }
 
@@ -57,8 +69,12 @@ impl VpnProtocol {
             VpnProtocol::Shadowsocks => 0.85,
             VpnProtocol::OpenVpnUdp => 0.8,
             VpnProtocol::WireGuardObfuscated => 0.75,
-            VpnProtocol::Trojan | VpnProtocol::VLESS => 0.7,
+            VpnProtocol::Trojan | VpnProtocol::VLESS | VpnProtocol::VMess => 0.7,
             VpnProtocol::OpenVpnTcp => 0.6,
+            // WS framing plus a full TLS record layer on top adds more
+            // overhead than any other transport here offers — it's a
+            // last resort, not a daily driver.
+            VpnProtocol::WebSocketTls => 0.5,
         }
     }
 
@@ -66,7 +82,8 @@ impl VpnProtocol {
     pub fn stealth_score(&self) -> f64 {
         match self {
             VpnProtocol::VLESS => 1.0,
-            VpnProtocol::Trojan => 0.95,
+            VpnProtocol::Trojan | VpnProtocol::WebSocketTls => 0.95,
+            VpnProtocol::VMess => 0.9,
             VpnProtocol::Hysteria2 => 0.9,
             VpnProtocol::Shadowsocks => 0.85,
             VpnProtocol::WireGuardObfuscated => 0.8,
@@ -74,6 +91,43 @@ impl VpnProtocol {
             VpnProtocol::OpenVpnUdp | VpnProtocol::WireGuard | VpnProtocol::IKEv2 => 0.3,
         }
     }
+
+    /// Ranks `candidates` (one protocol per server) by a combined objective:
+    /// `w_perf*performance_score + w_stealth*stealth_score - w_latency*normalized_ewma`,
+    /// where `normalized_ewma` scales each candidate's live latency EWMA
+    /// (see the `latency` module) against the fleet median across all of
+    /// them. Lets the connection manager prefer low-latency stealthy
+    /// endpoints under active censorship instead of relying on the static
+    /// scores alone. Returns candidates paired with their score, sorted
+    /// descending (best first).
+    pub fn select_best(
+        candidates: &[crate::latency::LatencyCandidate],
+        weights: crate::latency::SelectionWeights,
+    ) -> Vec<(crate::latency::LatencyCandidate, f64)> {
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+
+        let median_ewma = {
+            let mut values: Vec<f64> = candidates.iter().map(|c| c.ewma_ms).collect();
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            values[values.len() / 2].max(1.0)
+        };
+
+        let mut ranked: Vec<_> = candidates
+            .iter()
+            .map(|c| {
+                let normalized_ewma = c.ewma_ms / median_ewma;
+                let score = weights.performance * c.protocol.performance_score()
+                    + weights.stealth * c.protocol.stealth_score()
+                    - weights.latency * normalized_ewma;
+                (c.clone(), score)
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        ranked
+    }
 }
 
 impl std::fmt::Display for VpnProtocol {
@@ -0,0 +1,102 @@
+//! Multi-endpoint reconnection support for long-running sessions: round-robins
+//! across a pool of candidate server endpoints so one unreachable address
+//! doesn't stall reconnection, and retries with exponential backoff plus
+//! jitter — the same thundering-herd avoidance gRPC/AWS SDK clients use —
+//! instead of hammering the same endpoint on a fixed interval.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use rand::Rng;
+
+/// A pool of candidate endpoints for the same logical server, tried in
+/// round-robin order.
+#[derive(Debug, Clone)]
+pub struct EndpointPool {
+    endpoints: Vec<SocketAddr>,
+    cursor: usize,
+}
+
+impl EndpointPool {
+    /// Builds a pool from at least one endpoint. Panics if `endpoints` is
+    /// empty — callers should fall back to the single endpoint already
+    /// returned by the control-plane session instead of calling this with
+    /// nothing.
+    pub fn new(endpoints: Vec<SocketAddr>) -> Self {
+        assert!(!endpoints.is_empty(), "EndpointPool needs at least one endpoint");
+        Self { endpoints, cursor: 0 }
+    }
+
+    /// Returns the next endpoint to try and advances the cursor, wrapping
+    /// back to the first endpoint after the last.
+    pub fn next(&mut self) -> SocketAddr {
+        let endpoint = self.endpoints[self.cursor];
+        self.cursor = (self.cursor + 1) % self.endpoints.len();
+        endpoint
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+}
+
+/// Exponential backoff with jitter for reconnect attempts: doubles the delay
+/// after each consecutive failure up to `cap`, then randomizes each delay by
+/// ±50% so many clients reconnecting after a shared outage don't all retry
+/// in lockstep.
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffPolicy {
+    pub base: Duration,
+    pub cap: Duration,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(250),
+            cap: Duration::from_secs(30),
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// Delay before the retry following `consecutive_failures` failures in a
+    /// row (0 on the first retry), randomized by ±50%.
+    pub fn delay_for(&self, consecutive_failures: u32) -> Duration {
+        let exponent = consecutive_failures.min(16); // avoids overflowing the shift well before `cap` is hit
+        let raw = self.base.saturating_mul(1u32 << exponent).min(self.cap);
+        let jitter = rand::thread_rng().gen_range(0.5..1.5);
+        raw.mul_f64(jitter).min(self.cap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_pool_round_robins() {
+        let a: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let b: SocketAddr = "127.0.0.1:2".parse().unwrap();
+        let mut pool = EndpointPool::new(vec![a, b]);
+        assert_eq!(pool.next(), a);
+        assert_eq!(pool.next(), b);
+        assert_eq!(pool.next(), a);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_endpoint_pool_rejects_empty() {
+        EndpointPool::new(vec![]);
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = BackoffPolicy::default();
+        for failures in 0..20 {
+            let delay = policy.delay_for(failures);
+            assert!(delay <= policy.cap);
+            assert!(delay >= policy.base.mul_f64(0.5).min(policy.cap));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::obfuscation::TransportMode;
 use crate::protocol::VpnProtocol;
 use serde::{Deserialize, Serialize};
 
@@ -20,7 +21,7 @@ pub struct NetworkQuality {
     pub stability: f64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FirewallProfile {
     Open,
     Residential,
@@ -28,7 +29,7 @@ pub enum FirewallProfile {
     NationalCensorship, // Strict DPI environments
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceType {
     Desktop,
     Mobile,
@@ -287,6 +288,27 @@ impl ProtocolSelector {
         }
     }
 
+    /// Picks the byte-stream carrier for a connection: firewalls that block
+    /// everything but 443 (corporate proxies, national censorship) call for
+    /// tunneling the protocol inside a WebSocket-over-TLS connection instead
+    /// of sending it direct.
+    pub fn select_transport(&self, context: &SelectionContext) -> TransportMode {
+        if context.firewall_profile == FirewallProfile::NationalCensorship
+            || context.firewall_profile == FirewallProfile::Corporate
+        {
+            tracing::info!("Restrictive firewall detected, routing through WSS transport");
+            return TransportMode::WebSocketTls;
+        }
+
+        if self.is_censored_country(&context.user_country)
+            && self.censorship_level(&context.user_country) >= CensorshipLevel::High
+        {
+            return TransportMode::WebSocketTls;
+        }
+
+        TransportMode::Direct
+    }
+
     #[deprecated(note = "Use score_protocol_advanced for better accuracy")]
     pub fn score_protocol(
         &self,
@@ -385,4 +407,44 @@ mod tests {
         let protocol = selector.select_best_protocol(&context);
         assert_eq!(protocol, VpnProtocol::IKEv2);
     }
+
+    #[test]
+    fn test_transport_selection_corporate_firewall() {
+        let selector = ProtocolSelector::new();
+        let context = SelectionContext {
+            network_quality: NetworkQuality {
+                latency_ms: 40,
+                packet_loss: 0.0,
+                bandwidth_mbps: 100.0,
+                stability: 0.9,
+            },
+            firewall_profile: FirewallProfile::Corporate,
+            user_country: "FR".to_string(),
+            device_type: DeviceType::Desktop,
+            battery_level: None,
+            use_case: UseCase::Browsing,
+        };
+
+        assert_eq!(selector.select_transport(&context), TransportMode::WebSocketTls);
+    }
+
+    #[test]
+    fn test_transport_selection_open_network() {
+        let selector = ProtocolSelector::new();
+        let context = SelectionContext {
+            network_quality: NetworkQuality {
+                latency_ms: 20,
+                packet_loss: 0.0,
+                bandwidth_mbps: 200.0,
+                stability: 0.95,
+            },
+            firewall_profile: FirewallProfile::Open,
+            user_country: "FR".to_string(),
+            device_type: DeviceType::Desktop,
+            battery_level: None,
+            use_case: UseCase::Browsing,
+        };
+
+        assert_eq!(selector.select_transport(&context), TransportMode::Direct);
+    }
 }
@@ -1,10 +1,14 @@
 use async_trait::async_trait;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
 use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, Command};
-use tracing::{error, info, warn};
+use tokio::sync::Mutex;
+use tracing::info;
 
 use crate::{
     error::{Result, VpnError},
@@ -13,6 +17,221 @@ use crate::{
     binary_manager::BinaryManager,
 };
 
+/// How long a warm `sslocal` instance sits idle before `ShadowsocksPool` reaps it.
+const POOL_IDLE_TTL: Duration = Duration::from_secs(120);
+
+/// Byte counters sampled from sslocal's `--stat-path` manager socket, keyed
+/// by local SOCKS5 port since that's the only handle `ShadowsocksTunnel`
+/// (and its synchronous `stats()`) has at hand.
+#[derive(Debug, Clone, Copy, Default)]
+struct PortStats {
+    bytes_total: u64,
+    last_sample_bytes: u64,
+    last_sample_at: Option<Instant>,
+    throughput_mbps: f64,
+}
+
+fn stats_map() -> &'static StdMutex<HashMap<u16, PortStats>> {
+    static STATS: OnceLock<StdMutex<HashMap<u16, PortStats>>> = OnceLock::new();
+    STATS.get_or_init(|| StdMutex::new(HashMap::new()))
+}
+
+/// Records a fresh cumulative byte count for `port`, updating its rolling
+/// throughput estimate from the delta since the previous sample.
+fn record_stat_sample(port: u16, bytes_total: u64) {
+    let mut map = stats_map().lock().unwrap();
+    let entry = map.entry(port).or_default();
+    let now = Instant::now();
+
+    if let Some(last_at) = entry.last_sample_at {
+        let elapsed = now.duration_since(last_at).as_secs_f64();
+        if elapsed > 0.0 {
+            let delta_bytes = bytes_total.saturating_sub(entry.last_sample_bytes);
+            entry.throughput_mbps = (delta_bytes as f64 * 8.0) / elapsed / 1_000_000.0;
+        }
+    }
+
+    entry.bytes_total = bytes_total;
+    entry.last_sample_bytes = bytes_total;
+    entry.last_sample_at = Some(now);
+}
+
+/// Binds the Unix domain socket sslocal's `--stat-path` connects to and
+/// relays each `{"<port>": cumulative_bytes}` line it emits into `stats_map`.
+#[cfg(unix)]
+async fn spawn_stat_listener(stat_path: PathBuf, port: u16) -> Result<()> {
+    let _ = std::fs::remove_file(&stat_path);
+
+    let listener = tokio::net::UnixListener::bind(&stat_path)
+        .map_err(|e| VpnError::ConnectionFailed(format!("Failed to bind stat socket {:?}: {}", stat_path, e)))?;
+
+    tokio::spawn(async move {
+        let Ok((stream, _)) = listener.accept().await else {
+            return;
+        };
+
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Ok(report) = serde_json::from_str::<HashMap<String, u64>>(&line) {
+                if let Some(&bytes_total) = report.get(&port.to_string()) {
+                    record_stat_sample(port, bytes_total);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn spawn_stat_listener(_stat_path: PathBuf, _port: u16) -> Result<()> {
+    // sslocal's --stat-path manager socket is a Unix domain socket; no
+    // equivalent is wired up on non-Unix platforms yet.
+    Ok(())
+}
+
+/// A warm `sslocal` subprocess kept alive for reuse, keyed by `(server_addr, method)`.
+struct PooledInstance {
+    process: Child,
+    config_file: PathBuf,
+    stat_path: PathBuf,
+    local_port: u16,
+    refs: u32,
+    idle_since: Option<Instant>,
+}
+
+/// Pool of warm `sslocal` instances keyed by `(server_addr, method)`, so rapid
+/// reconnects (e.g. a browser opening many sessions) hand out an already-running
+/// local SOCKS5 port instead of paying a fresh spawn + bring-up cost each time.
+/// Borrows wstunnel's connection-pooling idea of keeping connections open to
+/// avoid repeated handshake latency "with socks5 to navigate with a browser".
+pub struct ShadowsocksPool {
+    instances: Arc<Mutex<HashMap<(String, String), PooledInstance>>>,
+}
+
+impl ShadowsocksPool {
+    fn new() -> Self {
+        Self {
+            instances: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the process-wide pool shared by every `ShadowsocksTunnel`.
+    pub fn global() -> &'static ShadowsocksPool {
+        static POOL: OnceLock<ShadowsocksPool> = OnceLock::new();
+        POOL.get_or_init(ShadowsocksPool::new)
+    }
+
+    /// Hands back the local port of a warm instance for `(server_addr, method)`,
+    /// spawning a fresh `sslocal` if none is idle.
+    async fn acquire(
+        &self,
+        bin_path: &std::path::Path,
+        key: (String, String),
+        password: &str,
+    ) -> Result<u16> {
+        let mut instances = self.instances.lock().await;
+
+        if let Some(instance) = instances.get_mut(&key) {
+            if matches!(instance.process.try_wait(), Ok(None)) {
+                instance.refs += 1;
+                instance.idle_since = None;
+                info!("Reusing warm sslocal instance for {:?} on port {}", key, instance.local_port);
+                return Ok(instance.local_port);
+            }
+            // Stale entry (process died) — drop it and spawn fresh below.
+            instances.remove(&key);
+        }
+
+        let local_port = 1080 + (rand::random::<u16>() % 2000);
+        let ss_config = ShadowsocksConfig {
+            server: key.0.split(':').next().unwrap_or_default().to_string(),
+            server_port: key.0.rsplit(':').next().and_then(|p| p.parse().ok()).unwrap_or(8388),
+            local_address: "127.0.0.1".to_string(),
+            local_port,
+            password: password.to_string(),
+            method: key.1.clone(),
+            timeout: 300,
+        };
+
+        let config_json = serde_json::to_string_pretty(&ss_config)
+            .map_err(|e| VpnError::InvalidConfig(format!("JSON serialization error: {}", e)))?;
+        let config_path = std::env::temp_dir().join(format!("ss_config_{}.json", uuid::Uuid::new_v4()));
+        tokio::fs::write(&config_path, config_json)
+            .await
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to write config file: {}", e)))?;
+
+        // Bind the manager stat socket before sslocal can connect to it.
+        let stat_path = std::env::temp_dir().join(format!("ss_stat_{}.sock", uuid::Uuid::new_v4()));
+        spawn_stat_listener(stat_path.clone(), local_port).await?;
+
+        info!("Spawning fresh pooled sslocal instance for {:?}", key);
+        let mut child = Command::new(bin_path)
+            .arg("-c")
+            .arg(&config_path)
+            .arg("--stat-path")
+            .arg(&stat_path)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|e| VpnError::ConnectionFailed(format!("Failed to launch sslocal: {}", e)))?;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(VpnError::ConnectionFailed(format!("sslocal crashed on startup: {}", status)));
+        }
+
+        instances.insert(
+            key,
+            PooledInstance {
+                process: child,
+                config_file: config_path,
+                stat_path,
+                local_port,
+                refs: 1,
+                idle_since: None,
+            },
+        );
+
+        Ok(local_port)
+    }
+
+    /// Releases a reference on the instance for `key`, marking it idle once
+    /// the last tunnel using it disconnects instead of killing it immediately.
+    async fn release(&self, key: &(String, String)) {
+        let mut instances = self.instances.lock().await;
+        if let Some(instance) = instances.get_mut(key) {
+            instance.refs = instance.refs.saturating_sub(1);
+            if instance.refs == 0 {
+                instance.idle_since = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Kills and removes every instance that has sat idle past `POOL_IDLE_TTL`.
+    pub async fn reap_idle(&self) {
+        let mut instances = self.instances.lock().await;
+        let mut expired = Vec::new();
+
+        for (key, instance) in instances.iter() {
+            if instance.idle_since.map(|t| t.elapsed() >= POOL_IDLE_TTL).unwrap_or(false) {
+                expired.push(key.clone());
+            }
+        }
+
+        for key in expired {
+            if let Some(mut instance) = instances.remove(&key) {
+                let _ = instance.process.start_kill();
+                let _ = tokio::fs::remove_file(&instance.config_file).await;
+                let _ = tokio::fs::remove_file(&instance.stat_path).await;
+                stats_map().lock().unwrap().remove(&instance.local_port);
+                info!("Reaped idle sslocal instance for {:?}", key);
+            }
+        }
+    }
+}
+
 /// Config format for the `sslocal` subprocess (Standard JSON)
 #[derive(Serialize)]
 struct ShadowsocksConfig {
@@ -25,10 +244,12 @@ struct ShadowsocksConfig {
     timeout: u64,
 }
 
-/// Tunnel implementation using shadowsocks-rust's `sslocal` binary
+/// Tunnel implementation using shadowsocks-rust's `sslocal` binary. Instances
+/// are backed by a shared `ShadowsocksPool`, so repeated connect/disconnect
+/// cycles to the same `(server_addr, method)` reuse a warm `sslocal` process
+/// instead of respawning it.
 pub struct ShadowsocksTunnel {
-    process: Option<Child>,
-    config_file: Option<PathBuf>,
+    pool_key: Option<(String, String)>,
     bytes_sent: u64,
     bytes_received: u64,
     start_time: Option<Instant>,
@@ -38,30 +259,13 @@ pub struct ShadowsocksTunnel {
 impl ShadowsocksTunnel {
     pub fn new() -> Self {
         Self {
-            process: None,
-            config_file: None,
+            pool_key: None,
             bytes_sent: 0,
             bytes_received: 0,
             start_time: None,
             local_port: 1080,
         }
     }
-
-    /// Generates a unique temporary config file for the Shadowsocks client
-    async fn create_config_file(&self, config: &ShadowsocksConfig) -> Result<PathBuf> {
-        let config_json = serde_json::to_string_pretty(config)
-            .map_err(|e| VpnError::InvalidConfig(format!("JSON serialization error: {}", e)))?;
-
-        let temp_dir = std::env::temp_dir();
-        let config_path = temp_dir.join(format!("ss_config_{}.json", uuid::Uuid::new_v4()));
-
-        tokio::fs::write(&config_path, config_json)
-            .await
-            .map_err(|e| VpnError::InvalidConfig(format!("Failed to write config file: {}", e)))?;
-
-        info!("Shadowsocks config created: {:?}", config_path);
-        Ok(config_path)
-    }
 }
 
 #[async_trait]
@@ -111,50 +315,11 @@ impl VpnTunnel for ShadowsocksTunnel {
             return Err(VpnError::InvalidConfig("Empty method or password".into()));
         }
 
-        // Assign a random local port for the SOCKS5 proxy
-        self.local_port = 1080 + (rand::random::<u8>() % 20) as u16;
-
-        let ss_config = ShadowsocksConfig {
-            server: config.server_addr.ip().to_string(),
-            server_port: config.server_addr.port(),
-            local_address: "127.0.0.1".to_string(),
-            local_port: self.local_port,
-            password,
-            method,
-            timeout: 300,
-        };
-
-        let config_path = self.create_config_file(&ss_config).await?;
-
-        // Spawn sslocal as a background process
-        info!("Launching sslocal with config {:?}", config_path);
-        let mut child = Command::new(bin_path)
-            .arg("-c")
-            .arg(&config_path)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| VpnError::ConnectionFailed(format!("Failed to launch sslocal: {}", e)))?;
-
-        // Grace period for process startup
-        tokio::time::sleep(Duration::from_millis(500)).await;
-
-        // Health check on the spawned process
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                return Err(VpnError::ConnectionFailed(format!(
-                    "sslocal crashed on startup: {}",
-                    status
-                )));
-            }
-            Ok(None) => {}
-            Err(e) => {
-                warn!("Could not check sslocal status: {}", e);
-            }
-        }
-
-        self.process = Some(child);
-        self.config_file = Some(config_path);
+        // Hand out a warm instance for this (server_addr, method) if the pool
+        // has one, otherwise spawn and register a fresh sslocal process.
+        let pool_key = (config.server_addr.to_string(), method.clone());
+        self.local_port = ShadowsocksPool::global().acquire(&bin_path, pool_key.clone(), &password).await?;
+        self.pool_key = Some(pool_key);
         self.start_time = Some(Instant::now());
 
         info!(
@@ -184,19 +349,9 @@ impl VpnTunnel for ShadowsocksTunnel {
     async fn disconnect(&mut self) -> Result<()> {
         info!("Stopping Shadowsocks tunnel...");
 
-        if let Some(mut process) = self.process.take() {
-            let _ = process.kill().await;
-            let _ = process.wait().await;
-            info!("sslocal process terminated");
-        }
-
-        // Clean up temporary config
-        if let Some(config_path) = self.config_file.take() {
-            if let Err(e) = tokio::fs::remove_file(&config_path).await {
-                error!("Error removing config {:?}: {}", config_path, e);
-            } else {
-                info!("Temporary config removed");
-            }
+        if let Some(key) = self.pool_key.take() {
+            ShadowsocksPool::global().release(&key).await;
+            info!("Released pooled sslocal instance for {:?}", key);
         }
 
         self.start_time = None;
@@ -204,13 +359,24 @@ impl VpnTunnel for ShadowsocksTunnel {
     }
 
     fn stats(&self) -> TunnelStats {
+        // sslocal's manager stat socket only reports one cumulative byte
+        // count per port rather than separate tx/rx totals, so the real
+        // figure is attributed to bytes_received (the dominant direction for
+        // typical VPN usage) while bytes_sent keeps tracking app-level writes.
+        let (bytes_received, current_throughput_mbps) = stats_map()
+            .lock()
+            .unwrap()
+            .get(&self.local_port)
+            .map(|s| (s.bytes_total, s.throughput_mbps))
+            .unwrap_or((self.bytes_received, 0.0));
+
         TunnelStats {
             bytes_sent: self.bytes_sent,
-            bytes_received: self.bytes_received,
+            bytes_received,
             avg_latency_ms: 150,
             packet_loss: 0.0,
             uptime: self.start_time.map(|t| t.elapsed()).unwrap_or_default(),
-            current_throughput_mbps: 0.0,
+            current_throughput_mbps,
         }
     }
 
@@ -218,12 +384,3 @@ impl VpnTunnel for ShadowsocksTunnel {
         VpnProtocol::Shadowsocks
     }
 }
-
-impl Drop for ShadowsocksTunnel {
-    fn drop(&mut self) {
-        // Safe process teardown if disconnect wasn't explicitly called
-        if let Some(mut process) = self.process.take() {
-            let _ = process.start_kill();
-        }
-    }
-}
@@ -0,0 +1,350 @@
+//! Parses the ecosystem's standard proxy share-link URIs straight into a
+//! `ConnectionConfig` — the same convenience vpncloud's config wizard adds
+//! over hand-rolled JSON, so a provider's `vless://`/`trojan://`/`vmess://`/
+//! `hysteria2://` subscription works as a drop-in client config instead of
+//! requiring manual assembly.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use std::collections::HashMap;
+
+use crate::error::{Result, VpnError};
+use crate::hooks::HookConfig;
+use crate::obfuscation::TransportMode;
+use crate::protocol::VpnProtocol;
+use crate::tunnel::{ConnectionConfig, Credentials, ReconnectPolicy, StreamTransport};
+
+const KNOWN_SCHEMES: [&str; 5] = ["vless://", "trojan://", "vmess://", "hysteria2://", "hy2://"];
+
+/// Parses a single share-link URI (`vless://`, `trojan://`, `vmess://`,
+/// `hysteria2://`/`hy2://`) into a ready-to-use `ConnectionConfig`.
+pub fn parse_share_uri(uri: &str) -> Result<ConnectionConfig> {
+    let uri = uri.trim();
+
+    if let Some(rest) = uri.strip_prefix("vless://") {
+        parse_authority_scheme(rest, VpnProtocol::VLESS)
+    } else if let Some(rest) = uri.strip_prefix("trojan://") {
+        parse_authority_scheme(rest, VpnProtocol::Trojan)
+    } else if let Some(rest) = uri.strip_prefix("hysteria2://") {
+        parse_authority_scheme(rest, VpnProtocol::Hysteria2)
+    } else if let Some(rest) = uri.strip_prefix("hy2://") {
+        parse_authority_scheme(rest, VpnProtocol::Hysteria2)
+    } else if let Some(rest) = uri.strip_prefix("vmess://") {
+        parse_vmess(rest)
+    } else {
+        Err(VpnError::InvalidConfig(format!(
+            "Unsupported share URI scheme: {}",
+            uri
+        )))
+    }
+}
+
+/// Decodes a base64 "subscription" blob (a newline-delimited list of share
+/// URIs) into one `ConnectionConfig` per recognized line. Blank lines and
+/// lines that aren't one of the known schemes (e.g. a provider's comment
+/// header) are skipped rather than treated as a parse failure.
+pub fn parse_subscription(base64_blob: &str) -> Result<Vec<ConnectionConfig>> {
+    let decoded = decode_base64_any(base64_blob.trim())?;
+    let text = String::from_utf8(decoded)
+        .map_err(|e| VpnError::InvalidConfig(format!("Subscription isn't valid UTF-8: {}", e)))?;
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter(|line| KNOWN_SCHEMES.iter().any(|scheme| line.starts_with(scheme)))
+        .map(parse_share_uri)
+        .collect()
+}
+
+fn decode_base64_any(s: &str) -> Result<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(s)
+        .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(s))
+        .or_else(|_| general_purpose::URL_SAFE.decode(s))
+        .or_else(|_| general_purpose::URL_SAFE_NO_PAD.decode(s))
+        .map_err(|e| VpnError::InvalidConfig(format!("Invalid base64 subscription: {}", e)))
+}
+
+/// Shared shape of `vless://`, `trojan://` and `hysteria2://` links:
+/// `userinfo@host:port?query#fragment`.
+struct Authority {
+    userinfo: String,
+    host: String,
+    port: u16,
+    query: HashMap<String, String>,
+}
+
+fn parse_authority(rest: &str) -> Result<Authority> {
+    let rest = rest.split('#').next().unwrap_or(rest);
+    let (main, query_str) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let (userinfo, hostport) = main
+        .rsplit_once('@')
+        .ok_or_else(|| VpnError::InvalidConfig("Share URI is missing user info".into()))?;
+
+    let (host, port_str) = hostport
+        .rsplit_once(':')
+        .ok_or_else(|| VpnError::InvalidConfig("Share URI is missing a port".into()))?;
+
+    let port: u16 = port_str
+        .parse()
+        .map_err(|_| VpnError::InvalidConfig(format!("Invalid port in share URI: {}", port_str)))?;
+
+    let query = query_str
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            Some((percent_decode(k), percent_decode(v)))
+        })
+        .collect();
+
+    Ok(Authority {
+        userinfo: percent_decode(userinfo),
+        host: host.to_string(),
+        port,
+        query,
+    })
+}
+
+fn parse_authority_scheme(rest: &str, protocol: VpnProtocol) -> Result<ConnectionConfig> {
+    let authority = parse_authority(rest)?;
+    let server_addr = resolve(&authority.host, authority.port)?;
+
+    Ok(ConnectionConfig {
+        protocol,
+        server_addr,
+        credentials: Credentials::Password {
+            username: None,
+            password: authority.userinfo,
+        },
+        timeout: Duration::from_secs(10),
+        transport: TransportMode::Direct,
+        cipher: None,
+        auth_digest: None,
+        dns_servers: Vec::new(),
+        routes: Vec::new(),
+        redirect_gateway: true,
+        block_outside_dns: false,
+        reconnect_policy: ReconnectPolicy::default(),
+        hooks: HookConfig::default(),
+        kill_switch: false,
+        stream_transport: stream_transport_from_query(&authority.query),
+        sni: authority.query.get("sni").cloned(),
+        alpn: parse_alpn(&authority.query),
+        dns: crate::tunnel::DnsConfig::default(),
+        allow_insecure_tls: authority.query.get("allowInsecure").map(|v| v == "1" || v == "true").unwrap_or(false),
+        mux: crate::tunnel::MuxConfig::default(),
+    })
+}
+
+/// `vmess://<base64 JSON>`, the legacy "vmess AEAD" share-link format:
+/// `{"add": host, "port": .., "id": uuid, "net": "ws"/"grpc"/"tcp", ...}`.
+fn parse_vmess(rest: &str) -> Result<ConnectionConfig> {
+    let decoded = decode_base64_any(rest.split('#').next().unwrap_or(rest))?;
+    let parsed: serde_json::Value = serde_json::from_slice(&decoded)
+        .map_err(|e| VpnError::InvalidConfig(format!("Invalid vmess JSON payload: {}", e)))?;
+
+    let host = parsed["add"]
+        .as_str()
+        .ok_or_else(|| VpnError::InvalidConfig("vmess payload is missing \"add\" (host)".into()))?;
+    let port: u16 = match &parsed["port"] {
+        serde_json::Value::String(s) => s
+            .parse()
+            .map_err(|_| VpnError::InvalidConfig(format!("Invalid vmess port: {}", s)))?,
+        serde_json::Value::Number(n) => n
+            .as_u64()
+            .and_then(|p| u16::try_from(p).ok())
+            .ok_or_else(|| VpnError::InvalidConfig("Invalid vmess port".into()))?,
+        _ => return Err(VpnError::InvalidConfig("vmess payload is missing \"port\"".into())),
+    };
+    let uuid = parsed["id"]
+        .as_str()
+        .ok_or_else(|| VpnError::InvalidConfig("vmess payload is missing \"id\" (UUID)".into()))?;
+
+    let server_addr = resolve(host, port)?;
+
+    let stream_transport = match parsed["net"].as_str().unwrap_or("tcp") {
+        "ws" => StreamTransport::Ws {
+            path: parsed["path"].as_str().unwrap_or("/").to_string(),
+            host: parsed["host"].as_str().unwrap_or(host).to_string(),
+        },
+        "grpc" => StreamTransport::Grpc {
+            service_name: parsed["path"].as_str().unwrap_or("").to_string(),
+        },
+        "httpupgrade" => StreamTransport::HttpUpgrade,
+        _ => StreamTransport::Tcp,
+    };
+
+    let sni = parsed["sni"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string());
+
+    Ok(ConnectionConfig {
+        protocol: VpnProtocol::VMess,
+        server_addr,
+        credentials: Credentials::Password {
+            username: None,
+            password: uuid.to_string(),
+        },
+        timeout: Duration::from_secs(10),
+        transport: TransportMode::Direct,
+        cipher: None,
+        auth_digest: None,
+        dns_servers: Vec::new(),
+        routes: Vec::new(),
+        redirect_gateway: true,
+        block_outside_dns: false,
+        reconnect_policy: ReconnectPolicy::default(),
+        hooks: HookConfig::default(),
+        kill_switch: false,
+        stream_transport,
+        sni,
+        alpn: Vec::new(),
+        dns: crate::tunnel::DnsConfig::default(),
+        allow_insecure_tls: false,
+        mux: crate::tunnel::MuxConfig::default(),
+    })
+}
+
+fn stream_transport_from_query(query: &HashMap<String, String>) -> StreamTransport {
+    match query.get("type").map(String::as_str) {
+        Some("ws") => StreamTransport::Ws {
+            path: query.get("path").cloned().unwrap_or_else(|| "/".to_string()),
+            host: query
+                .get("host")
+                .or_else(|| query.get("sni"))
+                .cloned()
+                .unwrap_or_default(),
+        },
+        Some("grpc") => StreamTransport::Grpc {
+            service_name: query
+                .get("serviceName")
+                .or_else(|| query.get("servicename"))
+                .cloned()
+                .unwrap_or_default(),
+        },
+        Some("httpupgrade") => StreamTransport::HttpUpgrade,
+        _ => StreamTransport::Tcp,
+    }
+}
+
+fn parse_alpn(query: &HashMap<String, String>) -> Vec<String> {
+    query
+        .get("alpn")
+        .map(|s| s.split(',').map(|p| p.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    if let Ok(ip) = host.parse() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+
+    (host, port)
+        .to_socket_addrs()
+        .map_err(|e| VpnError::InvalidConfig(format!("Failed to resolve {}: {}", host, e)))?
+        .next()
+        .ok_or_else(|| VpnError::InvalidConfig(format!("No address found for {}", host)))
+}
+
+/// Minimal percent-decoder for URI components (query values, user info) —
+/// avoids pulling in a full URL-parsing crate for these few fixed shapes.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(byte) = u8::from_str_radix(std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or(""), 16) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_vless_uri() {
+        let config = parse_share_uri("vless://uuid-123@203.0.113.5:443?type=ws&security=tls&sni=example.com&path=%2Fpath&alpn=h2,http%2F1.1").unwrap();
+
+        assert_eq!(config.protocol, VpnProtocol::VLESS);
+        assert_eq!(config.server_addr, "203.0.113.5:443".parse().unwrap());
+        assert_eq!(config.credentials, Credentials::Password { username: None, password: "uuid-123".to_string() });
+        assert_eq!(config.sni, Some("example.com".to_string()));
+        assert_eq!(config.alpn, vec!["h2".to_string(), "http/1.1".to_string()]);
+        assert_eq!(config.stream_transport, StreamTransport::Ws { path: "/path".to_string(), host: "example.com".to_string() });
+    }
+
+    #[test]
+    fn test_parse_trojan_uri() {
+        let config = parse_share_uri("trojan://s3cr3t@198.51.100.9:8443?allowInsecure=1#My%20Node").unwrap();
+
+        assert_eq!(config.protocol, VpnProtocol::Trojan);
+        assert_eq!(config.server_addr, "198.51.100.9:8443".parse().unwrap());
+        assert_eq!(config.credentials, Credentials::Password { username: None, password: "s3cr3t".to_string() });
+        assert!(config.allow_insecure_tls);
+    }
+
+    #[test]
+    fn test_parse_hysteria2_uri_and_hy2_alias() {
+        let via_full_scheme = parse_share_uri("hysteria2://auth@203.0.113.7:443?sni=h2.example.com").unwrap();
+        let via_alias = parse_share_uri("hy2://auth@203.0.113.7:443?sni=h2.example.com").unwrap();
+
+        for config in [via_full_scheme, via_alias] {
+            assert_eq!(config.protocol, VpnProtocol::Hysteria2);
+            assert_eq!(config.sni, Some("h2.example.com".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_parse_vmess_uri() {
+        let payload = json!({
+            "add": "203.0.113.11",
+            "port": 443,
+            "id": "uuid-456",
+            "net": "ws",
+            "path": "/vmess",
+            "host": "cdn.example.com",
+            "sni": "cdn.example.com",
+        });
+        let uri = format!("vmess://{}", general_purpose::STANDARD.encode(payload.to_string()));
+
+        let config = parse_share_uri(&uri).unwrap();
+
+        assert_eq!(config.protocol, VpnProtocol::VMess);
+        assert_eq!(config.server_addr, "203.0.113.11:443".parse().unwrap());
+        assert_eq!(config.credentials, Credentials::Password { username: None, password: "uuid-456".to_string() });
+        assert_eq!(config.stream_transport, StreamTransport::Ws { path: "/vmess".to_string(), host: "cdn.example.com".to_string() });
+        assert_eq!(config.sni, Some("cdn.example.com".to_string()));
+    }
+
+    #[test]
+    fn test_unsupported_scheme_is_rejected() {
+        assert!(parse_share_uri("ss://notsupported@host:1234").is_err());
+    }
+
+    #[test]
+    fn test_parse_subscription_skips_blank_and_comment_lines() {
+        let blob = "# my subscription\n\ntrojan://pw@198.51.100.1:443\nvless://uuid@198.51.100.2:443?type=tcp\n";
+        let encoded = general_purpose::STANDARD.encode(blob);
+
+        let configs = parse_subscription(&encoded).unwrap();
+
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].protocol, VpnProtocol::Trojan);
+        assert_eq!(configs[1].protocol, VpnProtocol::VLESS);
+    }
+}
@@ -0,0 +1,335 @@
+//! Local SOCKS5 listener that forwards accepted streams through the active
+//! `VpnTunnel` instead of the host's default route.
+//!
+//! This gives per-application tunneling (point a single browser at
+//! `127.0.0.1:1080`) without configuring a system-wide TUN device, for
+//! protocols whose `VpnTunnel` impl doesn't already spin up its own local
+//! SOCKS5 port via an external binary (see `ShadowsocksTunnel`,
+//! `HysteriaTunnel`, `V2RayTunnel`).
+//!
+//! Implements the subset of RFC 1928 needed for that: version negotiation,
+//! optional username/password auth (RFC 1929), the CONNECT command for TCP,
+//! and UDP ASSOCIATE for datagrams. Address parsing covers all three
+//! SOCKS5 address types (IPv4, domain, IPv6).
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::error::{Result, VpnError};
+use crate::tunnel::VpnTunnel;
+
+const SOCKS_VERSION: u8 = 0x05;
+
+/// RFC 1929 username/password a client must present before the proxy will
+/// relay its requests. Omitting this from `Socks5Proxy::new` accepts
+/// no-auth clients only.
+#[derive(Debug, Clone)]
+pub struct Socks5Auth {
+    pub username: String,
+    pub password: String,
+}
+
+/// The SOCKS5 command a client requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Connect,
+    UdpAssociate,
+}
+
+/// The address a CONNECT/UDP ASSOCIATE request named. Not used to dial out
+/// locally — the real destination is resolved on the other end of the
+/// tunnel — but kept for logging.
+#[derive(Debug, Clone)]
+enum TargetAddr {
+    Ip(SocketAddr),
+    Domain(String, u16),
+}
+
+/// A local SOCKS5 listener that relays accepted streams through a shared
+/// `VpnTunnel` rather than the host's routing table.
+pub struct Socks5Proxy {
+    listen_addr: SocketAddr,
+    tunnel: Arc<Mutex<Box<dyn VpnTunnel>>>,
+    auth: Option<Socks5Auth>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Socks5Proxy {
+    /// Builds a proxy over an already-connected tunnel. Call `start` to
+    /// begin accepting clients on `listen_addr`.
+    pub fn new(listen_addr: SocketAddr, tunnel: Arc<Mutex<Box<dyn VpnTunnel>>>, auth: Option<Socks5Auth>) -> Self {
+        Self {
+            listen_addr,
+            tunnel,
+            auth,
+            task: Mutex::new(None),
+        }
+    }
+
+    /// Binds `listen_addr` and starts accepting SOCKS5 clients in the
+    /// background. Returns once the listener is bound; each client is
+    /// handled on its own spawned task.
+    pub async fn start(&self) -> Result<()> {
+        let listener = TcpListener::bind(self.listen_addr).await.map_err(VpnError::NetworkError)?;
+        info!("SOCKS5 proxy listening on {}", self.listen_addr);
+
+        let tunnel = self.tunnel.clone();
+        let auth = self.auth.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let tunnel = tunnel.clone();
+                        let auth = auth.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_client(stream, tunnel, auth).await {
+                                warn!("SOCKS5 client {} error: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        warn!("SOCKS5 accept error, stopping listener: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        *self.task.lock().await = Some(handle);
+        Ok(())
+    }
+
+    /// Stops accepting new clients. Meant to be called alongside
+    /// `VpnTunnel::disconnect` so per-application routing doesn't outlive
+    /// the tunnel it depends on.
+    pub async fn stop(&self) {
+        if let Some(handle) = self.task.lock().await.take() {
+            handle.abort();
+        }
+    }
+}
+
+async fn handle_client(
+    mut stream: TcpStream,
+    tunnel: Arc<Mutex<Box<dyn VpnTunnel>>>,
+    auth: Option<Socks5Auth>,
+) -> Result<()> {
+    negotiate_auth(&mut stream, &auth).await?;
+    let (cmd, target) = read_request(&mut stream).await?;
+    info!("SOCKS5 {:?} request for {:?}", cmd, target);
+
+    match cmd {
+        Command::Connect => {
+            reply(&mut stream, 0x00, SocketAddr::from(([0, 0, 0, 0], 0))).await?;
+            relay_tcp(stream, tunnel).await
+        }
+        Command::UdpAssociate => {
+            let udp = UdpSocket::bind("0.0.0.0:0").await.map_err(VpnError::NetworkError)?;
+            let bound = udp.local_addr().map_err(VpnError::NetworkError)?;
+            reply(&mut stream, 0x00, bound).await?;
+            relay_udp(stream, udp, tunnel).await
+        }
+    }
+}
+
+/// Version identifier/method-selection handshake (RFC 1928 section 3),
+/// followed by RFC 1929 username/password subnegotiation when the proxy
+/// was configured with `auth`.
+async fn negotiate_auth(stream: &mut TcpStream, auth: &Option<Socks5Auth>) -> Result<()> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await.map_err(VpnError::NetworkError)?;
+    if header[0] != SOCKS_VERSION {
+        return Err(VpnError::ProtocolError(format!("Unsupported SOCKS version {}", header[0])));
+    }
+
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await.map_err(VpnError::NetworkError)?;
+
+    let wants_userpass = auth.is_some() && methods.contains(&0x02);
+    let selected = if wants_userpass { 0x02 } else { 0x00 };
+    if auth.is_some() && selected != 0x02 {
+        stream
+            .write_all(&[SOCKS_VERSION, 0xFF])
+            .await
+            .map_err(VpnError::NetworkError)?;
+        return Err(VpnError::AuthFailed);
+    }
+
+    stream
+        .write_all(&[SOCKS_VERSION, selected])
+        .await
+        .map_err(VpnError::NetworkError)?;
+
+    if let Some(expected) = auth {
+        let mut sub_header = [0u8; 2];
+        stream.read_exact(&mut sub_header).await.map_err(VpnError::NetworkError)?;
+        let mut uname = vec![0u8; sub_header[1] as usize];
+        stream.read_exact(&mut uname).await.map_err(VpnError::NetworkError)?;
+
+        let plen = [stream.read_u8().await.map_err(VpnError::NetworkError)?];
+        let mut passwd = vec![0u8; plen[0] as usize];
+        stream.read_exact(&mut passwd).await.map_err(VpnError::NetworkError)?;
+
+        let ok = uname == expected.username.as_bytes() && passwd == expected.password.as_bytes();
+        stream
+            .write_all(&[0x01, if ok { 0x00 } else { 0x01 }])
+            .await
+            .map_err(VpnError::NetworkError)?;
+        if !ok {
+            return Err(VpnError::AuthFailed);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a SOCKS5 request (section 4): `VER CMD RSV ATYP DST.ADDR DST.PORT`.
+async fn read_request(stream: &mut TcpStream) -> Result<(Command, TargetAddr)> {
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await.map_err(VpnError::NetworkError)?;
+    if header[0] != SOCKS_VERSION {
+        return Err(VpnError::ProtocolError(format!("Unsupported SOCKS version {}", header[0])));
+    }
+
+    let cmd = match header[1] {
+        0x01 => Command::Connect,
+        0x03 => Command::UdpAssociate,
+        other => return Err(VpnError::ProtocolError(format!("Unsupported SOCKS command {}", other))),
+    };
+
+    let target = match header[3] {
+        0x01 => {
+            let mut octets = [0u8; 4];
+            stream.read_exact(&mut octets).await.map_err(VpnError::NetworkError)?;
+            let port = stream.read_u16().await.map_err(VpnError::NetworkError)?;
+            TargetAddr::Ip(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(octets)), port))
+        }
+        0x03 => {
+            let len = stream.read_u8().await.map_err(VpnError::NetworkError)? as usize;
+            let mut domain = vec![0u8; len];
+            stream.read_exact(&mut domain).await.map_err(VpnError::NetworkError)?;
+            let port = stream.read_u16().await.map_err(VpnError::NetworkError)?;
+            TargetAddr::Domain(
+                String::from_utf8(domain).map_err(|e| VpnError::ProtocolError(format!("Invalid domain name: {}", e)))?,
+                port,
+            )
+        }
+        0x04 => {
+            let mut octets = [0u8; 16];
+            stream.read_exact(&mut octets).await.map_err(VpnError::NetworkError)?;
+            let port = stream.read_u16().await.map_err(VpnError::NetworkError)?;
+            TargetAddr::Ip(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        other => return Err(VpnError::ProtocolError(format!("Unsupported SOCKS address type {}", other))),
+    };
+
+    Ok((cmd, target))
+}
+
+/// Writes a SOCKS5 reply (section 6): `VER REP RSV ATYP BND.ADDR BND.PORT`.
+/// Always reports `ATYP=IPv4` since `bound` is either a wildcard (CONNECT,
+/// the tunnel hides the real bind) or our own UDP relay socket.
+async fn reply(stream: &mut TcpStream, rep: u8, bound: SocketAddr) -> Result<()> {
+    let mut out = vec![SOCKS_VERSION, rep, 0x00, 0x01];
+    match bound {
+        SocketAddr::V4(addr) => {
+            out.extend_from_slice(&addr.ip().octets());
+            out.extend_from_slice(&addr.port().to_be_bytes());
+        }
+        SocketAddr::V6(_) => {
+            out.extend_from_slice(&[0, 0, 0, 0]);
+            out.extend_from_slice(&[0, 0]);
+        }
+    }
+    stream.write_all(&out).await.map_err(VpnError::NetworkError)
+}
+
+/// Pumps bytes between the accepted TCP stream and the tunnel until either
+/// side closes. The tunnel is shared across all concurrent clients, so
+/// every `send`/`recv` call takes the lock for just that one call rather
+/// than holding it for the whole relay.
+async fn relay_tcp(mut stream: TcpStream, tunnel: Arc<Mutex<Box<dyn VpnTunnel>>>) -> Result<()> {
+    let mut client_buf = [0u8; 4096];
+    let mut tunnel_buf = vec![0u8; 65535];
+    loop {
+        tokio::select! {
+            read = stream.read(&mut client_buf) => {
+                let n = read.map_err(VpnError::NetworkError)?;
+                if n == 0 {
+                    break;
+                }
+                tunnel.lock().await.send(&client_buf[..n]).await?;
+            }
+            recv = async { tunnel.lock().await.recv(&mut tunnel_buf).await } => {
+                let n = recv?;
+                if n == 0 {
+                    continue;
+                }
+                stream.write_all(&tunnel_buf[..n]).await.map_err(VpnError::NetworkError)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Relays SOCKS5 UDP ASSOCIATE datagrams (section 7): each packet on the
+/// relay socket is unwrapped from its `RSV FRAG ATYP DST.ADDR DST.PORT`
+/// header before being handed to the tunnel, and re-wrapped on the way
+/// back. The TCP control connection stays open only to detect the client
+/// tearing down the association.
+async fn relay_udp(mut control: TcpStream, udp: UdpSocket, tunnel: Arc<Mutex<Box<dyn VpnTunnel>>>) -> Result<()> {
+    let mut datagram_buf = [0u8; 65535];
+    let mut tunnel_buf = vec![0u8; 65535];
+    let mut closed_probe = [0u8; 1];
+    // The client's source address from its most recent datagram — where
+    // replies get sent back, since a single UDP ASSOCIATE socket serves
+    // one client for the association's lifetime.
+    let mut last_client: Option<SocketAddr> = None;
+    loop {
+        tokio::select! {
+            read = udp.recv_from(&mut datagram_buf) => {
+                let (n, src) = read.map_err(VpnError::NetworkError)?;
+                last_client = Some(src);
+                // Header is `RSV(2) FRAG(1) ATYP ...`; fragmentation isn't
+                // supported, payload starts after the address fields.
+                if n < 4 {
+                    continue;
+                }
+                let addr_len = match datagram_buf[3] {
+                    0x01 => 4 + 2,
+                    0x04 => 16 + 2,
+                    0x03 => datagram_buf.get(4).map(|&l| l as usize + 1 + 2).unwrap_or(0),
+                    _ => 0,
+                };
+                let payload_start = 4 + addr_len;
+                if payload_start > n {
+                    continue;
+                }
+                tunnel.lock().await.send(&datagram_buf[payload_start..n]).await?;
+            }
+            recv = async { tunnel.lock().await.recv(&mut tunnel_buf).await } => {
+                let n = recv?;
+                if n == 0 {
+                    continue;
+                }
+                if let Some(client) = last_client {
+                    let mut packet = vec![0x00, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0];
+                    packet.extend_from_slice(&tunnel_buf[..n]);
+                    udp.send_to(&packet, client).await.map_err(VpnError::NetworkError)?;
+                }
+            }
+            probe = control.read(&mut closed_probe) => {
+                if matches!(probe, Ok(0) | Err(_)) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
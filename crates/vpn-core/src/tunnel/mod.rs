@@ -1,13 +1,22 @@
 use async_trait::async_trait;
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
 
 use crate::error::Result;
+use crate::hooks::HookConfig;
+use crate::obfuscation::TransportMode;
 use crate::protocol::VpnProtocol;
 
+pub mod tls_transport;
+pub mod transport;
 pub mod windows;
+pub mod ws_transport;
+pub use tls_transport::{TlsObfuscationConfig, TlsTransport};
+pub use transport::{Transport, UdpTransport, WsTlsTransport};
 pub use windows::WindowsTunnel;
+pub use ws_transport::{WssConnectionPool, WssTransport, WssTransportConfig, WssTransportServer};
 
 /// Provides a handle to an active VPN tunnel connection
 #[derive(Debug, Clone)]
@@ -25,10 +34,127 @@ pub struct ConnectionConfig {
     pub server_addr: SocketAddr,
     pub credentials: Credentials,
     pub timeout: Duration,
+    /// Carrier for the protocol's byte stream (e.g. direct UDP/TCP vs. WSS)
+    pub transport: TransportMode,
+    /// Data-channel cipher. `None` falls back to `ConfigTemplate`'s default
+    /// (AES-256-GCM).
+    pub cipher: Option<String>,
+    /// HMAC digest for auth. `None` falls back to `ConfigTemplate`'s default
+    /// (SHA256).
+    pub auth_digest: Option<String>,
+    /// DNS servers to push to the client. Empty means no DNS push.
+    pub dns_servers: Vec<IpAddr>,
+    /// Subnets to route through the tunnel for split-tunneling. Ignored
+    /// (full-tunnel) when `redirect_gateway` is set or this is empty.
+    pub routes: Vec<IpNet>,
+    /// Force all traffic through the tunnel, overriding `routes`.
+    pub redirect_gateway: bool,
+    /// Block DNS queries that try to bypass the tunnel's pushed resolvers.
+    pub block_outside_dns: bool,
+    /// Retry cadence for a tunnel's reconnection supervisor (see
+    /// `IKEv2Tunnel`'s MOBIKE-aware auto-reconnect) when the host's default
+    /// route changes mid-session.
+    pub reconnect_policy: ReconnectPolicy,
+    /// ifup/ifdown-style scripts run at lifecycle boundaries (see
+    /// `crate::hooks`). Events without a configured script are a no-op.
+    pub hooks: HookConfig,
+    /// Arm `crate::killswitch::KillSwitch` around the connection so traffic
+    /// fails closed if the underlying daemon (`openvpn`/`charon-cmd`)
+    /// crashes, instead of leaking out the default route.
+    pub kill_switch: bool,
+    /// How `V2RayTunnel`/`HysteriaTunnel` wrap their stream so it blends
+    /// in with ordinary traffic instead of presenting a fingerprintable
+    /// raw TCP+TLS handshake. Defaults to `Tcp` for backward compatibility.
+    pub stream_transport: StreamTransport,
+    /// TLS SNI to present during the handshake (e.g. a domain fronted
+    /// behind a CDN). `None` falls back to each tunnel's own default fake
+    /// SNI.
+    pub sni: Option<String>,
+    /// TLS ALPN protocol list. Empty means the underlying client's default.
+    pub alpn: Vec<String>,
+    /// Resolver override for protocols (currently `V2RayTunnel`) that do
+    /// their own DNS resolution, so name lookups traverse the encrypted
+    /// channel instead of leaking to the host's default resolver.
+    pub dns: DnsConfig,
+    /// Accept the remote TLS certificate without verifying it against a
+    /// trusted root. Defaults to `false`; only meant for the fake-SNI case
+    /// where there's no real certificate to verify in the first place. Set
+    /// to `true` only when `sni` isn't a certificate the peer can present.
+    pub allow_insecure_tls: bool,
+    /// Share many logical SOCKS5 streams over one underlying TLS
+    /// connection (see `V2RayTunnel`), the way wstunnel pools connections
+    /// to avoid paying a fresh TCP+TLS handshake per browser request.
+    pub mux: MuxConfig,
+}
+
+/// Connection multiplexing for `V2RayTunnel`'s outbound. Disabled by
+/// default to preserve the existing single-stream-per-connection
+/// behavior; enabling it cuts latency for workloads (like a browser) that
+/// open many short-lived connections at once.
+#[derive(Debug, Clone, Default)]
+pub struct MuxConfig {
+    pub enabled: bool,
+    /// Max number of logical streams multiplexed onto one connection.
+    pub concurrency: u32,
+}
+
+/// Upstream DNS servers a tunnel should use for its own name resolution
+/// (e.g. resolving the share-link's SNI or a domain fronted behind a CDN),
+/// mirroring reqwest's resolver-override model rather than trusting the
+/// host's system resolver, which would otherwise leak queries outside the
+/// tunnel.
+#[derive(Debug, Clone, Default)]
+pub struct DnsConfig {
+    /// Upstream resolvers, e.g. `1.1.1.1` or a DoH URL like
+    /// `https://dns.google/dns-query`. Empty means the underlying client's
+    /// own default (no override emitted).
+    pub servers: Vec<String>,
+    /// Route DNS lookups themselves through the tunnel's proxy outbound
+    /// instead of resolving directly, closing the leak where a DPI
+    /// observer can fingerprint the connection by its cleartext DNS query.
+    pub route_through_tunnel: bool,
+}
+
+/// The inbound disguise V2Ray/Hysteria2 uses for its outbound stream —
+/// the same websocket-proxy/gRPC masquerading wstunnel and vpncloud use
+/// to ride behind a CDN and look like ordinary HTTPS.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum StreamTransport {
+    /// Raw TCP, no disguise beyond the protocol's own TLS.
+    #[default]
+    Tcp,
+    /// WebSocket-over-TLS: `path` and `Host` header let it hide behind a
+    /// CDN that proxies ordinary `wss://` traffic.
+    Ws { path: String, host: String },
+    /// gRPC-over-TLS (HTTP/2), identified by `service_name`.
+    Grpc { service_name: String },
+    /// HTTP/1.1 Upgrade handshake, a lighter-weight alternative to
+    /// WebSocket some CDNs proxy more readily.
+    HttpUpgrade,
+}
+
+/// Governs how a tunnel's reconnection supervisor retries after a detected
+/// network change: how many attempts before giving up, and the exponential
+/// backoff applied between them (capped at `backoff_ceiling`).
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub backoff_ceiling: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(1),
+            backoff_ceiling: Duration::from_secs(30),
+        }
+    }
 }
 
 /// Multi-protocol authentication types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Credentials {
     KeyPair {
         private_key: Vec<u8>,
@@ -0,0 +1,146 @@
+//! Genuine TLS record-layer transport for tunnels that need to pass as an
+//! ordinary HTTPS flow to a stateful DPI engine.
+//!
+//! Unlike `obfuscation::apply_tls_wrapping` (a cheap 5-byte fake record
+//! header with no handshake), this module drives a real `rustls`
+//! ClientHello→ServerHello→Finished handshake over the wire and tunnels
+//! payloads as encrypted Application Data records. The server side loads an
+//! embedded certificate/key pair (as wstunnel does with its
+//! `TLS_CERTIFICATE`/`TLS_PRIVATE_KEY` statics) so no external PKI is
+//! required to stand up a plausible-looking endpoint.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
+
+use crate::error::{Result, VpnError};
+
+/// Self-signed cert/key pair embedded at build time so the server side can
+/// terminate TLS without depending on a real CA. Only used to dress up the
+/// wire format as HTTPS, not as an authentication mechanism.
+const EMBEDDED_TLS_CERTIFICATE: &str = include_str!("embedded_tls_cert.pem");
+const EMBEDDED_TLS_PRIVATE_KEY: &str = include_str!("embedded_tls_key.pem");
+
+/// SNI and ALPN settings used to make the handshake resemble a plausible
+/// target host instead of a trivially-fingerprintable VPN endpoint.
+#[derive(Debug, Clone)]
+pub struct TlsObfuscationConfig {
+    /// Hostname sent in the ClientHello's SNI extension
+    pub server_name: String,
+    /// ALPN protocols offered by the client, e.g. `h2`, `http/1.1`
+    pub alpn: Vec<String>,
+}
+
+impl Default for TlsObfuscationConfig {
+    fn default() -> Self {
+        Self {
+            server_name: "www.google.com".to_string(),
+            alpn: vec!["h2".to_string(), "http/1.1".to_string()],
+        }
+    }
+}
+
+fn embedded_cert_chain() -> Result<Vec<CertificateDer<'static>>> {
+    rustls_pemfile::certs(&mut Cursor::new(EMBEDDED_TLS_CERTIFICATE))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| VpnError::Internal(format!("Invalid embedded TLS certificate: {}", e)))
+}
+
+fn embedded_private_key() -> Result<PrivateKeyDer<'static>> {
+    rustls_pemfile::pkcs8_private_keys(&mut Cursor::new(EMBEDDED_TLS_PRIVATE_KEY))
+        .next()
+        .ok_or_else(|| VpnError::Internal("No private key found in embedded PEM".into()))?
+        .map(PrivateKeyDer::Pkcs8)
+        .map_err(|e| VpnError::Internal(format!("Invalid embedded TLS private key: {}", e)))
+}
+
+/// Builds the server-side TLS config from the embedded cert/key pair.
+fn server_tls_config() -> Result<Arc<ServerConfig>> {
+    let chain = embedded_cert_chain()?;
+    let key = embedded_private_key()?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .map_err(|e| VpnError::Internal(format!("Failed to build TLS server config: {}", e)))?;
+
+    Ok(Arc::new(config))
+}
+
+/// Builds the client-side TLS config, trusting only the embedded certificate
+/// (this is an obfuscation layer, not a real PKI relationship) and offering
+/// the configured ALPN protocols.
+fn client_tls_config(config: &TlsObfuscationConfig) -> Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    for cert in embedded_cert_chain()? {
+        roots
+            .add(cert)
+            .map_err(|e| VpnError::Internal(format!("Failed to trust embedded TLS certificate: {}", e)))?;
+    }
+
+    let mut client_config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    client_config.alpn_protocols = config.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+
+    Ok(Arc::new(client_config))
+}
+
+/// Drives the client side of the handshake over an already-connected TCP
+/// stream, presenting `config.server_name` as the SNI.
+pub async fn wrap_client(stream: TcpStream, config: &TlsObfuscationConfig) -> Result<TlsStream<TcpStream>> {
+    let connector = TlsConnector::from(client_tls_config(config)?);
+
+    let server_name = ServerName::try_from(config.server_name.clone())
+        .map_err(|e| VpnError::ConnectionFailed(format!("Invalid SNI hostname: {}", e)))?;
+
+    let stream = connector
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| VpnError::ConnectionFailed(format!("TLS handshake failed: {}", e)))?;
+
+    Ok(TlsStream::Client(stream))
+}
+
+/// Drives the server side of the handshake, terminating TLS with the
+/// embedded certificate/key pair.
+pub async fn wrap_server(stream: TcpStream) -> Result<TlsStream<TcpStream>> {
+    let acceptor = TlsAcceptor::from(server_tls_config()?);
+
+    let stream = acceptor
+        .accept(stream)
+        .await
+        .map_err(|e| VpnError::ConnectionFailed(format!("TLS handshake failed: {}", e)))?;
+
+    Ok(TlsStream::Server(stream))
+}
+
+/// Tunnels raw tunnel payloads as encrypted Application Data records over an
+/// established TLS session.
+pub struct TlsTransport {
+    stream: TlsStream<TcpStream>,
+}
+
+impl TlsTransport {
+    pub fn new(stream: TlsStream<TcpStream>) -> Self {
+        Self { stream }
+    }
+
+    pub async fn send(&mut self, data: &[u8]) -> Result<usize> {
+        self.stream
+            .write_all(data)
+            .await
+            .map_err(VpnError::NetworkError)?;
+        Ok(data.len())
+    }
+
+    pub async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.stream.read(buf).await.map_err(VpnError::NetworkError)
+    }
+}
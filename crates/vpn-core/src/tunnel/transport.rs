@@ -0,0 +1,91 @@
+//! Pluggable carrier for `WireGuardTunnel`'s encapsulated frames.
+//!
+//! `boringtun`'s encap/decap logic only produces and consumes opaque byte
+//! frames — it has no opinion on how they reach the peer. `Transport`
+//! abstracts that wire, so the same `WireGuardTunnel` can ride either a raw
+//! UDP socket (the protocol's native carrier) or a `wss://` connection (see
+//! `WsTlsTransport`) for networks that block UDP outright but allow 443/TLS.
+
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+
+use crate::error::{Result, VpnError};
+use crate::tunnel::ws_transport::{WssTransport, WssTransportConfig};
+
+/// Largest frame `Transport` implementations need to carry — WireGuard
+/// packets never exceed the path MTU plus its own small header.
+const MAX_FRAME_SIZE: usize = 65535;
+
+/// Carries WireGuard's already-encapsulated frames to/from the peer.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends one already-encapsulated frame.
+    async fn send_frame(&mut self, data: &[u8]) -> Result<()>;
+
+    /// Receives the next frame from the peer.
+    async fn recv_frame(&mut self) -> Result<Vec<u8>>;
+}
+
+/// WireGuard's native carrier: one UDP datagram per frame.
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+impl UdpTransport {
+    /// Binds an ephemeral local socket and connects it to `server_addr`, so
+    /// `send`/`recv` can use the connected-UDP convenience methods instead
+    /// of tracking the peer address on every call.
+    pub async fn connect(server_addr: SocketAddr) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(VpnError::NetworkError)?;
+        socket.connect(server_addr).await.map_err(VpnError::NetworkError)?;
+        Ok(Self { socket })
+    }
+}
+
+#[async_trait]
+impl Transport for UdpTransport {
+    async fn send_frame(&mut self, data: &[u8]) -> Result<()> {
+        self.socket.send(data).await.map_err(VpnError::NetworkError)?;
+        Ok(())
+    }
+
+    async fn recv_frame(&mut self) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; MAX_FRAME_SIZE];
+        let n = self.socket.recv(&mut buf).await.map_err(VpnError::NetworkError)?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+/// Carries WireGuard's frames inside a `wss://` connection instead of raw
+/// UDP, so the tunnel still works on networks whose firewall permits
+/// nothing but outbound 443/TLS. Each frame maps to exactly one binary
+/// WebSocket message — the WS framing itself delimits frame boundaries, so
+/// no additional length prefix is needed on top of it.
+pub struct WsTlsTransport {
+    inner: WssTransport,
+}
+
+impl WsTlsTransport {
+    pub async fn connect(config: WssTransportConfig) -> Result<Self> {
+        let mut inner = WssTransport::new(config);
+        inner.connect().await?;
+        Ok(Self { inner })
+    }
+}
+
+#[async_trait]
+impl Transport for WsTlsTransport {
+    async fn send_frame(&mut self, data: &[u8]) -> Result<()> {
+        self.inner.send(data).await?;
+        Ok(())
+    }
+
+    async fn recv_frame(&mut self) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; MAX_FRAME_SIZE];
+        let n = self.inner.recv(&mut buf).await?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
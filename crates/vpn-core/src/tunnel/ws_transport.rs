@@ -0,0 +1,357 @@
+//! WebSocket-over-TLS transport for tunnels that need to pass as ordinary
+//! HTTPS traffic through firewalls that only permit outbound 443.
+//!
+//! Borrows wstunnel's connection-pooling idea: a pool of pre-established,
+//! idle WSS connections is kept per endpoint so a SOCKS-style client opening
+//! many short-lived inner flows reuses a warm connection instead of paying a
+//! fresh TCP+TLS handshake for each one.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use rustls::{ClientConfig, RootCertStore};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::handshake::server::{Request as ServerRequest, Response as ServerResponse};
+use tokio_tungstenite::tungstenite::http::{Request, Response, StatusCode};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{
+    client_async, client_async_tls_with_config, connect_async, connect_async_tls_with_config, accept_hdr_async,
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+use tracing::{info, warn};
+
+use crate::error::{Result, VpnError};
+use crate::proxy_config::ProxyConfig;
+
+/// A client-side WSS socket, possibly routed through a TLS layer depending on scheme.
+pub type PooledSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Parameters for dialing the WSS endpoint that fronts a tunnel server.
+#[derive(Debug, Clone)]
+pub struct WssTransportConfig {
+    /// Full `wss://host:port/path` of the upgrade endpoint
+    pub url: String,
+    /// Bearer token the server validates before completing the upgrade
+    pub auth_token: String,
+    /// Maximum number of idle connections kept warm per endpoint
+    pub max_pool_size: usize,
+    /// PEM-encoded CA certificate to pin the TLS handshake to, instead of
+    /// trusting the ambient root store. `None` falls back to the platform
+    /// default roots (ordinary HTTPS trust).
+    pub pinned_ca: Option<Vec<u8>>,
+    /// Routes the initial TCP dial to the WSS endpoint through an outbound
+    /// forward proxy instead of connecting directly — lets this transport's
+    /// control/handshake bootstrap reach the server from networks where
+    /// only an authenticated HTTP CONNECT or SOCKS5 proxy has egress.
+    pub proxy: Option<ProxyConfig>,
+}
+
+impl Default for WssTransportConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            auth_token: String::new(),
+            max_pool_size: 4,
+            pinned_ca: None,
+            proxy: None,
+        }
+    }
+}
+
+/// Pool of idle, pre-established WSS connections keyed by endpoint URL.
+#[derive(Clone)]
+pub struct WssConnectionPool {
+    idle: Arc<Mutex<HashMap<String, Vec<PooledSocket>>>>,
+    max_pool_size: usize,
+}
+
+impl WssConnectionPool {
+    pub fn new(max_pool_size: usize) -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(HashMap::new())),
+            max_pool_size: max_pool_size.max(1),
+        }
+    }
+
+    /// Hands back a warm socket if one is idle for this endpoint, otherwise dials a fresh one.
+    pub async fn acquire(&self, config: &WssTransportConfig) -> Result<PooledSocket> {
+        {
+            let mut idle = self.idle.lock().await;
+            if let Some(sockets) = idle.get_mut(&config.url) {
+                if let Some(socket) = sockets.pop() {
+                    return Ok(socket);
+                }
+            }
+        }
+        dial(config).await
+    }
+
+    /// Returns a socket to the idle pool for reuse, up to `max_pool_size` per endpoint.
+    pub async fn release(&self, url: &str, socket: PooledSocket) {
+        let mut idle = self.idle.lock().await;
+        let sockets = idle.entry(url.to_string()).or_default();
+        if sockets.len() < self.max_pool_size {
+            sockets.push(socket);
+        }
+    }
+
+    /// Eagerly dials up to `count` connections so the first inner flows don't
+    /// pay handshake latency.
+    pub async fn warm(&self, config: &WssTransportConfig, count: usize) -> Result<()> {
+        for _ in 0..count.min(self.max_pool_size) {
+            let socket = dial(config).await?;
+            self.release(&config.url, socket).await;
+        }
+        info!("Warmed {} idle WSS connections to {}", count.min(self.max_pool_size), config.url);
+        Ok(())
+    }
+}
+
+async fn dial(config: &WssTransportConfig) -> Result<PooledSocket> {
+    let host = host_from_url(&config.url);
+
+    let request = Request::builder()
+        .uri(&config.url)
+        .header("Host", host)
+        .header("Authorization", format!("Bearer {}", config.auth_token))
+        .header("Sec-WebSocket-Key", generate_key())
+        .header("Sec-WebSocket-Version", "13")
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .body(())
+        .map_err(|e| VpnError::ConnectionFailed(format!("Invalid WSS handshake request: {}", e)))?;
+
+    let (socket, response) = match &config.proxy {
+        Some(proxy) => {
+            let stream = dial_tcp_via_proxy(&host, proxy).await?;
+            let connector = match &config.pinned_ca {
+                Some(ca_pem) => Some(Connector::Rustls(Arc::new(pinned_tls_config(ca_pem)?))),
+                None => None,
+            };
+
+            if config.url.starts_with("wss://") {
+                client_async_tls_with_config(request, stream, None, connector)
+                    .await
+                    .map_err(|e| VpnError::ConnectionFailed(format!("WSS handshake through proxy failed: {}", e)))?
+            } else {
+                client_async(request, MaybeTlsStream::Plain(stream))
+                    .await
+                    .map_err(|e| VpnError::ConnectionFailed(format!("WS handshake through proxy failed: {}", e)))?
+            }
+        }
+        None => match &config.pinned_ca {
+            Some(ca_pem) => {
+                let connector = Connector::Rustls(Arc::new(pinned_tls_config(ca_pem)?));
+                connect_async_tls_with_config(request, None, false, Some(connector))
+                    .await
+                    .map_err(|e| VpnError::ConnectionFailed(format!("WSS handshake failed: {}", e)))?
+            }
+            None => connect_async(request)
+                .await
+                .map_err(|e| VpnError::ConnectionFailed(format!("WSS handshake failed: {}", e)))?,
+        },
+    };
+
+    info!("WSS transport connected to {} ({})", config.url, response.status());
+    Ok(socket)
+}
+
+/// Builds a TLS client config that trusts only `ca_pem`, so a pinned WSS
+/// handshake can't be quietly intercepted by a root the OS trusts but the
+/// server operator never issued from (e.g. a corporate MITM proxy).
+fn pinned_tls_config(ca_pem: &[u8]) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut Cursor::new(ca_pem)) {
+        let cert = cert.map_err(|e| VpnError::InvalidConfig(format!("Invalid pinned CA certificate: {}", e)))?;
+        roots
+            .add(cert)
+            .map_err(|e| VpnError::InvalidConfig(format!("Failed to trust pinned CA: {}", e)))?;
+    }
+
+    Ok(ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+/// Resolves `host_port` ("host:port", as returned by `host_from_url`) and
+/// dials it through `proxy` instead of connecting directly.
+async fn dial_tcp_via_proxy(host_port: &str, proxy: &ProxyConfig) -> Result<TcpStream> {
+    let target = tokio::net::lookup_host(host_port)
+        .await
+        .map_err(VpnError::NetworkError)?
+        .next()
+        .ok_or_else(|| VpnError::ConnectionFailed(format!("Could not resolve WSS endpoint {}", host_port)))?;
+
+    proxy.dial_tcp(target).await
+}
+
+fn host_from_url(url: &str) -> String {
+    url.split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Client-facing transport that frames outgoing packets as binary WebSocket
+/// messages, backed by a `WssConnectionPool` so each inner flow reuses a warm
+/// connection.
+pub struct WssTransport {
+    config: WssTransportConfig,
+    pool: WssConnectionPool,
+    socket: Option<PooledSocket>,
+}
+
+impl WssTransport {
+    pub fn new(config: WssTransportConfig) -> Self {
+        let pool = WssConnectionPool::new(config.max_pool_size);
+        Self {
+            config,
+            pool,
+            socket: None,
+        }
+    }
+
+    /// Shares an externally-managed pool instead of creating a dedicated one,
+    /// so many short-lived inner flows can draw from the same warm set.
+    pub fn with_pool(config: WssTransportConfig, pool: WssConnectionPool) -> Self {
+        Self {
+            config,
+            pool,
+            socket: None,
+        }
+    }
+
+    /// Acquires a warm (or freshly dialed) connection for this inner flow.
+    pub async fn connect(&mut self) -> Result<()> {
+        self.socket = Some(self.pool.acquire(&self.config).await?);
+        Ok(())
+    }
+
+    /// Frames `data` as a single binary WebSocket message.
+    pub async fn send(&mut self, data: &[u8]) -> Result<usize> {
+        let socket = self
+            .socket
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("WSS transport not connected".into()))?;
+
+        socket
+            .send(Message::Binary(data.to_vec()))
+            .await
+            .map_err(|e| VpnError::NetworkError(std::io::Error::other(e.to_string())))?;
+
+        Ok(data.len())
+    }
+
+    /// Reads the next binary frame into `buf`, returning its length. Control
+    /// frames are consumed transparently. Errors rather than truncating if
+    /// the frame doesn't fit `buf` — a caller that silently got back fewer
+    /// bytes than were sent would hand the tunnel a corrupt, not just
+    /// short, packet.
+    pub async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let socket = self
+            .socket
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("WSS transport not connected".into()))?;
+
+        loop {
+            return match socket.next().await {
+                Some(Ok(Message::Binary(data))) => {
+                    if data.len() > buf.len() {
+                        Err(VpnError::NetworkError(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!(
+                                "received {}-byte WS frame exceeds {}-byte caller buffer",
+                                data.len(),
+                                buf.len()
+                            ),
+                        )))
+                    } else {
+                        buf[..data.len()].copy_from_slice(&data);
+                        Ok(data.len())
+                    }
+                }
+                Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) | Some(Ok(Message::Text(_))) => {
+                    continue;
+                }
+                Some(Ok(Message::Close(_))) | None => Ok(0),
+                Some(Ok(Message::Frame(_))) => continue,
+                Some(Err(e)) => Err(VpnError::NetworkError(std::io::Error::other(e.to_string()))),
+            };
+        }
+    }
+
+    /// Returns the connection to the pool for reuse instead of closing it.
+    pub async fn release(&mut self) {
+        if let Some(socket) = self.socket.take() {
+            self.pool.release(&self.config.url, socket).await;
+        }
+    }
+}
+
+/// Server-side acceptor: upgrades an incoming TCP connection on a configured
+/// path, validating the bearer token before splicing binary frames to/from
+/// the inner tunnel.
+pub struct WssTransportServer {
+    listener: TcpListener,
+    expected_token: String,
+    path: String,
+}
+
+impl WssTransportServer {
+    pub async fn bind(addr: &str, path: impl Into<String>, expected_token: impl Into<String>) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await.map_err(VpnError::NetworkError)?;
+        Ok(Self {
+            listener,
+            expected_token: expected_token.into(),
+            path: path.into(),
+        })
+    }
+
+    /// Accepts the next connection, rejecting it before the handshake
+    /// completes if the path or auth token don't match.
+    pub async fn accept(&self) -> Result<WebSocketStream<TcpStream>> {
+        let (stream, peer) = self.listener.accept().await.map_err(VpnError::NetworkError)?;
+
+        let expected_token = self.expected_token.clone();
+        let expected_path = self.path.clone();
+
+        let callback = move |req: &ServerRequest, response: ServerResponse| {
+            if req.uri().path() != expected_path {
+                return Err(reject(StatusCode::NOT_FOUND));
+            }
+
+            let authorized = req
+                .headers()
+                .get("Authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v == format!("Bearer {}", expected_token))
+                .unwrap_or(false);
+
+            if !authorized {
+                return Err(reject(StatusCode::UNAUTHORIZED));
+            }
+
+            Ok(response)
+        };
+
+        let socket = accept_hdr_async(stream, callback).await.map_err(|e| {
+            warn!("WSS upgrade rejected from {}: {}", peer, e);
+            VpnError::ConnectionFailed(format!("WSS upgrade rejected: {}", e))
+        })?;
+
+        info!("WSS transport peer accepted from {}", peer);
+        Ok(socket)
+    }
+}
+
+fn reject(status: StatusCode) -> Response<Option<String>> {
+    Response::builder()
+        .status(status)
+        .body(None)
+        .expect("static rejection response is always valid")
+}
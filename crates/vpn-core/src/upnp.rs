@@ -0,0 +1,110 @@
+//! UPnP/IGD port-forwarding for P2P nodes behind NAT.
+//!
+//! Borrows VpnCloud's use of the `igd` crate for NAT traversal: discover the
+//! local gateway, request a port mapping for the node's WireGuard/OpenVPN
+//! listen port, and keep renewing the lease so the node stays directly
+//! dialable for as long as it's sharing bandwidth. Used when a node
+//! registers/goes online; if the mapping can't be established, the node
+//! should report no external endpoint and callers fall back to routing
+//! connections through the central relay instead of a dead direct address.
+
+use std::net::{IpAddr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+
+use igd::aio::search_gateway;
+use igd::{PortMappingProtocol, SearchOptions};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::error::{Result, VpnError};
+
+/// How long a requested port mapping is leased for before it must be
+/// renewed. Chosen well under most routers' own UPnP lease limits.
+const LEASE_DURATION_SECS: u32 = 600;
+
+/// Renew the lease at roughly 2/3 of `LEASE_DURATION_SECS`, so a missed
+/// renewal attempt still has a retry window before the router expires it.
+const RENEWAL_INTERVAL: Duration = Duration::from_secs(LEASE_DURATION_SECS as u64 * 2 / 3);
+
+/// A port mapping the node can advertise as its directly dialable endpoint.
+#[derive(Debug, Clone, Copy)]
+pub struct MappedEndpoint {
+    pub external_ip: IpAddr,
+    pub external_port: u16,
+}
+
+impl MappedEndpoint {
+    pub fn as_socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.external_ip, self.external_port)
+    }
+}
+
+/// Discovers the local IGD and keeps a single port mapping alive for the
+/// lifetime of a sharing node.
+pub struct PortMapper {
+    local_addr: SocketAddrV4,
+    protocol: PortMappingProtocol,
+    description: String,
+}
+
+impl PortMapper {
+    pub fn new(
+        local_addr: SocketAddrV4,
+        protocol: PortMappingProtocol,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            local_addr,
+            protocol,
+            description: description.into(),
+        }
+    }
+
+    /// Discovers the gateway and requests the initial mapping. Returns the
+    /// externally reachable `ip:port` on success; callers should treat any
+    /// error here as "mapping failed" and fall back to the central relay
+    /// instead of advertising a direct endpoint nobody can reach.
+    pub async fn map(&self) -> Result<MappedEndpoint> {
+        let gateway = search_gateway(SearchOptions::default())
+            .await
+            .map_err(|e| VpnError::NatTraversalFailed(format!("Découverte IGD échouée: {}", e)))?;
+
+        gateway
+            .add_port(
+                self.protocol,
+                self.local_addr.port(),
+                self.local_addr,
+                LEASE_DURATION_SECS,
+                &self.description,
+            )
+            .await
+            .map_err(|e| VpnError::NatTraversalFailed(format!("Mapping UPnP échoué: {}", e)))?;
+
+        let external_ip = gateway
+            .get_external_ip()
+            .await
+            .map_err(|e| VpnError::NatTraversalFailed(format!("IP externe introuvable: {}", e)))?;
+
+        Ok(MappedEndpoint {
+            external_ip: IpAddr::V4(external_ip),
+            external_port: self.local_addr.port(),
+        })
+    }
+
+    /// Spawns a background task that re-requests the mapping every
+    /// `RENEWAL_INTERVAL`, keeping the lease alive for as long as the node
+    /// is sharing. Renewal failures are logged and retried on the next
+    /// tick rather than tearing the node down — a transient router hiccup
+    /// shouldn't kick a node offline.
+    pub fn spawn_renewal(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(RENEWAL_INTERVAL).await;
+                if let Err(e) = self.map().await {
+                    warn!("Échec renouvellement mapping UPnP: {}", e);
+                }
+            }
+        })
+    }
+}
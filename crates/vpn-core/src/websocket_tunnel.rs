@@ -0,0 +1,138 @@
+//! `VpnTunnel` implementation that rides inside a WSS (WebSocket-over-TLS)
+//! connection on port 443 — a last-resort transport for networks (or
+//! captive portals) whose firewall permits nothing but outbound HTTPS.
+//!
+//! Wraps the raw `tunnel::ws_transport::WssTransport` the same way
+//! `ShadowsocksTunnel` wraps its pooled `sslocal` process: this module only
+//! adapts it to the `VpnTunnel` trait so it can be selected, connected, and
+//! measured like any other protocol.
+
+use async_trait::async_trait;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Instant;
+use tracing::info;
+
+use crate::{
+    error::{Result, VpnError},
+    protocol::VpnProtocol,
+    tunnel::ws_transport::{WssTransport, WssTransportConfig},
+    tunnel::{ConnectionConfig, Credentials, TunnelHandle, TunnelStats, VpnTunnel},
+};
+
+/// Tunnel backed by `WssTransport`: the encapsulated VPN payload is framed
+/// as binary WebSocket messages over a TLS connection to
+/// `config.server_addr`. Reuses `Credentials::Certificate`'s `ca` field to
+/// pin that TLS handshake to a known server certificate rather than
+/// trusting the ambient root store, the way a generic HTTPS client would.
+pub struct WebSocketTunnel {
+    transport: Option<WssTransport>,
+    bytes_sent: u64,
+    bytes_received: u64,
+    start_time: Option<Instant>,
+    assigned_ip: IpAddr,
+}
+
+impl WebSocketTunnel {
+    pub fn new() -> Self {
+        Self {
+            transport: None,
+            bytes_sent: 0,
+            bytes_received: 0,
+            start_time: None,
+            assigned_ip: IpAddr::V4(Ipv4Addr::new(10, 9, 0, 2)),
+        }
+    }
+}
+
+impl Default for WebSocketTunnel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl VpnTunnel for WebSocketTunnel {
+    async fn connect(&mut self, config: &ConnectionConfig) -> Result<TunnelHandle> {
+        let ca = match &config.credentials {
+            Credentials::Certificate { ca, .. } => ca.clone(),
+            _ => {
+                return Err(VpnError::InvalidConfig(
+                    "WebSocketTunnel requires Certificate credentials for TLS pinning".into(),
+                ))
+            }
+        };
+
+        info!("Connecting WebSocket (WSS) tunnel to {}", config.server_addr);
+
+        let wss_config = WssTransportConfig {
+            url: format!("wss://{}/tunnel", config.server_addr),
+            max_pool_size: 1,
+            pinned_ca: Some(ca),
+            ..Default::default()
+        };
+
+        let mut transport = WssTransport::new(wss_config);
+        transport.connect().await?;
+
+        self.transport = Some(transport);
+        self.start_time = Some(Instant::now());
+
+        info!("WebSocket tunnel established to {}", config.server_addr);
+
+        Ok(TunnelHandle {
+            id: uuid::Uuid::new_v4().to_string(),
+            protocol: VpnProtocol::WebSocketTls,
+            assigned_ip: self.assigned_ip,
+            remote_endpoint: config.server_addr,
+        })
+    }
+
+    async fn send(&mut self, data: &[u8]) -> Result<usize> {
+        let transport = self
+            .transport
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("WebSocket tunnel not connected".into()))?;
+
+        let n = transport.send(data).await?;
+        self.bytes_sent += n as u64;
+        Ok(n)
+    }
+
+    async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let transport = self
+            .transport
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("WebSocket tunnel not connected".into()))?;
+
+        let n = transport.recv(buf).await?;
+        self.bytes_received += n as u64;
+        Ok(n)
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        info!("Disconnecting WebSocket tunnel");
+        if let Some(mut transport) = self.transport.take() {
+            transport.release().await;
+        }
+        self.start_time = None;
+        Ok(())
+    }
+
+    fn stats(&self) -> TunnelStats {
+        TunnelStats {
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            // WS framing plus the TLS record layer cost more round-trip
+            // overhead than a raw transport; reflects this being a
+            // last-resort path, not a daily driver.
+            avg_latency_ms: 180,
+            packet_loss: 0.0,
+            uptime: self.start_time.map(|t| t.elapsed()).unwrap_or_default(),
+            current_throughput_mbps: 0.0,
+        }
+    }
+
+    fn protocol(&self) -> VpnProtocol {
+        VpnProtocol::WebSocketTls
+    }
+}
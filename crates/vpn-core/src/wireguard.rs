@@ -1,8 +1,13 @@
 //! Implémentation WireGuard utilisant boringtun
 
 use crate::error::{Result, VpnError};
+use crate::hooks::{HookConfig, HookContext, HookEvent};
 use crate::protocol::VpnProtocol;
-use crate::tunnel::{ConnectionConfig, Credentials, Interface, TunnelHandle, TunnelStats, VpnTunnel};
+use crate::tunnel::ws_transport::WssTransportConfig;
+use crate::tunnel::{
+    ConnectionConfig, Credentials, Interface, StreamTransport, Transport, TunnelHandle, TunnelStats, UdpTransport,
+    VpnTunnel, WsTlsTransport,
+};
 use async_trait::async_trait;
 use boringtun::noise::{Tunn, TunnResult};
 use std::net::{IpAddr, SocketAddr};
@@ -18,6 +23,17 @@ pub struct WireGuardTunnel {
     stats: Arc<Mutex<TunnelStats>>,
     /// Handle boringtun
     tunnel: Option<Box<Tunn>>,
+    /// Carrier for the encapsulated frames `tunnel` produces/consumes —
+    /// raw UDP by default, or a `WsTlsTransport` when `ConnectionConfig`
+    /// asks to ride inside a `wss://` connection to defeat UDP-blocking
+    /// firewalls/DPI (see `StreamTransport::Ws`).
+    transport: Option<Box<dyn Transport>>,
+    /// ifup/ifdown-style lifecycle scripts, captured from `ConnectionConfig`
+    /// at `connect()` time so `disconnect()` can run `on_disconnect`
+    /// without needing the config again. No built-in kill-switch here:
+    /// boringtun runs entirely in-process with no real OS tun device or
+    /// egress to fail closed around.
+    hooks: HookConfig,
 }
 
 struct TunnelState {
@@ -44,6 +60,34 @@ impl WireGuardTunnel {
                 current_throughput_mbps: 0.0,
             })),
             tunnel: None,
+            transport: None,
+            hooks: HookConfig::default(),
+        }
+    }
+
+    /// Picks the frame carrier `connect` should dial, based on
+    /// `config.stream_transport`: `StreamTransport::Ws` rides a `wss://`
+    /// connection to `config.server_addr`'s `/vpn/ws` upgrade endpoint
+    /// instead of raw UDP.
+    async fn dial_transport(config: &ConnectionConfig) -> Result<Box<dyn Transport>> {
+        match &config.stream_transport {
+            StreamTransport::Ws { path, host } => {
+                let host = if host.is_empty() {
+                    config.server_addr.ip().to_string()
+                } else {
+                    host.clone()
+                };
+                let path = if path.is_empty() { "/vpn/ws" } else { path };
+                let url = format!("wss://{}:{}{}", host, config.server_addr.port(), path);
+
+                let transport = WsTlsTransport::connect(WssTransportConfig {
+                    url,
+                    ..Default::default()
+                })
+                .await?;
+                Ok(Box::new(transport))
+            }
+            _ => Ok(Box::new(UdpTransport::connect(config.server_addr).await?)),
         }
     }
 }
@@ -53,6 +97,13 @@ impl VpnTunnel for WireGuardTunnel {
     async fn connect(&mut self, config: &ConnectionConfig) -> Result<TunnelHandle> {
         tracing::info!("Connexion WireGuard vers {:?}", config.server_addr);
 
+        self.hooks = config.hooks.clone();
+        self.hooks.run(HookEvent::Connecting, &HookContext {
+            protocol: Some("WireGuard".to_string()),
+            server_addr: Some(config.server_addr.to_string()),
+            ..Default::default()
+        }).await?;
+
         // Extraction des clés
         let (private_key, peer_public_key) = match &config.credentials {
             Credentials::KeyPair {
@@ -88,6 +139,7 @@ impl VpnTunnel for WireGuardTunnel {
         );
 
         self.tunnel = Some(Box::new(tunnel));
+        self.transport = Some(Self::dial_transport(config).await?);
 
         // Mise à jour état
         {
@@ -99,6 +151,13 @@ impl VpnTunnel for WireGuardTunnel {
 
         tracing::info!("Tunnel WireGuard établi ! IP assignée: {}", local_ip);
 
+        self.hooks.run(HookEvent::Connected, &HookContext {
+            protocol: Some("WireGuard".to_string()),
+            server_addr: Some(config.server_addr.to_string()),
+            assigned_ip: Some(local_ip.to_string()),
+            ..Default::default()
+        }).await?;
+
         Ok(TunnelHandle {
             id: "wg-0".to_string(),
             protocol: VpnProtocol::WireGuard,
@@ -108,37 +167,87 @@ impl VpnTunnel for WireGuardTunnel {
     }
 
     async fn send(&mut self, data: &[u8]) -> Result<usize> {
-        if let Some(tunnel) = &mut self.tunnel {
-            // Encapsulation boringtun
-            let mut buf = vec![0u8; data.len() + 100]; // Buffer avec overhead
-            match tunnel.encapsulate(data, &mut buf) {
-                TunnResult::WriteToNetwork(packet) => {
-                    // Ici on enverrait 'packet' sur l'interface UDP
-                    // Pour le prototype, on simule l'envoi
-                    let len = packet.len();
-                    let mut stats = self.stats.lock().await;
-                    stats.bytes_sent += len as u64;
-                    Ok(len)
-                },
-                _ => Err(VpnError::Internal("Erreur encapsulation WireGuard".into())),
+        let tunnel = self
+            .tunnel
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("Tunnel non initialisé".into()))?;
+
+        // Encapsulation boringtun
+        let mut encap_buf = vec![0u8; data.len() + 100]; // Buffer avec overhead
+        match tunnel.encapsulate(data, &mut encap_buf) {
+            TunnResult::WriteToNetwork(packet) => {
+                let transport = self
+                    .transport
+                    .as_mut()
+                    .ok_or_else(|| VpnError::ConnectionFailed("Transport non initialisé".into()))?;
+                transport.send_frame(packet).await?;
+
+                let len = packet.len();
+                let mut stats = self.stats.lock().await;
+                stats.bytes_sent += len as u64;
+                Ok(len)
             }
-        } else {
-            Err(VpnError::ConnectionFailed("Tunnel non initialisé".into()))
+            _ => Err(VpnError::Internal("Erreur encapsulation WireGuard".into())),
         }
     }
 
     async fn recv(&mut self, buf: &mut [u8]) -> Result<usize> {
-        // Simulation réception
-        // Dans la réalité: lire socket UDP -> tunnel.decapsulate -> écrire dans buf
-        tokio::time::sleep(Duration::from_millis(10)).await;
-        Ok(0)
+        let transport = self
+            .transport
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("Transport non initialisé".into()))?;
+        let frame = transport.recv_frame().await?;
+
+        let tunnel = self
+            .tunnel
+            .as_mut()
+            .ok_or_else(|| VpnError::ConnectionFailed("Tunnel non initialisé".into()))?;
+
+        let mut decap_buf = vec![0u8; frame.len() + 100];
+        match tunnel.decapsulate(None, &frame, &mut decap_buf) {
+            TunnResult::WriteToTunnelV4(packet, _) | TunnResult::WriteToTunnelV6(packet, _) => {
+                if packet.len() > buf.len() {
+                    return Err(VpnError::NetworkError(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "decapsulated {}-byte packet exceeds {}-byte caller buffer",
+                            packet.len(),
+                            buf.len()
+                        ),
+                    )));
+                }
+                buf[..packet.len()].copy_from_slice(packet);
+                let n = packet.len();
+                let mut stats = self.stats.lock().await;
+                stats.bytes_received += n as u64;
+                Ok(n)
+            }
+            // Handshake/keepalive response boringtun wants echoed straight
+            // back, not handed to the caller as decrypted payload.
+            TunnResult::WriteToNetwork(packet) => {
+                self.transport
+                    .as_mut()
+                    .ok_or_else(|| VpnError::ConnectionFailed("Transport non initialisé".into()))?
+                    .send_frame(packet)
+                    .await?;
+                Ok(0)
+            }
+            TunnResult::Done => Ok(0),
+            TunnResult::Err(e) => Err(VpnError::Internal(format!("Erreur décapsulation WireGuard: {:?}", e))),
+        }
     }
 
     async fn disconnect(&mut self) -> Result<()> {
         tracing::info!("Déconnexion WireGuard");
+        self.hooks.run(HookEvent::Disconnecting, &HookContext::default()).await?;
+
         let mut state = self.state.lock().await;
         state.connected = false;
         self.tunnel = None;
+        self.transport = None;
+        drop(state);
+
+        self.hooks.run(HookEvent::Disconnected, &HookContext::default()).await?;
         Ok(())
     }
 
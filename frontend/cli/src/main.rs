@@ -3,12 +3,13 @@
 //! Petit outil pour tester manuellement le core VPN et la sélection de protocole.
 
 use clap::{Parser, Subcommand};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::time::Duration;
 use tracing::{info, warn, error};
 use tracing_subscriber::EnvFilter;
 use vpn_core::{
     crypto::SecretKey,
+    hooks::{HookConfig, HookContext, HookEvent},
     selector::{ProtocolSelector, SelectionContext, NetworkQuality, FirewallProfile, DeviceType, UseCase},
     tunnel::{ConnectionConfig, Credentials},
     wireguard::WireGuardTunnel,
@@ -29,14 +30,19 @@ struct Cli {
 enum Commands {
     /// Teste la sélection intelligente de protocole
     Select {
-        #[arg(long, default_value = "FR")]
-        country: String,
+        #[arg(long)]
+        country: Option<String>,
         #[arg(long)]
         censored: bool,
         #[arg(long)]
         mobile: bool,
         #[arg(long)]
         battery: Option<f32>,
+        /// Profil nommé à charger (`worldvpn-cli config wizard`) pour les
+        /// valeurs par défaut de `--country`/`--censored`/`--mobile`,
+        /// surchargeables en passant le drapeau correspondant.
+        #[arg(long)]
+        profile: Option<String>,
     },
     /// Établit une connexion VPN simulée
     Connect {
@@ -45,17 +51,205 @@ enum Commands {
         #[arg(long, default_value = "127.0.0.1:51820")]
         server: String,
     },
+    /// Gère les profils de connexion persistants (~/.worldvpn/profiles/)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Connexion directe via un lien de partage (vless://, trojan://,
+    /// vmess://, hysteria2:///hy2://) ou une "subscription" base64, sans
+    /// passer par l'API centrale WorldVPN.
+    ImportLink {
+        /// Lien de partage, ou blob base64 "subscription" (plusieurs liens
+        /// newline-delimited). La première entrée reconnue est utilisée.
+        uri: String,
+        /// Port d'écoute du proxy SOCKS5 local.
+        #[arg(long, default_value_t = 1080)]
+        socks_port: u16,
+    },
     /// Connexion via le serveur API
     RemoteConnect {
-        #[arg(long, default_value = "http://127.0.0.1:3000")]
-        api: String,
-        #[arg(long, default_value = "user_cli")]
-        user: String,
-        #[arg(long, default_value = "wireguard")]
-        proto: String,
+        #[arg(long)]
+        api: Option<String>,
+        #[arg(long)]
+        user: Option<String>,
+        #[arg(long)]
+        proto: Option<String>,
+        /// Profil nommé à charger (`worldvpn-cli config wizard`) pour les
+        /// valeurs par défaut de `--api`/`--user`/`--proto`, surchargeables
+        /// en passant le drapeau correspondant.
+        #[arg(long)]
+        profile: Option<String>,
+        /// Script de hook à lancer sur un événement du cycle de vie, au
+        /// format `<event>=<chemin>` (répétable). Événements reconnus :
+        /// on-connect, on-disconnect, on-ip-change, on-error.
+        #[arg(long = "hook", value_name = "EVENT=PATH")]
+        hooks: Vec<String>,
+        /// Transport du flux sortant : `direct` (TCP/UDP brut) ou `wss`
+        /// (WebSocket-over-TLS, pour traverser les pare-feux qui ne
+        /// laissent passer que le 443/HTTPS).
+        #[arg(long, default_value = "direct")]
+        transport: String,
+        /// Chemin HTTP envoyé dans la requête d'upgrade WebSocket (`--transport wss`).
+        #[arg(long, default_value = "/ws")]
+        ws_path: String,
+        /// En-tête `Host` présenté lors de l'upgrade WebSocket (`--transport wss`).
+        /// Par défaut, l'adresse du serveur VPN lui-même.
+        #[arg(long)]
+        ws_host: Option<String>,
+        /// Annonce le support SOCKS5 UDP ASSOCIATE (QUIC, DNS, WebRTC...) en
+        /// plus de CONNECT. Le proxy local le gère déjà dans les deux cas ;
+        /// ce drapeau ne fait qu'afficher l'information pour le test.
+        #[arg(long)]
+        udp: bool,
+        /// Port d'écoute du proxy SOCKS5 local, pour faire tourner plusieurs
+        /// instances en parallèle.
+        #[arg(long, default_value_t = 1080)]
+        socks_port: u16,
+        /// Endpoint candidat supplémentaire (répétable), essayé en
+        /// round-robin avec celui renvoyé par l'API si la connexion échoue.
+        #[arg(long = "server", value_name = "HOST:PORT")]
+        servers: Vec<String>,
+        /// Maintient le tunnel en vie indéfiniment : reconnecte
+        /// automatiquement (backoff exponentiel + jitter, bascule sur
+        /// l'endpoint suivant) au lieu de se déconnecter après la démo.
+        /// Arrêt avec Ctrl+C.
+        #[arg(long)]
+        reconnect: bool,
+        /// Résout l'API et l'endpoint serveur via DNS-over-HTTPS plutôt que
+        /// le résolveur système, pour échapper au blocage/espionnage DNS
+        /// (ex: `--doh https://1.1.1.1/dns-query`).
+        #[arg(long, value_name = "URL")]
+        doh: Option<String>,
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Demande interactivement pays/profil réseau/type d'appareil/protocole
+    /// préféré/URL d'API, puis enregistre le résultat sous un profil nommé.
+    Wizard {
+        /// Nom sous lequel enregistrer le profil (chargé ensuite via `--profile <name>`).
+        #[arg(long, default_value = "default")]
+        name: String,
+    },
+}
+
+/// Parses repeated `--hook <event>=<path>` flags into a `HookConfig`,
+/// layered on top of the `WORLDVPN_HOOK_ON_*` environment defaults so a
+/// flag always takes precedence over the environment.
+fn build_hook_config(flags: &[String]) -> HookConfig {
+    let mut config = HookConfig::from_env();
+    for flag in flags {
+        let Some((event, path)) = flag.split_once('=') else {
+            warn!("Ignoring malformed --hook '{}', expected <event>=<path>", flag);
+            continue;
+        };
+        match HookEvent::parse(event) {
+            Some(event) => config.set(event, path.into()),
+            None => warn!("Ignoring --hook for unknown event '{}'", event),
+        }
+    }
+    config
+}
+
+/// Pins a `--doh <url>` endpoint to bootstrap IPs so reaching the DoH
+/// resolver itself never touches the system resolver: if the URL's host is
+/// already a literal IP (the common case, e.g. `https://1.1.1.1/dns-query`)
+/// that IP is used directly, otherwise it's resolved once through the
+/// system resolver as the one plaintext lookup DoH can't itself avoid.
+fn resolve_doh_bootstrap(doh_url: &str) -> Option<Vec<IpAddr>> {
+    let host = reqwest::Url::parse(doh_url).ok().and_then(|u| u.host_str().map(str::to_string))?;
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Some(vec![ip]);
+    }
+
+    match (host.as_str(), 443u16).to_socket_addrs() {
+        Ok(addrs) => Some(addrs.map(|a| a.ip()).collect()),
+        Err(e) => {
+            warn!("Impossible de résoudre l'hôte du résolveur DoH '{}': {}", host, e);
+            None
+        }
+    }
+}
+
+/// Reads one line from stdin, trimmed, falling back to `default` if empty.
+fn prompt(question: &str, default: &str) -> String {
+    print!("{} [{}]: ", question, default);
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line).is_err() {
+        return default.to_string();
+    }
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        default.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Prompts for a numbered choice among `options`, returning its index.
+/// Falls back to `default_index` on blank input or an out-of-range answer.
+fn prompt_choice(question: &str, options: &[&str], default_index: usize) -> usize {
+    println!("{}", question);
+    for (i, option) in options.iter().enumerate() {
+        let marker = if i == default_index { "*" } else { " " };
+        println!("  {} {}) {}", marker, i + 1, option);
+    }
+    let answer = prompt("Choix", &(default_index + 1).to_string());
+    answer
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n >= 1 && *n <= options.len())
+        .map(|n| n - 1)
+        .unwrap_or(default_index)
+}
+
+/// Interactive `config wizard`: prompts for the fields `SelectionContext`
+/// and `RemoteConnect` need, then saves them as a named `Profile`. Mirrors
+/// how established VPN clients bootstrap first-time users.
+fn run_config_wizard(name: &str) -> anyhow::Result<()> {
+    println!("🧙 Assistant de configuration WorldVPN — profil '{}'", name);
+
+    let country = prompt("Pays (code ISO)", "FR");
+    let api = prompt("URL de l'API", "http://127.0.0.1:3000");
+    let proto = prompt("Protocole préféré (wireguard/shadowsocks/ovpn/ikev2/hysteria/trojan/v2ray)", "wireguard");
+
+    let firewall_profile = match prompt_choice(
+        "Profil de pare-feu",
+        &["Ouvert", "Résidentiel", "Entreprise", "Censure nationale"],
+        1,
+    ) {
+        0 => FirewallProfile::Open,
+        2 => FirewallProfile::Corporate,
+        3 => FirewallProfile::NationalCensorship,
+        _ => FirewallProfile::Residential,
+    };
+
+    let device_type = match prompt_choice("Type d'appareil", &["Ordinateur", "Mobile", "Autre"], 0) {
+        1 => DeviceType::Mobile,
+        2 => DeviceType::Other,
+        _ => DeviceType::Desktop,
+    };
+
+    let user = prompt("Nom d'utilisateur", "user_cli");
+
+    let profile = vpn_core::profile::Profile {
+        api,
+        user,
+        proto,
+        country,
+        firewall_profile,
+        device_type,
+    };
+
+    profile.save(name)?;
+    println!("✅ Profil '{}' enregistré. Utilisez --profile {} pour le charger.", name, name);
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialisation logs
@@ -66,7 +260,38 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Select { country, censored, mobile, battery } => {
+        Commands::Select { country, censored, mobile, battery, profile } => {
+            let loaded_profile = match &profile {
+                Some(name) => match vpn_core::profile::Profile::load(name) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        println!("❌ Impossible de charger le profil '{}': {}", name, e);
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+
+            let country = country
+                .or_else(|| loaded_profile.as_ref().map(|p| p.country.clone()))
+                .unwrap_or_else(|| "FR".to_string());
+
+            let firewall_profile = if censored {
+                FirewallProfile::Corporate
+            } else if let Some(ref p) = loaded_profile {
+                p.firewall_profile
+            } else {
+                FirewallProfile::Residential
+            };
+
+            let device_type = if mobile {
+                DeviceType::Mobile
+            } else if let Some(ref p) = loaded_profile {
+                p.device_type
+            } else {
+                DeviceType::Desktop
+            };
+
             let mut ctx = SelectionContext {
                 user_country: country,
                 network_quality: NetworkQuality {
@@ -75,8 +300,8 @@ async fn main() -> anyhow::Result<()> {
                     bandwidth_mbps: 100.0,
                     stability: 1.0,
                 },
-                firewall_profile: if censored { FirewallProfile::Corporate } else { FirewallProfile::Residential }, // Correction enum names
-                device_type: if mobile { DeviceType::Mobile } else { DeviceType::Desktop },
+                firewall_profile,
+                device_type,
                 use_case: UseCase::Browsing,
                 battery_level: Some(battery.unwrap_or(1.0)),
             };
@@ -96,7 +321,87 @@ async fn main() -> anyhow::Result<()> {
         Commands::Connect { proto, server } => {
             println!("⚠️ Mode simulation locale uniquement.");
         }
-        Commands::RemoteConnect { api, user, proto } => {
+        Commands::Config { action } => match action {
+            ConfigAction::Wizard { name } => run_config_wizard(&name)?,
+        },
+        Commands::ImportLink { uri, socks_port } => {
+            const SHARE_SCHEMES: [&str; 5] = ["vless://", "trojan://", "vmess://", "hysteria2://", "hy2://"];
+            let is_single_link = SHARE_SCHEMES.iter().any(|scheme| uri.starts_with(scheme));
+
+            let configs = if is_single_link {
+                vpn_core::share_uri::parse_share_uri(&uri).map(|c| vec![c])
+            } else {
+                vpn_core::share_uri::parse_subscription(&uri)
+            };
+
+            let config = match configs {
+                Ok(configs) => match configs.into_iter().next() {
+                    Some(config) => config,
+                    None => {
+                        println!("❌ Aucun lien reconnu dans la subscription fournie");
+                        return Ok(());
+                    }
+                },
+                Err(e) => {
+                    println!("❌ Lien de partage invalide: {}", e);
+                    return Ok(());
+                }
+            };
+
+            println!("🌍 Connexion directe via lien de partage ({:?}) vers {}", config.protocol, config.server_addr);
+
+            let mut tunnel: Box<dyn VpnTunnel> = match config.protocol {
+                VpnProtocol::Hysteria2 => Box::new(vpn_core::hysteria::HysteriaTunnel::new()),
+                VpnProtocol::Trojan => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::Trojan)),
+                VpnProtocol::VLESS => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::VLESS)),
+                VpnProtocol::VMess => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::VMess)),
+                _ => Box::new(WireGuardTunnel::new()),
+            };
+
+            match tunnel.connect(&config).await {
+                Ok(handle) => {
+                    println!("✅ TUNNEL ÉTABLI avec succès ! Interface locale : {}", handle.assigned_ip);
+
+                    let tunnel = std::sync::Arc::new(tokio::sync::Mutex::new(tunnel));
+                    let listen_addr: SocketAddr = SocketAddr::from(([127, 0, 0, 1], socks_port));
+                    let proxy = vpn_core::socks5_proxy::Socks5Proxy::new(listen_addr, tunnel.clone(), None);
+                    match proxy.start().await {
+                        Ok(()) => println!("   🚀 Proxy SOCKS5 local actif sur le port {}", socks_port),
+                        Err(e) => println!("⚠️  Impossible de démarrer le proxy SOCKS5: {}", e),
+                    }
+
+                    println!("⏳ Tunnel actif... (Ctrl+C pour arrêter)");
+                    let _ = tokio::signal::ctrl_c().await;
+
+                    tunnel.lock().await.disconnect().await?;
+                }
+                Err(e) => println!("❌ Erreur Tunnel: {}", e),
+            }
+        }
+        Commands::RemoteConnect { api, user, proto, profile, hooks, transport, ws_path, ws_host, udp, socks_port, servers, reconnect, doh } => {
+            let loaded_profile = match &profile {
+                Some(name) => match vpn_core::profile::Profile::load(name) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        println!("❌ Impossible de charger le profil '{}': {}", name, e);
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+
+            let api = api
+                .or_else(|| loaded_profile.as_ref().map(|p| p.api.clone()))
+                .unwrap_or_else(|| "http://127.0.0.1:3000".to_string());
+            let user = user
+                .or_else(|| loaded_profile.as_ref().map(|p| p.user.clone()))
+                .unwrap_or_else(|| "user_cli".to_string());
+            let proto = proto
+                .or_else(|| loaded_profile.as_ref().map(|p| p.proto.clone()))
+                .unwrap_or_else(|| "wireguard".to_string());
+
+            let hook_config = build_hook_config(&hooks);
+
             let protocol = match proto.to_lowercase().as_str() {
                 "wg" | "wireguard" => VpnProtocol::WireGuard,
                 "ss" | "shadowsocks" => VpnProtocol::Shadowsocks,
@@ -116,8 +421,27 @@ async fn main() -> anyhow::Result<()> {
             
             // 1. Login pour obtenir le JWT
             println!("🔐 Authentification...");
-            let client = vpn_core::client::VpnApiClient::new(api.clone());
-            
+            let client_result = match &doh {
+                Some(doh_url) => match resolve_doh_bootstrap(doh_url) {
+                    Some(bootstrap_ips) => {
+                        println!("   🕵️ Résolution DNS-over-HTTPS activée ({})", doh_url);
+                        vpn_core::client::VpnApiClient::with_doh(api.clone(), doh_url.clone(), bootstrap_ips)
+                    }
+                    None => {
+                        warn!("DoH indisponible, retour au résolveur système pour l'API");
+                        vpn_core::client::VpnApiClient::new(api.clone())
+                    }
+                },
+                None => vpn_core::client::VpnApiClient::new(api.clone()),
+            };
+            let client = match client_result {
+                Ok(c) => c,
+                Err(e) => {
+                    println!("❌ Impossible d'initialiser le client API: {}", e);
+                    return Ok(());
+                }
+            };
+
             let login_response = match client.login(user.clone(), user.clone()).await {
                 Ok(r) => r,
                 Err(e) => {
@@ -131,18 +455,26 @@ async fn main() -> anyhow::Result<()> {
             // 2. Connexion VPN avec le token
             println!("\n🔌 Demande de connexion VPN...");
             // Initialisation de la session
-            let session = match client.connect(
+            let connect_result = match client.connect(
                 protocol,
-                user, 
+                user,
                 Some("pubkey_placeholder".into()),
-                &login_response.token
+                &login_response.token,
+                &login_response.refresh_token,
             ).await {
-                Ok(s) => s,
+                Ok(r) => r,
                 Err(e) => {
                     println!("❌ Erreur API: {}", e);
                     return Ok(());
                 }
             };
+            if connect_result.renewed_tokens.is_some() {
+                // TODO: persister le nouveau refresh token dans le profil utilisateur une
+                // fois ce flux CLI de démo capable d'écrire son fichier de profil ;
+                // pour l'instant le token d'accès rafraîchi couvre déjà cette session.
+                println!("🔄 Token d'accès renouvelé (refresh token précédent désormais invalide)");
+            }
+            let session = connect_result.info;
 
             println!("🔑 Session obtenue ! ID: {}", session.session_id);
             println!("   🎯 Endpoint: {}", session.server_endpoint);
@@ -151,7 +483,49 @@ async fn main() -> anyhow::Result<()> {
             }
 
             // 3. Initialisation du Tunnel
-            let server_addr: SocketAddr = session.server_endpoint.parse().expect("Adresse invalide");
+            // `server_endpoint` est déjà une IP:port dans ce flux de démo,
+            // mais un déploiement réel peut renvoyer un nom d'hôte ; dans ce
+            // cas la résolution passe par le même résolveur DoH que l'API
+            // plutôt que par le résolveur système, pour ne jamais révéler
+            // l'adresse du serveur VPN en clair.
+            let server_addr: SocketAddr = match session.server_endpoint.parse::<SocketAddr>() {
+                Ok(addr) => addr,
+                Err(_) => {
+                    let Some((host, port)) = session.server_endpoint.rsplit_once(':') else {
+                        println!("❌ Endpoint serveur invalide: {}", session.server_endpoint);
+                        return Ok(());
+                    };
+                    let Ok(port) = port.parse::<u16>() else {
+                        println!("❌ Port d'endpoint invalide: {}", session.server_endpoint);
+                        return Ok(());
+                    };
+                    let Some(doh_url) = &doh else {
+                        println!(
+                            "❌ Endpoint serveur '{}' n'est pas une IP littérale (utilisez --doh pour résoudre les noms d'hôte)",
+                            session.server_endpoint
+                        );
+                        return Ok(());
+                    };
+                    let Some(bootstrap_ips) = resolve_doh_bootstrap(doh_url) else {
+                        println!("❌ DoH indisponible pour résoudre l'endpoint serveur '{}'", host);
+                        return Ok(());
+                    };
+                    let resolver = match vpn_core::doh::DohResolver::new(doh_url.clone(), bootstrap_ips) {
+                        Ok(resolver) => resolver,
+                        Err(e) => {
+                            println!("❌ Impossible d'initialiser le résolveur DoH: {}", e);
+                            return Ok(());
+                        }
+                    };
+                    match resolver.resolve_socket_addr(host, port).await {
+                        Ok(addr) => addr,
+                        Err(e) => {
+                            println!("❌ Résolution DoH de '{}' échouée: {}", host, e);
+                            return Ok(());
+                        }
+                    }
+                }
+            };
 
             // Configuration Credentials selon protocole
             let credentials = match protocol {
@@ -169,52 +543,213 @@ async fn main() -> anyhow::Result<()> {
                 }
             };
 
+            let supports_stream_transport = matches!(
+                protocol,
+                VpnProtocol::WireGuard
+                    | VpnProtocol::WireGuardObfuscated
+                    | VpnProtocol::Hysteria2
+                    | VpnProtocol::Trojan
+                    | VpnProtocol::VLESS
+            );
+            let stream_transport = match transport.to_lowercase().as_str() {
+                "wss" | "ws" if supports_stream_transport => {
+                    println!("   🥸 Transport WSS activé (Host: {})", ws_host.as_deref().unwrap_or(&server_addr.ip().to_string()));
+                    vpn_core::tunnel::StreamTransport::Ws {
+                        path: ws_path,
+                        host: ws_host.unwrap_or_else(|| server_addr.ip().to_string()),
+                    }
+                }
+                "wss" | "ws" => {
+                    warn!("Transport WSS non supporté pour {}, retour au transport direct", protocol.name());
+                    Default::default()
+                }
+                _ => Default::default(),
+            };
+
             let config = ConnectionConfig {
                 protocol,
                 server_addr,
                 credentials,
                 timeout: Duration::from_secs(10),
+                transport: vpn_core::obfuscation::TransportMode::Direct,
+                cipher: None,
+                auth_digest: None,
+                dns_servers: Vec::new(),
+                routes: Vec::new(),
+                redirect_gateway: true,
+                block_outside_dns: false,
+                reconnect_policy: Default::default(),
+                hooks: hook_config,
+                kill_switch: std::env::var("WORLDVPN_KILL_SWITCH").as_deref() == Ok("true"),
+                stream_transport,
+                sni: None,
+                alpn: Vec::new(),
+                dns: Default::default(),
+                allow_insecure_tls: false,
+                mux: Default::default(),
             };
 
-            // Création du tunnel abstrait
-            // Instanciation tunnel
-            let mut tunnel: Box<dyn VpnTunnel> = match protocol {
-                VpnProtocol::Shadowsocks => Box::new(vpn_core::shadowsocks::ShadowsocksTunnel::new()),
-                VpnProtocol::WireGuard | VpnProtocol::WireGuardObfuscated => Box::new(WireGuardTunnel::new()),
-                VpnProtocol::OpenVpnTcp | VpnProtocol::OpenVpnUdp => Box::new(vpn_core::openvpn::OpenVpnTunnel::new()),
-                VpnProtocol::IKEv2 => Box::new(vpn_core::ikev2::IKEv2Tunnel::new()),
-                VpnProtocol::Hysteria2 => Box::new(vpn_core::hysteria::HysteriaTunnel::new()),
-                VpnProtocol::Trojan => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::Trojan)),
-                VpnProtocol::VLESS => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::VLESS)),
-                _ => Box::new(WireGuardTunnel::new()),
+            // Pool d'endpoints candidats : celui renvoyé par l'API d'abord,
+            // puis tout --server supplémentaire, essayés en round-robin si
+            // la connexion échoue.
+            let mut endpoints = vec![server_addr];
+            for raw in &servers {
+                match raw.parse::<SocketAddr>() {
+                    Ok(addr) => endpoints.push(addr),
+                    Err(e) => warn!("--server '{}' ignoré, adresse invalide: {}", raw, e),
+                }
+            }
+            let mut endpoint_pool = vpn_core::reconnect::EndpointPool::new(endpoints);
+            let backoff = vpn_core::reconnect::BackoffPolicy::default();
+            let mut consecutive_failures: u32 = 0;
+
+            let hook_ctx = HookContext {
+                protocol: Some(protocol.name().to_string()),
+                server_addr: Some(session.server_endpoint.clone()),
+                session_id: Some(session.session_id.clone()),
+                ..Default::default()
             };
-            println!("\n🔌 Initialisation du tunnel {}...", protocol.name());
-            
-            match tunnel.connect(&config).await {
-                Ok(handle) => {
-                    println!("✅ TUNNEL ÉTABLI avec succès !");
-                    
-                    if protocol == VpnProtocol::Shadowsocks {
-                         println!("   🚀 Proxy SOCKS5 local actif sur le port 1086");
-                         println!("   Configurez votre navigateur/système pour utiliser 127.0.0.1:1086");
-                    } else {
-                         println!("   • Interface locale : {}", handle.assigned_ip);
-                    }
-                    
-                    if let Err(e) = tunnel.send(b"Ping").await {
-                        // En mode SOCKS, send n'envoie rien (simulation)
-                        if protocol != VpnProtocol::Shadowsocks {
-                             println!("⚠️  Note: L'envoi a échoué (normal sans serveur réel)");
+
+            'session: loop {
+                let endpoint = endpoint_pool.next();
+                let mut config = config.clone();
+                config.server_addr = endpoint;
+                let mut hook_ctx = hook_ctx.clone();
+
+                // Création du tunnel abstrait
+                // Instanciation tunnel
+                let mut tunnel: Box<dyn VpnTunnel> = match protocol {
+                    VpnProtocol::Shadowsocks => Box::new(vpn_core::shadowsocks::ShadowsocksTunnel::new()),
+                    VpnProtocol::WireGuard | VpnProtocol::WireGuardObfuscated => Box::new(WireGuardTunnel::new()),
+                    VpnProtocol::OpenVpnTcp | VpnProtocol::OpenVpnUdp => Box::new(vpn_core::openvpn::OpenVpnTunnel::new()),
+                    VpnProtocol::IKEv2 => Box::new(vpn_core::ikev2::IKEv2Tunnel::new()),
+                    VpnProtocol::Hysteria2 => Box::new(vpn_core::hysteria::HysteriaTunnel::new()),
+                    VpnProtocol::Trojan => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::Trojan)),
+                    VpnProtocol::VLESS => Box::new(vpn_core::v2ray::V2RayTunnel::new(VpnProtocol::VLESS)),
+                    _ => Box::new(WireGuardTunnel::new()),
+                };
+                println!("\n🔌 Initialisation du tunnel {} vers {}...", protocol.name(), endpoint);
+
+                match tunnel.connect(&config).await {
+                    Ok(handle) => {
+                        println!("✅ TUNNEL ÉTABLI avec succès !");
+                        consecutive_failures = 0;
+
+                        hook_ctx.assigned_ip = Some(handle.assigned_ip.to_string());
+                        if let Err(e) = config.hooks.run(HookEvent::Connected, &hook_ctx).await {
+                            warn!("on-connect hook failed: {}", e);
+                        }
+
+                        // Protocols that already spawn their own local SOCKS5
+                        // port via an external binary (sslocal/hysteria/v2ray)
+                        // don't need the generic proxy layered on top of them.
+                        let has_native_socks5 = matches!(
+                            protocol,
+                            VpnProtocol::Shadowsocks | VpnProtocol::Hysteria2 | VpnProtocol::Trojan | VpnProtocol::VLESS
+                        );
+
+                        if protocol == VpnProtocol::Shadowsocks {
+                             println!("   🚀 Proxy SOCKS5 local actif sur le port 1086");
+                             println!("   Configurez votre navigateur/système pour utiliser 127.0.0.1:1086");
+                        } else {
+                             println!("   • Interface locale : {}", handle.assigned_ip);
+                        }
+
+                        let tunnel = std::sync::Arc::new(tokio::sync::Mutex::new(tunnel));
+                        let socks5 = if has_native_socks5 {
+                            None
+                        } else {
+                            let listen_addr: SocketAddr = SocketAddr::from(([127, 0, 0, 1], socks_port));
+                            let proxy = vpn_core::socks5_proxy::Socks5Proxy::new(listen_addr, tunnel.clone(), None);
+                            match proxy.start().await {
+                                Ok(()) => {
+                                    println!("   🚀 Proxy SOCKS5 local actif sur le port {}", socks_port);
+                                    if udp {
+                                        println!("   📡 UDP ASSOCIATE disponible (QUIC/DNS/WebRTC via le tunnel)");
+                                    }
+                                    Some(proxy)
+                                }
+                                Err(e) => {
+                                    println!("⚠️  Impossible de démarrer le proxy SOCKS5: {}", e);
+                                    None
+                                }
+                            }
+                        };
+
+                        if let Err(e) = tunnel.lock().await.send(b"Ping").await {
+                            // En mode SOCKS, send n'envoie rien (simulation)
+                            if protocol != VpnProtocol::Shadowsocks {
+                                 println!("⚠️  Note: L'envoi a échoué (normal sans serveur réel)");
+                            }
+                            let mut ctx = hook_ctx.clone();
+                            ctx.error_message = Some(e.to_string());
+                            if let Err(e) = config.hooks.run(HookEvent::Error, &ctx).await {
+                                warn!("on-error hook failed: {}", e);
+                            }
+                        }
+
+                        // En mode `--reconnect`, le tunnel reste actif jusqu'à
+                        // Ctrl+C ou un échec du health-check, auquel cas on
+                        // reconnecte sur l'endpoint suivant du pool. Sans ce
+                        // drapeau, on garde la démo d'origine (10s puis stop).
+                        let lost_connection = if reconnect {
+                            println!("⏳ Tunnel actif... (Ctrl+C pour arrêter, reconnexion automatique activée)");
+                            tokio::select! {
+                                _ = tokio::signal::ctrl_c() => false,
+                                error = async {
+                                    loop {
+                                        tokio::time::sleep(Duration::from_secs(5)).await;
+                                        if let Err(e) = tunnel.lock().await.ping().await {
+                                            return e;
+                                        }
+                                    }
+                                } => {
+                                    warn!("Health-check du tunnel en échec: {}", error);
+                                    true
+                                }
+                            }
+                        } else {
+                            println!("⏳ Tunnel actif... (Ctrl+C pour arrêter)");
+                            tokio::time::sleep(Duration::from_secs(10)).await;
+                            false
+                        };
+
+                        if let Some(proxy) = socks5 {
+                            proxy.stop().await;
+                        }
+
+                        if let Err(e) = config.hooks.run(HookEvent::Disconnecting, &hook_ctx).await {
+                            warn!("pre-disconnect hook failed: {}", e);
+                        }
+                        tunnel.lock().await.disconnect().await?;
+                        if let Err(e) = config.hooks.run(HookEvent::Disconnected, &hook_ctx).await {
+                            warn!("on-disconnect hook failed: {}", e);
+                        }
+
+                        if lost_connection {
+                            info!("Reconnexion immédiate sur l'endpoint suivant ({} candidats)...", endpoint_pool.len());
+                            continue 'session;
+                        }
+                        break 'session;
+                    },
+                    Err(e) => {
+                        println!("❌ Erreur Tunnel: {}", e);
+                        let mut ctx = hook_ctx.clone();
+                        ctx.error_message = Some(e.to_string());
+                        if let Err(e) = config.hooks.run(HookEvent::Error, &ctx).await {
+                            warn!("on-error hook failed: {}", e);
                         }
+
+                        if !reconnect {
+                            break 'session;
+                        }
+
+                        let delay = backoff.delay_for(consecutive_failures);
+                        consecutive_failures += 1;
+                        warn!("Reconnexion dans {:?} (tentative {})...", delay, consecutive_failures);
+                        tokio::time::sleep(delay).await;
                     }
-                    
-                    // Maintenir ouvert quelques secondes pour la démo
-                    println!("⏳ Tunnel actif... (Ctrl+C pour arrêter)");
-                    tokio::time::sleep(Duration::from_secs(10)).await;
-                    
-                    tunnel.disconnect().await?;
-                },
-                Err(e) => println!("❌ Erreur Tunnel: {}", e),
+                }
             }
         }
     }
@@ -5,6 +5,7 @@ use serde::{Serialize, Deserialize};
 // Shared state to track VPN status across the app
 struct AppState {
     vpn_status: Mutex<VpnStatus>,
+    hooks: vpn_core::hooks::HookConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -53,6 +54,16 @@ async fn connect_vpn(
         status.state = ConnectionState::Connecting;
     }
 
+    let connecting_ctx = vpn_core::hooks::HookContext {
+        protocol: Some(protocol.clone()),
+        ..Default::default()
+    };
+    if let Err(e) = state.hooks.run(vpn_core::hooks::HookEvent::Connecting, &connecting_ctx).await {
+        let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
+        status.state = ConnectionState::Error(e.to_string());
+        return Err(format!("on_connecting hook aborted the connection: {}", e));
+    }
+
     // 2. Platform Specific logic
     #[cfg(target_os = "windows")]
     {
@@ -80,26 +91,58 @@ async fn connect_vpn(
     tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
 
     // 4. Update state to Connected
-    let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
-    status.state = ConnectionState::Connected;
-    status.current_ip = Some(format!("10.8.0.{}", rand::random::<u8>()));
-    status.protocol = Some(protocol);
-    status.connected_since = Some(chrono::Utc::now().timestamp());
+    let connected_status = {
+        let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
+        status.state = ConnectionState::Connected;
+        status.current_ip = Some(format!("10.8.0.{}", rand::random::<u8>()));
+        status.protocol = Some(protocol);
+        status.connected_since = Some(chrono::Utc::now().timestamp());
+        status.clone()
+    };
+
+    let connected_ctx = vpn_core::hooks::HookContext {
+        protocol: connected_status.protocol.clone(),
+        assigned_ip: connected_status.current_ip.clone(),
+        ..Default::default()
+    };
+    if let Err(e) = state.hooks.run(vpn_core::hooks::HookEvent::Connected, &connected_ctx).await {
+        let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
+        status.state = ConnectionState::Error(e.to_string());
+        return Err(format!("on_connect hook failed: {}", e));
+    }
 
-    Ok(status.clone())
+    Ok(connected_status)
 }
 
 #[tauri::command]
 async fn disconnect_vpn(state: State<'_, AppState>) -> Result<VpnStatus, String> {
-    let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
-    
-    // Simulate Disconnection
-    status.state = ConnectionState::Disconnected;
-    status.current_ip = None;
-    status.protocol = None;
-    status.connected_since = None;
-
-    Ok(status.clone())
+    let disconnecting_ctx = {
+        let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
+        status.state = ConnectionState::Disconnecting;
+        vpn_core::hooks::HookContext {
+            protocol: status.protocol.clone(),
+            assigned_ip: status.current_ip.clone(),
+            bytes_sent: status.bytes_up,
+            bytes_received: status.bytes_down,
+            ..Default::default()
+        }
+    };
+    let _ = state.hooks.run(vpn_core::hooks::HookEvent::Disconnecting, &disconnecting_ctx).await;
+
+    let status = {
+        let mut status = state.vpn_status.lock().map_err(|_| "Failed to lock state")?;
+
+        // Simulate Disconnection
+        status.state = ConnectionState::Disconnected;
+        status.current_ip = None;
+        status.protocol = None;
+        status.connected_since = None;
+        status.clone()
+    };
+
+    let _ = state.hooks.run(vpn_core::hooks::HookEvent::Disconnected, &disconnecting_ctx).await;
+
+    Ok(status)
 }
 
 #[tauri::command]
@@ -131,6 +174,7 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .manage(AppState {
             vpn_status: Mutex::new(VpnStatus::default()),
+            hooks: vpn_core::hooks::HookConfig::from_env(),
         })
         .invoke_handler(tauri::generate_handler![
             connect_vpn, 